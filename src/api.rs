@@ -13,6 +13,7 @@ in a thread-safe way, a requirement captured in the [CredentialStore] and
 use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use super::{Entry, Error, Result};
 
@@ -43,6 +44,96 @@ pub trait CredentialApi {
     /// whether to recreate a deleted credential or to fail with a NoEntry error.
     fn set_secret(&self, secret: &[u8]) -> Result<()>;
 
+    /// Set the underlying credential's protected data, and report whether
+    /// a credential was created or an existing one updated.
+    ///
+    /// The error cases are the same as for [set_secret](CredentialApi::set_secret).
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which calls [set_secret](CredentialApi::set_secret) and returns
+    /// [SetOutcome::Unspecified]. Stores that know which happened should override it.
+    fn set_secret_reporting(&self, secret: &[u8]) -> Result<SetOutcome> {
+        self.set_secret(secret)?;
+        Ok(SetOutcome::Unspecified)
+    }
+
+    /// Set the underlying credential's protected data only if it differs from the given data.
+    ///
+    /// Returns whether the data was written. If there is no matching credential,
+    /// the data is always written (and a credential created) as with
+    /// [set_secret](CredentialApi::set_secret), whose error cases also apply.
+    ///
+    /// The default implementation reads the current data and then writes the
+    /// new data if necessary, so another writer may intervene between the two.
+    /// Stores that can compare and write in one step should override this method.
+    fn set_secret_if_changed(&self, secret: &[u8]) -> Result<bool> {
+        match self.get_secret() {
+            Ok(current) if current == secret => Ok(false),
+            Ok(_) | Err(Error::NoEntry) => {
+                self.set_secret(secret)?;
+                Ok(true)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the underlying credential's protected data if it is what's expected.
+    ///
+    /// An `expected` value of `None` means that there must be no matching
+    /// credential, and a `new` value of `None` means that the matching
+    /// credential is to be deleted. Returns whether the current data
+    /// was as expected, in which case the change has been made.
+    ///
+    /// When the current data is as expected, the error cases are those of
+    /// [set_secret](CredentialApi::set_secret) or
+    /// [delete_credential](CredentialApi::delete_credential), whichever applies.
+    ///
+    /// The default implementation reads the current data and then makes
+    /// the change if appropriate, so another writer may intervene between the two.
+    /// Stores that can compare and write in one step should override this method.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        let current = match self.get_secret() {
+            Ok(current) => Some(current),
+            Err(Error::NoEntry) => None,
+            Err(e) => return Err(e),
+        };
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+        match new {
+            Some(new) => self.set_secret(new)?,
+            None if current.is_some() => self.delete_credential()?,
+            None => {}
+        }
+        Ok(true)
+    }
+
+    /// Treat the underlying credential's protected data as a counter and add to it.
+    ///
+    /// The data is interpreted as a big-endian `u64`. The counter is incremented
+    /// by `delta`, saved, and its new value returned. If there is no matching
+    /// credential, one is created with a counter value of `delta`.
+    ///
+    /// - If the data is not 8 bytes long,
+    ///   return a [BadDataFormat](Error::BadDataFormat) error.
+    /// - If the addition would overflow,
+    ///   return an [Invalid](Error::Invalid) error.
+    ///
+    /// Other error cases are the same as with [set_secret](CredentialApi::set_secret).
+    ///
+    /// The default implementation reads the counter and then writes it,
+    /// so concurrent increments may be lost. Stores that can update
+    /// the data in one step should override this method.
+    fn increment_secret_u64(&self, delta: u64) -> Result<u64> {
+        let value = match self.get_secret() {
+            Ok(secret) => increment_counter(&secret, delta)?,
+            Err(Error::NoEntry) => delta,
+            Err(e) => return Err(e),
+        };
+        self.set_secret(&value.to_be_bytes())?;
+        Ok(value)
+    }
+
     /// Retrieve the protected data as a UTF-8 string from the underlying credential.
     ///
     /// This method has a default implementation in terms of
@@ -65,6 +156,41 @@ pub trait CredentialApi {
     /// - Otherwise, return the value of the single, matching credential.
     fn get_secret(&self) -> Result<Vec<u8>>;
 
+    /// Call the given function with a borrowed view of the protected data.
+    ///
+    /// The expected error and success cases are the same as with
+    /// [get_secret](CredentialApi::get_secret), which see. On success,
+    /// the function must have been called exactly once.
+    ///
+    /// The default implementation calls `get_secret` and lends the result
+    /// to the function. Credential stores which can lend out their
+    /// data without copying it should override this method.
+    fn with_secret(&self, f: &mut dyn FnMut(&[u8])) -> Result<()> {
+        let secret = self.get_secret()?;
+        f(&secret);
+        Ok(())
+    }
+
+    /// Tell whether the underlying credential exists but has empty protected data.
+    ///
+    /// The expected error cases are the same as with
+    /// [get_secret](CredentialApi::get_secret), which see.
+    /// In particular, a missing credential produces a [NoEntry](Error::NoEntry)
+    /// error rather than `true`, so callers can distinguish a credential
+    /// whose secret is empty from one that doesn't exist.
+    ///
+    /// Stores that can't save empty secrets should
+    /// reject them in [set_secret](CredentialApi::set_secret) with an
+    /// [Invalid](Error::Invalid) error, so this never returns `true` for them.
+    ///
+    /// The default implementation inspects the secret via
+    /// [with_secret](CredentialApi::with_secret).
+    fn has_empty_secret(&self) -> Result<bool> {
+        let mut empty = false;
+        self.with_secret(&mut |secret| empty = secret.is_empty())?;
+        Ok(empty)
+    }
+
     /// Return any store-specific decorations on this entry's credential.
     ///
     /// The expected error and success cases are the same as with
@@ -81,6 +207,56 @@ pub trait CredentialApi {
         Ok(HashMap::new())
     }
 
+    /// Return the secret and the attributes of the underlying credential together.
+    ///
+    /// The expected error and success cases are the same as with
+    /// [get_attributes](CredentialApi::get_attributes), which see.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which calls [get_secret](CredentialApi::get_secret) and then
+    /// [get_attributes](CredentialApi::get_attributes), so the credential can
+    /// change in between. Stores that can read both at once should override it.
+    fn get_secret_and_attributes(&self) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        let secret = self.get_secret()?;
+        Ok((secret, self.get_attributes()?))
+    }
+
+    /// Return the names of the store-specific decorations on this entry's credential.
+    ///
+    /// The expected error and success cases are the same as with
+    /// [get_attributes](CredentialApi::get_attributes), which see.
+    ///
+    /// The default implementation returns the keys of the map returned by
+    /// `get_attributes`. Credential stores which can list attribute names
+    /// without fetching their values should override this method.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        Ok(self.get_attributes()?.into_keys().collect())
+    }
+
+    /// Return the time at which this entry's credential was created.
+    ///
+    /// The expected error cases are the same as with
+    /// [get_attributes](CredentialApi::get_attributes), which see.
+    /// Returns `None` if the store doesn't record creation times.
+    ///
+    /// The default implementation returns `None` without checking
+    /// that the credential exists.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        Ok(None)
+    }
+
+    /// Return the time at which this entry's credential was last modified.
+    ///
+    /// The expected error cases are the same as with
+    /// [get_attributes](CredentialApi::get_attributes), which see.
+    /// Returns `None` if the store doesn't record modification times.
+    ///
+    /// The default implementation returns `None` without checking
+    /// that the credential exists.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        Ok(None)
+    }
+
     /// Update the secure store attributes on this entry's credential.
     ///
     /// If the user supplies any attributes that cannot be updated,
@@ -106,6 +282,31 @@ pub trait CredentialApi {
     /// return an [Ambiguous](Error::Ambiguous) error.
     fn delete_credential(&self) -> Result<()>;
 
+    /// Move the underlying credential to a new service and user.
+    ///
+    /// The credential keeps its secret and attributes. Afterwards,
+    /// credentials for the old service and user, including wrappers
+    /// for the moved credential, no longer find it.
+    ///
+    /// If the underlying credential doesn't exist, return
+    /// a [NoEntry](Error::NoEntry) error.
+    ///
+    /// If there is more than one matching credential,
+    /// return an [Ambiguous](Error::Ambiguous) error.
+    ///
+    /// If there is already a credential for the new service and user,
+    /// a store that can hold more than one credential for them may add
+    /// the moved credential to it; otherwise, return an
+    /// [Invalid](Error::Invalid) error without moving anything.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns a [NotSupportedByStore](Error::NotSupportedByStore) error.
+    fn rename(&self, _new_service: &str, _new_user: &str) -> Result<()> {
+        Err(Error::NotSupportedByStore(String::from(
+            "Credentials can't be renamed",
+        )))
+    }
+
     /// Return a wrapper for the underlying credential.
     ///
     /// If `self` is already a wrapper, return None.
@@ -117,9 +318,98 @@ pub trait CredentialApi {
     /// return an [Ambiguous](Error::Ambiguous) error.
     fn get_credential(&self) -> Result<Option<Arc<Credential>>>;
 
+    /// Return a wrapper for the primary credential matched by this credential.
+    ///
+    /// Stores that allow ambiguity decide which of the matching
+    /// credentials is the primary one, and they must always pick the
+    /// same one for the same set of credentials. If `self` is already a
+    /// wrapper for the primary credential, return None.
+    ///
+    /// If there is no matching credential, return
+    /// a [NoEntry](Error::NoEntry) error.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns the result of
+    /// [get_credential](CredentialApi::get_credential), which is right
+    /// for stores that never have more than one matching credential.
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        self.get_credential()
+    }
+
+    /// Tell whether the underlying credential exists, without reading its secret.
+    ///
+    /// If there is more than one matching credential,
+    /// return an [Ambiguous](Error::Ambiguous) error.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which calls [get_credential](CredentialApi::get_credential)
+    /// and maps a [NoEntry](Error::NoEntry) error to `false`.
+    /// Stores that can check for a credential more cheaply should override it.
+    fn exists(&self) -> Result<bool> {
+        match self.get_credential() {
+            Ok(_) => Ok(true),
+            Err(Error::NoEntry) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Tell whether `other` refers to the same underlying credential as this one.
+    ///
+    /// This compares identity, not content: two credentials with the same
+    /// secret and attributes are not the same credential.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns `false`, even when `other` is `self`.
+    /// Stores that can tell which credential they refer to should override it.
+    fn is_same_credential(&self, _other: &Credential) -> bool {
+        false
+    }
+
+    /// Return the store that built this credential, if it's known.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns `None`. Stores whose credentials keep
+    /// a reference to their store should override it.
+    fn get_store(&self) -> Option<Arc<CredentialStore>> {
+        None
+    }
+
+    /// Return the UUID of the one credential this wraps, if it's a
+    /// wrapper in a store whose credentials have UUIDs.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns `None`.
+    fn get_wrapped_uuid(&self) -> Option<String> {
+        None
+    }
+
     /// Return the `<service, user>` pair for this credential, if any.
     fn get_specifiers(&self) -> Option<(String, String)>;
 
+    /// Tell whether this credential is a specifier.
+    ///
+    /// A specifier names a credential by its `<service, user>` pair, and
+    /// setting its secret creates a matching credential if there isn't one.
+    /// An entry can be both a specifier and a [wrapper](CredentialApi::is_wrapper).
+    ///
+    /// The default implementation returns whether this credential
+    /// has [specifiers](CredentialApi::get_specifiers).
+    fn is_specifier(&self) -> bool {
+        self.get_specifiers().is_some()
+    }
+
+    /// Tell whether this credential is a wrapper.
+    ///
+    /// A wrapper refers to one existing credential, such as those returned by
+    /// [get_credential](CredentialApi::get_credential) and in
+    /// [Ambiguous](Error::Ambiguous) errors, and it never creates one.
+    ///
+    /// The default implementation returns `false`. Stores whose
+    /// credentials can be wrappers must override it.
+    fn is_wrapper(&self) -> bool {
+        false
+    }
+
     /// Return the inner credential object cast to [Any].
     ///
     /// This call is used to expose the Debug trait for credentials.
@@ -137,6 +427,27 @@ pub trait CredentialApi {
     }
 }
 
+/// Compare two byte strings in time that depends only on their lengths.
+///
+/// This is a helper for comparing secrets without revealing,
+/// through timing, how much of a candidate secret is right.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Add `delta` to the counter stored in `secret`.
+///
+/// This is a helper for implementations of
+/// [increment_secret_u64](CredentialApi::increment_secret_u64).
+pub fn increment_counter(secret: &[u8], delta: u64) -> Result<u64> {
+    super::error::decode_counter(secret)?
+        .checked_add(delta)
+        .ok_or_else(|| Error::Invalid("delta".to_string(), "overflows the counter".to_string()))
+}
+
 /// A thread-safe implementation of the [Credential API](CredentialApi).
 pub type Credential = dyn CredentialApi + Send + Sync;
 
@@ -168,6 +479,86 @@ pub enum CredentialPersistence {
     Unspecified,
 }
 
+/// What happened when a secret was set, returned from
+/// a credential's [set_secret_reporting](CredentialApi::set_secret_reporting) call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOutcome {
+    /// A new credential was created to hold the secret.
+    Created,
+    /// The secret of an existing credential was replaced.
+    Updated,
+    /// The store can't tell which happened.
+    Unspecified,
+}
+
+/// The kind of a credential store, returned from
+/// a credential store's [kind](CredentialStoreApi::kind) call.
+///
+/// This allows clients to special-case the stores built into this crate
+/// without comparing vendor strings. All other stores are of kind `Other`,
+/// with the store's vendor string as the value.
+///
+/// This enum may change even in minor and patch versions of the library, so it's
+/// marked as non-exhaustive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StoreKind {
+    /// The [mock](crate::mock) store
+    Mock,
+    /// The sample store (only built with the `sample` feature)
+    Sample,
+    /// Any other store, identified by its vendor
+    Other(String),
+}
+
+/// What a credential store can do, returned from
+/// a credential store's [capabilities](CredentialStoreApi::capabilities) call.
+///
+/// This lets clients adapt to the store they've been handed (for example,
+/// by hiding a search box) without probing it for
+/// [NotSupportedByStore](Error::NotSupportedByStore) errors.
+/// Store implementations can start from the default, which claims only
+/// what every store provides, and turn on what they support:
+/// ```
+/// # use keyring_core::api::Capabilities;
+/// let mut caps = Capabilities::default();
+/// caps.supports_search = true;
+/// ```
+///
+/// More capabilities may be added in later versions, so this
+/// can't be built with a struct expression outside this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The store implements [search](CredentialStoreApi::search).
+    pub supports_search: bool,
+    /// Credentials have attributes that can be
+    /// [updated](CredentialApi::update_attributes).
+    pub supports_attributes: bool,
+    /// There can be more than one credential for a service and user,
+    /// so operations can fail with an [Ambiguous](Error::Ambiguous) error.
+    pub supports_ambiguity: bool,
+    /// Credentials are written to disk, so they outlive the process.
+    pub persists_to_disk: bool,
+    /// Secrets can be arbitrary bytes, not just UTF-8 passwords.
+    pub supports_secret_bytes: bool,
+}
+
+impl Default for Capabilities {
+    /// Every store can hold arbitrary secret bytes, because
+    /// [set_secret](CredentialApi::set_secret) has no default implementation,
+    /// but nothing else can be assumed.
+    fn default() -> Self {
+        Capabilities {
+            supports_search: false,
+            supports_attributes: false,
+            supports_ambiguity: false,
+            persists_to_disk: false,
+            supports_secret_bytes: true,
+        }
+    }
+}
+
 /// The API that [credential stores](CredentialStore) implement.
 pub trait CredentialStoreApi {
     /// The name of the "vendor" that provides this store.
@@ -187,6 +578,25 @@ pub trait CredentialStoreApi {
     /// It's recommended that this include the version of the provider.
     fn id(&self) -> String;
 
+    /// The kind of this credential store.
+    ///
+    /// The default implementation returns [Other](StoreKind::Other)
+    /// with the store's vendor. Only the stores built into this crate
+    /// should override it.
+    fn kind(&self) -> StoreKind {
+        StoreKind::Other(self.vendor())
+    }
+
+    /// The vendors of the stores that hold this store's credentials.
+    ///
+    /// Stores that combine other stores should override this to return
+    /// the member vendors of each store they wrap, in order, so that
+    /// nested combinators report the leaf stores they are built from.
+    /// The default implementation returns just this store's vendor.
+    fn member_vendors(&self) -> Vec<String> {
+        vec![self.vendor()]
+    }
+
     /// Create an entry specified by the given service and user,
     /// perhaps with additional creation-time modifiers.
     ///
@@ -217,6 +627,72 @@ pub trait CredentialStoreApi {
         Err(Error::NotSupportedByStore(vendor))
     }
 
+    /// Run several searches at once.
+    ///
+    /// Returns the results of each search, in the order the specs were
+    /// given. If any spec is bad, the error for the first bad one is
+    /// returned, and none of the results.
+    ///
+    /// The default implementation calls [search](CredentialStoreApi::search)
+    /// for each spec in turn. Stores that can run all the searches
+    /// in one pass over their credentials should override this method.
+    fn search_multi(&self, specs: &[HashMap<&str, &str>]) -> Result<Vec<Vec<Entry>>> {
+        specs.iter().map(|spec| self.search(spec)).collect()
+    }
+
+    /// Set the secrets of many credentials, given as `(service, user, secret)`.
+    ///
+    /// Returns the result of setting each secret, in the order the items
+    /// were given, so a bad item doesn't keep the others from being set.
+    /// An error for the whole batch means that it failed after the
+    /// secrets were set, for example when the store was saved.
+    ///
+    /// The default implementation builds an entry for each item and sets
+    /// its secret. Stores that can write many credentials more cheaply
+    /// than one at a time should override this method.
+    fn set_many(&self, items: &[(String, String, Vec<u8>)]) -> Result<Vec<Result<()>>> {
+        Ok(items
+            .iter()
+            .map(|(service, user, secret)| self.build(service, user, None)?.set_secret(secret))
+            .collect())
+    }
+
+    /// Return wrappers for every credential in this store.
+    ///
+    /// Unlike a [search](CredentialStoreApi::search) with an empty spec,
+    /// whose meaning varies from store to store, this always returns
+    /// one entry for each credential, as [count](CredentialStoreApi::count)
+    /// would count them.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns a [NotSupportedByStore](Error::NotSupportedByStore) error.
+    fn all(&self) -> Result<Vec<Entry>> {
+        Err(Error::NotSupportedByStore(self.vendor()))
+    }
+
+    /// Return the number of credentials in this store.
+    ///
+    /// In stores that allow ambiguity, each of the credentials
+    /// for a service and user is counted.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns a [NotSupportedByStore](Error::NotSupportedByStore) error.
+    fn count(&self) -> Result<usize> {
+        Err(Error::NotSupportedByStore(self.vendor()))
+    }
+
+    /// Delete every credential in this store, returning how many were deleted.
+    ///
+    /// The credentials are counted as [count](CredentialStoreApi::count)
+    /// counts them. Stores that can should delete them all at once,
+    /// so that no other client sees some deleted and some not.
+    ///
+    /// For convenience, a default implementation of this method is
+    /// provided which returns a [NotSupportedByStore](Error::NotSupportedByStore) error.
+    fn clear(&self) -> Result<usize> {
+        Err(Error::NotSupportedByStore(self.vendor()))
+    }
+
     /// Return the inner store object cast to [Any].
     ///
     /// This call is used to expose the Debug trait for stores.
@@ -231,6 +707,16 @@ pub trait CredentialStoreApi {
         CredentialPersistence::UntilDelete
     }
 
+    /// What this store can do.
+    ///
+    /// The default implementation returns the
+    /// [default capabilities](Capabilities::default), which claim
+    /// only what every store provides. Stores should override it
+    /// to report what else they support.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
     /// The Debug trait call for the object.
     ///
     /// This is used to implement the Debug trait on this type; it
@@ -0,0 +1,479 @@
+/*!
+
+# Async API
+
+This module provides async versions of the credential and credential store
+traits, and an [AsyncEntry] for clients to use with them. It's only built
+if the `async` feature is specified.
+
+Most credential stores make blocking calls (over D-Bus, or into a platform
+keychain), so any existing store can be used through the async API by
+wrapping it in a [BlockingStore]. That store runs each operation with
+[tokio::task::spawn_blocking], so it must be used inside a Tokio runtime:
+
+```rust
+# use std::collections::HashMap;
+# use keyring_core::{mock, asynchronous::{AsyncCredentialStoreApi, BlockingStore}};
+# tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+let store = BlockingStore::new(mock::Store::new().unwrap());
+let entry = store.build("my-service", "my-user", None).await.unwrap();
+entry.set_password("my password").await.unwrap();
+assert_eq!(entry.get_password().await.unwrap(), "my password");
+# })
+```
+
+The mock store and (if the `sample` feature is specified) the sample store
+keep their credentials in memory, so they implement the async store trait
+directly: their operations run on the calling task. A sample store with a
+backing file is the exception: it may write the file, and wait for the
+lock on it, during any change, so its operations run on Tokio's blocking
+threads, as they do in a [BlockingStore].
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::api::CredentialStoreApi;
+use crate::{Credential, CredentialStore, Entry, Error, Result};
+
+/// The async version of [CredentialApi](crate::api::CredentialApi).
+///
+/// Each method behaves as the synchronous method of the same name does.
+#[async_trait]
+pub trait AsyncCredentialApi {
+    /// Set the credential's secret, creating the credential if need be.
+    async fn set_secret(&self, secret: &[u8]) -> Result<()>;
+
+    /// Set the credential's secret to the UTF-8 encoding of a password.
+    async fn set_password(&self, password: &str) -> Result<()> {
+        self.set_secret(password.as_bytes()).await
+    }
+
+    /// Get the credential's secret.
+    async fn get_secret(&self) -> Result<Vec<u8>>;
+
+    /// Get the credential's secret, decoded as a UTF-8 password.
+    async fn get_password(&self) -> Result<String> {
+        crate::error::decode_password(self.get_secret().await?)
+    }
+
+    /// Get the credential's attributes.
+    async fn get_attributes(&self) -> Result<HashMap<String, String>>;
+
+    /// Update the credential's attributes.
+    async fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()>;
+
+    /// Delete the credential.
+    async fn delete_credential(&self) -> Result<()>;
+
+    /// The service name and username of the credential, if it has them.
+    fn get_specifiers(&self) -> Option<(String, String)>;
+
+    /// The credential as an [Any](std::any::Any), so it can be downcast.
+    fn as_any(&self) -> &dyn std::any::Any;
+}
+
+/// A thread-safe implementation of the [AsyncCredentialApi] trait.
+pub type AsyncCredential = dyn AsyncCredentialApi + Send + Sync;
+
+/// The async version of [CredentialStoreApi].
+#[async_trait]
+pub trait AsyncCredentialStoreApi {
+    /// The vendor of the store.
+    fn vendor(&self) -> String;
+
+    /// The ID of this instance of the store.
+    fn id(&self) -> String;
+
+    /// Build an entry for a service name and username, with optional modifiers.
+    async fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<AsyncEntry>;
+
+    /// Search for credentials matching a spec.
+    async fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<AsyncEntry>>;
+}
+
+/// A thread-safe implementation of the [AsyncCredentialStoreApi] trait.
+pub type AsyncCredentialStore = dyn AsyncCredentialStoreApi + Send + Sync;
+
+/// An entry in a credential store, for use from async code.
+#[derive(Clone)]
+pub struct AsyncEntry {
+    inner: Arc<AsyncCredential>,
+}
+
+impl std::fmt::Debug for AsyncEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncEntry")
+            .field("specifiers", &self.inner.get_specifiers())
+            .finish()
+    }
+}
+
+impl AsyncEntry {
+    /// Create an entry that wraps an async credential.
+    pub fn new_with_credential(credential: Arc<AsyncCredential>) -> Self {
+        AsyncEntry { inner: credential }
+    }
+
+    /// Wrap a synchronous entry whose operations run on the calling task.
+    pub fn new_inline(entry: Entry) -> Self {
+        Self::new_with_credential(Arc::new(SyncCredential {
+            inner: entry.inner,
+            blocking: false,
+        }))
+    }
+
+    /// Wrap a synchronous entry whose operations run on Tokio's blocking threads.
+    pub fn new_blocking(entry: Entry) -> Self {
+        Self::new_with_credential(Arc::new(SyncCredential {
+            inner: entry.inner,
+            blocking: true,
+        }))
+    }
+
+    /// See [Entry::set_password].
+    pub async fn set_password(&self, password: &str) -> Result<()> {
+        self.inner.set_password(password).await
+    }
+
+    /// See [Entry::set_secret].
+    pub async fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.inner.set_secret(secret).await
+    }
+
+    /// See [Entry::get_password].
+    pub async fn get_password(&self) -> Result<String> {
+        self.inner.get_password().await
+    }
+
+    /// See [Entry::get_secret].
+    pub async fn get_secret(&self) -> Result<Vec<u8>> {
+        self.inner.get_secret().await
+    }
+
+    /// See [Entry::get_attributes].
+    pub async fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.inner.get_attributes().await
+    }
+
+    /// See [Entry::update_attributes].
+    pub async fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.inner.update_attributes(attrs).await
+    }
+
+    /// See [Entry::delete_credential].
+    pub async fn delete_credential(&self) -> Result<()> {
+        self.inner.delete_credential().await
+    }
+
+    /// See [Entry::get_specifiers].
+    pub fn get_specifiers(&self) -> Option<(String, String)> {
+        self.inner.get_specifiers()
+    }
+
+    /// Return the inner credential object cast to [Any](std::any::Any),
+    /// so it can be downcast.
+    pub fn as_any(&self) -> &dyn std::any::Any {
+        self.inner.as_any()
+    }
+}
+
+/// An async credential that runs the operations of a synchronous one.
+///
+/// If `blocking` is set, each operation runs on Tokio's blocking threads;
+/// otherwise, it runs on the calling task.
+#[derive(Debug)]
+pub struct SyncCredential {
+    pub inner: Arc<Credential>,
+    pub blocking: bool,
+}
+
+impl SyncCredential {
+    async fn run<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Credential) -> Result<T> + Send + 'static,
+    {
+        if !self.blocking {
+            return op(self.inner.as_ref());
+        }
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || op(inner.as_ref()))
+            .await
+            .map_err(|e| Error::PlatformFailure(Box::from(e)))?
+    }
+}
+
+#[async_trait]
+impl AsyncCredentialApi for SyncCredential {
+    async fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        let secret = secret.to_vec();
+        self.run(move |cred| cred.set_secret(&secret)).await
+    }
+
+    async fn get_secret(&self) -> Result<Vec<u8>> {
+        self.run(|cred| cred.get_secret()).await
+    }
+
+    async fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.run(|cred| cred.get_attributes()).await
+    }
+
+    async fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        let attrs = crate::attributes::externalize_attributes(attrs);
+        self.run(move |cred| {
+            cred.update_attributes(&crate::attributes::internalize_attributes(&attrs))
+        })
+        .await
+    }
+
+    async fn delete_credential(&self) -> Result<()> {
+        self.run(|cred| cred.delete_credential()).await
+    }
+
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.inner.get_specifiers()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// An async store that runs the operations of a synchronous store
+/// on Tokio's blocking threads.
+#[derive(Debug)]
+pub struct BlockingStore {
+    pub inner: Arc<CredentialStore>,
+}
+
+impl BlockingStore {
+    /// Wrap a synchronous store.
+    pub fn new(inner: Arc<CredentialStore>) -> Arc<Self> {
+        Arc::new(BlockingStore { inner })
+    }
+}
+
+#[async_trait]
+impl AsyncCredentialStoreApi for BlockingStore {
+    fn vendor(&self) -> String {
+        self.inner.vendor()
+    }
+
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    async fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<AsyncEntry> {
+        let store = self.inner.clone();
+        let (service, user) = (service.to_string(), user.to_string());
+        let mods = mods.map(crate::attributes::externalize_attributes);
+        let entry = tokio::task::spawn_blocking(move || {
+            let mods = mods.as_ref().map(crate::attributes::internalize_attributes);
+            store.build(&service, &user, mods.as_ref())
+        })
+        .await
+        .map_err(|e| Error::PlatformFailure(Box::from(e)))??;
+        Ok(AsyncEntry::new_blocking(entry))
+    }
+
+    async fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<AsyncEntry>> {
+        let store = self.inner.clone();
+        let spec = crate::attributes::externalize_attributes(spec);
+        let entries = tokio::task::spawn_blocking(move || {
+            store.search(&crate::attributes::internalize_attributes(&spec))
+        })
+        .await
+        .map_err(|e| Error::PlatformFailure(Box::from(e)))??;
+        Ok(entries.into_iter().map(AsyncEntry::new_blocking).collect())
+    }
+}
+
+#[async_trait]
+impl AsyncCredentialStoreApi for crate::mock::Store {
+    fn vendor(&self) -> String {
+        CredentialStoreApi::vendor(self)
+    }
+
+    fn id(&self) -> String {
+        CredentialStoreApi::id(self)
+    }
+
+    async fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<AsyncEntry> {
+        CredentialStoreApi::build(self, service, user, mods).map(AsyncEntry::new_inline)
+    }
+
+    async fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<AsyncEntry>> {
+        let entries = CredentialStoreApi::search(self, spec)?;
+        Ok(entries.into_iter().map(AsyncEntry::new_inline).collect())
+    }
+}
+
+/// Operations on a sample store without a backing file run on the calling task.
+/// Those on a store with one run on Tokio's blocking threads, because they
+/// may write the file.
+#[cfg(feature = "sample")]
+#[async_trait]
+impl AsyncCredentialStoreApi for crate::sample::Store {
+    fn vendor(&self) -> String {
+        CredentialStoreApi::vendor(self)
+    }
+
+    fn id(&self) -> String {
+        CredentialStoreApi::id(self)
+    }
+
+    async fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<AsyncEntry> {
+        if self.backing.is_some() {
+            let store = BlockingStore::new(self.get_store());
+            return store.build(service, user, mods).await;
+        }
+        CredentialStoreApi::build(self, service, user, mods).map(AsyncEntry::new_inline)
+    }
+
+    async fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<AsyncEntry>> {
+        if self.backing.is_some() {
+            let store = BlockingStore::new(self.get_store());
+            return store.search(spec).await;
+        }
+        let entries = CredentialStoreApi::search(self, spec)?;
+        Ok(entries.into_iter().map(AsyncEntry::new_inline).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::{AsyncCredentialStore, BlockingStore, SyncCredential};
+    use crate::{CredentialStore, Error, mock};
+
+    #[tokio::test]
+    async fn test_blocking_store() {
+        let sync: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<AsyncCredentialStore> = BlockingStore::new(sync.clone());
+        assert_eq!(store.vendor(), sync.vendor());
+        let entry = store.build("service", "user", None).await.unwrap();
+        assert!(matches!(entry.get_password().await, Err(Error::NoEntry)));
+        entry.set_password("password").await.unwrap();
+        assert_eq!(entry.get_password().await.unwrap(), "password");
+        let direct = sync.build("service", "user", None).unwrap();
+        assert_eq!(direct.get_password().unwrap(), "password");
+        assert_eq!(store.search(&HashMap::new()).await.unwrap().len(), 1);
+        entry.delete_credential().await.unwrap();
+        assert!(matches!(direct.get_password(), Err(Error::NoEntry)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_blocking_store_concurrently() {
+        let store: Arc<AsyncCredentialStore> = BlockingStore::new(mock::Store::new().unwrap());
+        let mut tasks = Vec::new();
+        for i in 0..10 {
+            let store = store.clone();
+            tasks.push(tokio::spawn(async move {
+                let entry = store.build("service", &format!("user{i}"), None).await?;
+                entry.set_password(&format!("password{i}")).await?;
+                entry.get_password().await
+            }));
+        }
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap().unwrap(), format!("password{i}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_store() {
+        let store: Arc<AsyncCredentialStore> = mock::Store::new().unwrap();
+        let entry = store.build("service", "user", None).await.unwrap();
+        entry.set_secret(&[1, 2, 3]).await.unwrap();
+        assert_eq!(entry.get_secret().await.unwrap(), vec![1, 2, 3]);
+        let cred: &SyncCredential = entry.as_any().downcast_ref().unwrap();
+        let mock: &mock::Cred = cred.inner.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.get_secret().await,
+            Err(Error::NoStorageAccess(_))
+        ));
+        assert_eq!(
+            entry.get_specifiers(),
+            Some(("service".to_string(), "user".to_string()))
+        );
+    }
+
+    #[cfg(feature = "sample")]
+    #[tokio::test]
+    async fn test_sample_store() {
+        let store: Arc<AsyncCredentialStore> = crate::sample::Store::new().unwrap();
+        let entry = store.build("service", "user", None).await.unwrap();
+        entry.set_password("password").await.unwrap();
+        entry
+            .update_attributes(&HashMap::from([("comment", "async")]))
+            .await
+            .unwrap();
+        assert_eq!(entry.get_attributes().await.unwrap()["comment"], "async");
+        let found = store
+            .search(&HashMap::from([("comment", "async")]))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_password().await.unwrap(), "password");
+        entry.delete_credential().await.unwrap();
+        assert!(matches!(entry.get_password().await, Err(Error::NoEntry)));
+    }
+
+    #[cfg(feature = "sample")]
+    #[tokio::test]
+    async fn test_backed_sample_store_runs_on_blocking_threads() {
+        let path = std::env::temp_dir()
+            .join(format!(
+                "store-async-test-{}.ron",
+                std::iter::repeat_with(fastrand::alphanumeric)
+                    .take(12)
+                    .collect::<String>()
+            ))
+            .to_str()
+            .unwrap()
+            .to_string();
+        let backed = crate::sample::Store::new_with_backing_autosave(&path).unwrap();
+        let store: Arc<AsyncCredentialStore> = backed.clone();
+        let entry = store.build("service", "user", None).await.unwrap();
+        let cred: &SyncCredential = entry.as_any().downcast_ref().unwrap();
+        assert!(cred.blocking);
+        entry.set_password("password").await.unwrap();
+        let found = store.search(&HashMap::new()).await.unwrap();
+        let cred: &SyncCredential = found[0].as_any().downcast_ref().unwrap();
+        assert!(cred.blocking);
+        let saved = crate::sample::Store::load_credentials(&path).unwrap();
+        assert_eq!(saved.len(), 1);
+        drop(store);
+        drop(backed);
+        _ = std::fs::remove_file(&path);
+        _ = std::fs::remove_file(format!("{path}.lock"));
+        let memory: Arc<AsyncCredentialStore> = crate::sample::Store::new().unwrap();
+        let entry = memory.build("service", "user", None).await.unwrap();
+        let cred: &SyncCredential = entry.as_any().downcast_ref().unwrap();
+        assert!(!cred.blocking);
+    }
+}
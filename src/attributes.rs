@@ -49,6 +49,27 @@ pub fn parse_attributes(
     Ok(result)
 }
 
+/// Parse an optional key-value &str map for allowed keys, as [parse_attributes]
+/// does, and then fill in default values for any keys that weren't given.
+///
+/// The keys in `defaults` are given without a `*` prefix, and their values
+/// are used as is, so they should be allowed keys with valid values.
+///
+/// Returns an [Invalid] error in the same cases as [parse_attributes].
+pub fn parse_attributes_with_defaults(
+    keys: &[&str],
+    defaults: &[(&str, &str)],
+    attrs: Option<&HashMap<&str, &str>>,
+) -> Result<HashMap<String, String>> {
+    let mut result = parse_attributes(keys, attrs)?;
+    for (key, value) in defaults {
+        result
+            .entry(key.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+    Ok(result)
+}
+
 /// Convert a borrowed key-value map of borrowed strings to an owned map of owned strings.
 pub fn externalize_attributes(attrs: &HashMap<&str, &str>) -> HashMap<String, String> {
     attrs
@@ -57,6 +78,76 @@ pub fn externalize_attributes(attrs: &HashMap<&str, &str>) -> HashMap<String, St
         .collect()
 }
 
+/// Parse a string of `key=value` pairs, separated by `;`, into an owned map.
+///
+/// A `\` makes the character after it literal, so keys and values can
+/// contain `;`, `=`, and `\` by escaping them as `\;`, `\=`, and `\\`.
+/// The empty string parses to an empty map. Borrow the result with
+/// [internalize_attributes] to pass it as modifiers or a search spec.
+///
+/// Returns an [Invalid] error naming the (1-based) pair at fault if a pair
+/// has no `=` or an empty key, if a key appears twice, or if the string
+/// ends with an unescaped `\`.
+pub fn parse_pairs(s: &str) -> Result<HashMap<String, String>> {
+    let mut result: HashMap<String, String> = HashMap::new();
+    if s.is_empty() {
+        return Ok(result);
+    }
+    let mut pairs: Vec<(String, Option<String>)> = vec![(String::new(), None)];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let (key, value) = pairs.last_mut().unwrap();
+        let c = match c {
+            '\\' => match chars.next() {
+                Some(c) => c,
+                None => {
+                    return Err(Invalid(
+                        format!("pair {}", pairs.len()),
+                        "ends with an unescaped `\\`".to_string(),
+                    ));
+                }
+            },
+            ';' => {
+                pairs.push((String::new(), None));
+                continue;
+            }
+            '=' if value.is_none() => {
+                *value = Some(String::new());
+                continue;
+            }
+            c => c,
+        };
+        match value {
+            Some(value) => value.push(c),
+            None => key.push(c),
+        }
+    }
+    for (i, (key, value)) in pairs.into_iter().enumerate() {
+        let name = format!("pair {}", i + 1);
+        let Some(value) = value else {
+            return Err(Invalid(name, "has no `=`".to_string()));
+        };
+        if key.is_empty() {
+            return Err(Invalid(name, "has an empty key".to_string()));
+        }
+        if result.contains_key(&key) {
+            return Err(Invalid(name, format!("repeats the key `{key}`")));
+        }
+        result.insert(key, value);
+    }
+    Ok(result)
+}
+
+/// Borrow an owned key-value map as a map of borrowed strings.
+///
+/// This is the inverse of [externalize_attributes].
+pub fn internalize_attributes(attrs: &HashMap<String, String>) -> HashMap<&str, &str> {
+    attrs
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,6 +178,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_attributes_with_defaults() {
+        let keys = ["mode", "*flag"];
+        let defaults = [("mode", "fast"), ("flag", "false")];
+        let parsed = parse_attributes_with_defaults(&keys, &defaults, None).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["mode"], "fast");
+        assert_eq!(parsed["flag"], "false");
+        let attrs = HashMap::from([("flag", "true")]);
+        let parsed = parse_attributes_with_defaults(&keys, &defaults, Some(&attrs)).unwrap();
+        assert_eq!(parsed["mode"], "fast");
+        assert_eq!(parsed["flag"], "true");
+        let attrs = HashMap::from([("mode", "slow"), ("other", "value")]);
+        match parse_attributes_with_defaults(&keys, &defaults, Some(&attrs)) {
+            Err(Invalid(key, msg)) => {
+                assert_eq!(key, "other");
+                assert_eq!(msg, "unknown key");
+            }
+            other => panic!("Incorrect result for unknown attribute: {other:?}"),
+        }
+    }
+
     #[test]
     fn test_externalize_attributes() {
         let attrs = HashMap::from([("key1", "value1"), ("key2", "true"), ("key3", "false")]);
@@ -96,4 +209,37 @@ mod tests {
         assert_eq!(externalized.get("key2"), Some(&"true".to_string()));
         assert_eq!(externalized.get("key3"), Some(&"false".to_string()));
     }
+
+    #[test]
+    fn test_parse_pairs() {
+        assert!(parse_pairs("").unwrap().is_empty());
+        let parsed = parse_pairs("service=foo;user=bar;comment=baz").unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed["service"], "foo");
+        assert_eq!(parsed["user"], "bar");
+        assert_eq!(parsed["comment"], "baz");
+        let parsed = parse_pairs("empty=;eq=a=b").unwrap();
+        assert_eq!(parsed["empty"], "");
+        assert_eq!(parsed["eq"], "a=b");
+        let parsed = parse_pairs(r"a\;b=c\;d;e\=f=g\\").unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["a;b"], "c;d");
+        assert_eq!(parsed["e=f"], "g\\");
+        let borrowed = internalize_attributes(&parsed);
+        assert_eq!(borrowed["a;b"], "c;d");
+        assert_eq!(externalize_attributes(&borrowed), parsed);
+        for (bad, pair) in [
+            ("novalue", "pair 1"),
+            ("a=b;", "pair 2"),
+            ("a=b;;c=d", "pair 2"),
+            ("=value", "pair 1"),
+            ("a=b;a=c", "pair 2"),
+            (r"a=b\", "pair 1"),
+        ] {
+            match parse_pairs(bad) {
+                Err(Invalid(key, _)) => assert_eq!(key, pair, "for {bad}"),
+                other => panic!("Incorrect result for {bad}: {other:?}"),
+            }
+        }
+    }
 }
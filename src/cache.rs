@@ -0,0 +1,675 @@
+/*!
+
+# Caching store
+
+This module provides a credential store wrapper that caches the secrets
+and attributes read through its entries, so that repeated reads of the
+same credential don't go to the wrapped store. It's only built if the
+`cache` feature is specified.
+
+```rust
+# use keyring_core::{api::CredentialStoreApi, cache::CachingStore, mock};
+let store = CachingStore::new(mock::Store::new().unwrap()).unwrap();
+let entry = store.build("my-service", "my-user", None).unwrap();
+entry.set_password("my password").unwrap();
+// this read goes to the wrapped store, the next one doesn't
+assert_eq!(entry.get_password().unwrap(), "my password");
+assert_eq!(entry.get_password().unwrap(), "my password");
+```
+
+Cached values are keyed by service, user, and (for wrappers) the UUID
+of the credential they wrap. Writes through the wrapper's entries go to
+the wrapped store first, and then drop every cached value for the
+service and user, since a write through one entry can change what
+another entry for the same service and user reads.
+
+Writes made directly to the wrapped store (or by other processes) aren't
+seen until the cached values expire. By default they never do, so you
+should either give the store a time to live with
+[new_with_ttl](CachingStore::new_with_ttl) or call
+[invalidate_all](CachingStore::invalidate_all) when you know the
+wrapped store has changed.
+
+Only successful reads are cached, so errors such as
+[NoEntry](crate::Error::NoEntry) are always reported by the wrapped store.
+The entries returned by searches, and those in
+[Ambiguous](crate::Error::Ambiguous) errors, share the store's cache.
+ */
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry as Slot;
+
+use crate::api::{CredentialApi, CredentialStoreApi};
+use crate::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, Entry, Error, Result,
+    SetOutcome,
+};
+
+/// The key of a cached credential: its service, user, and wrapped UUID, if any.
+type CacheKey = (String, String, Option<String>);
+
+/// The values cached for a credential, each with the time it was read.
+#[derive(Debug, Default)]
+struct Cached {
+    secret: Option<(Vec<u8>, Instant)>,
+    attributes: Option<(HashMap<String, String>, Instant)>,
+}
+
+/// The cache shared by a store and the credentials it builds.
+#[derive(Debug)]
+struct Cache {
+    entries: DashMap<CacheKey, Cached>,
+    /// Bumped each time a service and user are invalidated, so that
+    /// reads which started before then don't cache what they read.
+    /// Only invalidated pairs have a count; the others count as 0.
+    generations: DashMap<(String, String), u64>,
+    /// Bumped each time the whole cache is invalidated. It's added
+    /// to the count of each pair to make that pair's generation.
+    epoch: RwLock<u64>,
+    ttl: Option<Duration>,
+}
+
+impl Cache {
+    /// Tell whether a value read at `stamp` is still fresh.
+    fn is_fresh(&self, stamp: Instant) -> bool {
+        match self.ttl {
+            Some(ttl) => stamp.elapsed() < ttl,
+            None => true,
+        }
+    }
+
+    /// The current generation of the service and user.
+    fn generation(&self, service: &str, user: &str) -> u64 {
+        let epoch = *self.epoch.read().expect("RwLock bug at generation!");
+        let key = (service.to_string(), user.to_string());
+        epoch + self.generations.get(&key).map_or(0, |count| *count)
+    }
+
+    /// Update the cached values for `key` with `f`, unless its
+    /// service and user have been invalidated since `generation`.
+    ///
+    /// The generation stays locked until the values are updated,
+    /// so an invalidation can't slip in between. A pair that has
+    /// never been invalidated is locked without being added.
+    fn update_if_current(&self, key: CacheKey, generation: u64, f: impl FnOnce(&mut Cached)) {
+        let epoch = self.epoch.read().expect("RwLock bug at update!");
+        let current = |count: u64| *epoch + count == generation;
+        match self.generations.entry((key.0.clone(), key.1.clone())) {
+            Slot::Occupied(count) => {
+                if current(*count.get()) {
+                    f(&mut self.entries.entry(key).or_default());
+                }
+            }
+            Slot::Vacant(_uncounted) => {
+                if current(0) {
+                    f(&mut self.entries.entry(key).or_default());
+                }
+            }
+        }
+    }
+
+    /// Drop every cached value for the service and user.
+    fn invalidate(&self, service: &str, user: &str) {
+        let mut generation = self
+            .generations
+            .entry((service.to_string(), user.to_string()))
+            .or_default();
+        *generation += 1;
+        self.entries
+            .retain(|(s, u, _), _| !(s == service && u == user));
+    }
+
+    /// Drop every cached value.
+    fn invalidate_all(&self) {
+        let mut epoch = self.epoch.write().expect("RwLock bug at invalidate!");
+        *epoch += 1;
+        self.entries.clear();
+    }
+}
+
+/// Wrap an entry of the wrapped store so that it shares `cache`.
+fn wrap_entry(cache: &Arc<Cache>, entry: Entry) -> Entry {
+    Entry::new_with_credential(Arc::new(CachingCred {
+        inner: entry.inner,
+        cache: cache.clone(),
+    }))
+}
+
+/// Wrap the entries in an [Ambiguous](Error::Ambiguous) error so that they share `cache`.
+fn wrap_error(cache: &Arc<Cache>, err: Error) -> Error {
+    match err {
+        Error::Ambiguous(entries) => Error::Ambiguous(
+            entries
+                .into_iter()
+                .map(|entry| wrap_entry(cache, entry))
+                .collect(),
+        ),
+        err => err,
+    }
+}
+
+/// A store that caches what's read from the store it wraps.
+pub struct CachingStore {
+    pub id: String,
+    pub inner: Arc<CredentialStore>,
+    cache: Arc<Cache>,
+}
+
+impl std::fmt::Debug for CachingStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("inner", &self.inner)
+            .field("ttl", &self.cache.ttl)
+            .field("cached", &self.cache.entries.len())
+            .finish()
+    }
+}
+
+impl CachingStore {
+    /// Cache what's read from `inner`, until it's written or invalidated.
+    pub fn new(inner: Arc<CredentialStore>) -> Result<Arc<Self>> {
+        Self::new_internal(inner, None)
+    }
+
+    /// Cache what's read from `inner` for at most `ttl`.
+    pub fn new_with_ttl(inner: Arc<CredentialStore>, ttl: Duration) -> Result<Arc<Self>> {
+        Self::new_internal(inner, Some(ttl))
+    }
+
+    fn new_internal(inner: Arc<CredentialStore>, ttl: Option<Duration>) -> Result<Arc<Self>> {
+        Ok(Arc::new(CachingStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            inner,
+            cache: Arc::new(Cache {
+                entries: DashMap::new(),
+                generations: DashMap::new(),
+                epoch: RwLock::new(0),
+                ttl,
+            }),
+        }))
+    }
+
+    /// Drop every cached value, so the next reads go to the wrapped store.
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+    }
+}
+
+impl CredentialStoreApi for CachingStore {
+    fn vendor(&self) -> String {
+        String::from("Caching store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The credentials live in the wrapped store, so report its members.
+    fn member_vendors(&self) -> Vec<String> {
+        self.inner.member_vendors()
+    }
+
+    /// Build an entry in the wrapped store, and cache what's read through it.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let entry = self.inner.build(service, user, mods)?;
+        Ok(wrap_entry(&self.cache, entry))
+    }
+
+    /// Search the wrapped store.
+    ///
+    /// Searches aren't cached, but what's read through the entries they return is.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        let entries = self.inner.search(spec)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| wrap_entry(&self.cache, entry))
+            .collect())
+    }
+
+    /// List the credentials in the wrapped store.
+    ///
+    /// What's read through the entries returned is cached.
+    fn all(&self) -> Result<Vec<Entry>> {
+        let entries = self.inner.all()?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| wrap_entry(&self.cache, entry))
+            .collect())
+    }
+
+    /// Count the credentials in the wrapped store.
+    fn count(&self) -> Result<usize> {
+        self.inner.count()
+    }
+
+    /// Clear the wrapped store, and drop every cached value.
+    ///
+    /// The values are dropped even if the clear fails, since it may have been partly done.
+    fn clear(&self) -> Result<usize> {
+        let result = self.inner.clear();
+        self.invalidate_all();
+        result
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// Credentials live in the wrapped store, so they have its persistence.
+    fn persistence(&self) -> CredentialPersistence {
+        self.inner.persistence()
+    }
+
+    /// Credentials live in the wrapped store, so they have its capabilities.
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential whose reads are cached.
+pub struct CachingCred {
+    pub inner: Arc<Credential>,
+    cache: Arc<Cache>,
+}
+
+impl std::fmt::Debug for CachingCred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingCred")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl CachingCred {
+    /// The cache key for this credential, if it has specifiers.
+    fn key(&self) -> Option<CacheKey> {
+        let (service, user) = self.inner.get_specifiers()?;
+        Some((service, user, self.inner.get_wrapped_uuid()))
+    }
+
+    /// Write through to the inner credential, then drop the cached values it may have changed.
+    ///
+    /// The values are dropped even if the write fails, since it may have been partly done.
+    fn write<T>(&self, f: impl FnOnce(&Credential) -> Result<T>) -> Result<T> {
+        let result = f(self.inner.as_ref());
+        if let Some((service, user)) = self.inner.get_specifiers() {
+            self.cache.invalidate(&service, &user);
+        }
+        result.map_err(|e| wrap_error(&self.cache, e))
+    }
+
+    /// Read from the inner credential, wrapping the entries of any ambiguity error.
+    fn read<T>(&self, f: impl FnOnce(&Credential) -> Result<T>) -> Result<T> {
+        f(self.inner.as_ref()).map_err(|e| wrap_error(&self.cache, e))
+    }
+
+    /// Wrap a credential returned by the inner credential so that it shares this cache.
+    fn wrap(&self, cred: Option<Arc<Credential>>) -> Option<Arc<Credential>> {
+        cred.map(|inner| {
+            Arc::new(CachingCred {
+                inner,
+                cache: self.cache.clone(),
+            }) as Arc<Credential>
+        })
+    }
+}
+
+impl CredentialApi for CachingCred {
+    /// See the API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.write(|cred| cred.set_secret(secret))
+    }
+
+    /// See the API docs.
+    fn set_secret_reporting(&self, secret: &[u8]) -> Result<SetOutcome> {
+        self.write(|cred| cred.set_secret_reporting(secret))
+    }
+
+    /// See the API docs.
+    fn set_secret_if_changed(&self, secret: &[u8]) -> Result<bool> {
+        self.write(|cred| cred.set_secret_if_changed(secret))
+    }
+
+    /// See the API docs.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        self.write(|cred| cred.compare_and_set(expected, new))
+    }
+
+    /// See the API docs.
+    fn increment_secret_u64(&self, delta: u64) -> Result<u64> {
+        self.write(|cred| cred.increment_secret_u64(delta))
+    }
+
+    /// See the API docs.
+    ///
+    /// A fresh cached secret is returned without reading the inner credential.
+    /// A secret read while the credential was being written isn't cached.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        let Some(key) = self.key() else {
+            return self.read(|cred| cred.get_secret());
+        };
+        if let Some(cached) = self.cache.entries.get(&key) {
+            if let Some((secret, stamp)) = &cached.secret {
+                if self.cache.is_fresh(*stamp) {
+                    return Ok(secret.clone());
+                }
+            }
+        }
+        let generation = self.cache.generation(&key.0, &key.1);
+        let secret = self.read(|cred| cred.get_secret())?;
+        self.cache.update_if_current(key, generation, |cached| {
+            cached.secret = Some((secret.clone(), Instant::now()))
+        });
+        Ok(secret)
+    }
+
+    /// See the API docs.
+    ///
+    /// Fresh cached attributes are returned without reading the inner credential.
+    /// Attributes read while the credential was being written aren't cached.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        let Some(key) = self.key() else {
+            return self.read(|cred| cred.get_attributes());
+        };
+        if let Some(cached) = self.cache.entries.get(&key) {
+            if let Some((attributes, stamp)) = &cached.attributes {
+                if self.cache.is_fresh(*stamp) {
+                    return Ok(attributes.clone());
+                }
+            }
+        }
+        let generation = self.cache.generation(&key.0, &key.1);
+        let attributes = self.read(|cred| cred.get_attributes())?;
+        self.cache.update_if_current(key, generation, |cached| {
+            cached.attributes = Some((attributes.clone(), Instant::now()))
+        });
+        Ok(attributes)
+    }
+
+    /// See the API docs.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        self.read(|cred| cred.attribute_keys())
+    }
+
+    /// See the API docs.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_created())
+    }
+
+    /// See the API docs.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_modified())
+    }
+
+    /// See the API docs.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.write(|cred| cred.update_attributes(attrs))
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        self.write(|cred| cred.delete_credential())
+    }
+
+    /// See the API docs.
+    ///
+    /// The cached values for both the old and the new service and user are dropped.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        let result = self.write(|cred| cred.rename(new_service, new_user));
+        self.cache.invalidate(new_service, new_user);
+        result
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper shares this credential's cache.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        Ok(self.wrap(self.read(|cred| cred.get_credential())?))
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper shares this credential's cache.
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        Ok(self.wrap(self.read(|cred| cred.get_primary_credential())?))
+    }
+
+    /// See the API docs.
+    fn exists(&self) -> Result<bool> {
+        self.read(|cred| cred.exists())
+    }
+
+    /// See the API docs.
+    fn get_wrapped_uuid(&self) -> Option<String> {
+        self.inner.get_wrapped_uuid()
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.inner.get_specifiers()
+    }
+
+    /// See the API docs.
+    fn is_specifier(&self) -> bool {
+        self.inner.is_specifier()
+    }
+
+    /// See the API docs.
+    fn is_wrapper(&self) -> bool {
+        self.inner.is_wrapper()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+    use std::time::{Duration, Instant};
+
+    use dashmap::DashMap;
+
+    use super::{Cache, CachingCred, CachingStore};
+    use crate::mock::{self, MockCall};
+    use crate::{CredentialStore, Error};
+
+    fn get_secret_calls(mock: &mock::Store) -> usize {
+        mock.take_calls()
+            .iter()
+            .filter(|call| matches!(call, MockCall::GetSecret { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_reads_are_cached() {
+        let mock = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> = CachingStore::new(mock.clone()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        mock.enable_recording();
+        assert_eq!(entry.get_password().unwrap(), "password");
+        assert_eq!(get_secret_calls(&mock), 1);
+        assert_eq!(entry.get_password().unwrap(), "password");
+        let other = store.build("service", "user", None).unwrap();
+        assert_eq!(other.get_password().unwrap(), "password");
+        assert_eq!(get_secret_calls(&mock), 0);
+        entry.get_attributes().unwrap();
+        entry.get_attributes().unwrap();
+        let calls = mock.take_calls();
+        assert_eq!(calls.len(), 1);
+        assert!(matches!(calls[0], MockCall::GetAttributes { .. }));
+    }
+
+    #[test]
+    fn test_writes_invalidate() {
+        let mock = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> = CachingStore::new(mock.clone()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let other = store.build("service", "user", None).unwrap();
+        entry.set_password("first").unwrap();
+        assert_eq!(other.get_password().unwrap(), "first");
+        entry.set_password("second").unwrap();
+        assert_eq!(other.get_password().unwrap(), "second");
+        mock.enable_recording();
+        entry.delete_credential().unwrap();
+        assert!(matches!(other.get_password(), Err(Error::NoEntry)));
+        assert!(matches!(other.get_password(), Err(Error::NoEntry)));
+        assert_eq!(get_secret_calls(&mock), 2);
+        // the inner store is written before the cache is dropped
+        entry.set_password("third").unwrap();
+        assert_eq!(other.get_password().unwrap(), "third");
+        let calls = mock.take_calls();
+        assert!(matches!(calls[0], MockCall::SetSecret { .. }));
+        assert!(matches!(calls[1], MockCall::GetSecret { .. }));
+    }
+
+    #[test]
+    fn test_invalidate_all_and_ttl() {
+        let mock: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let direct = mock.build("service", "user", None).unwrap();
+        direct.set_password("first").unwrap();
+        let caching = CachingStore::new(mock.clone()).unwrap();
+        let store: Arc<CredentialStore> = caching.clone();
+        let entry = store.build("service", "user", None).unwrap();
+        assert_eq!(entry.get_password().unwrap(), "first");
+        direct.set_password("second").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "first");
+        caching.invalidate_all();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        let store: Arc<CredentialStore> =
+            CachingStore::new_with_ttl(mock.clone(), Duration::from_millis(50)).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        direct.set_password("third").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(entry.get_password().unwrap(), "third");
+        assert!(store.search(&HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_found_entries_share_the_cache() {
+        let mock = mock::Store::new().unwrap();
+        let caching = CachingStore::new(mock.clone()).unwrap();
+        let store: Arc<CredentialStore> = caching.clone();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        let found = store.search(&HashMap::new()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].as_any().downcast_ref::<CachingCred>().is_some());
+        mock.enable_recording();
+        assert_eq!(found[0].get_password().unwrap(), "password");
+        assert_eq!(found[0].get_password().unwrap(), "password");
+        assert_eq!(get_secret_calls(&mock), 1);
+        mock.make_ambiguous("service", "user", 2);
+        caching.invalidate_all();
+        let wrappers = match entry.get_password() {
+            Err(Error::Ambiguous(wrappers)) => wrappers,
+            other => panic!("expected an ambiguous error, got {other:?}"),
+        };
+        assert_eq!(wrappers.len(), 2);
+        for wrapper in &wrappers {
+            assert!(wrapper.as_any().downcast_ref::<CachingCred>().is_some());
+        }
+    }
+
+    #[test]
+    fn test_reads_overtaken_by_writes_are_not_cached() {
+        let cache = Cache {
+            entries: DashMap::new(),
+            generations: DashMap::new(),
+            epoch: RwLock::new(0),
+            ttl: None,
+        };
+        let key = ("service".to_string(), "user".to_string(), None);
+        let before = cache.generation("service", "user");
+        assert!(cache.generations.is_empty());
+        // a write finishes while the read is going on
+        cache.invalidate("service", "user");
+        cache.update_if_current(key.clone(), before, |cached| {
+            cached.secret = Some((b"stale".to_vec(), Instant::now()))
+        });
+        assert!(cache.entries.get(&key).is_none());
+        let after = cache.generation("service", "user");
+        cache.invalidate_all();
+        cache.update_if_current(key.clone(), after, |cached| {
+            cached.secret = Some((b"stale".to_vec(), Instant::now()))
+        });
+        assert!(cache.entries.get(&key).is_none());
+        let current = cache.generation("service", "user");
+        cache.update_if_current(key.clone(), current, |cached| {
+            cached.secret = Some((b"fresh".to_vec(), Instant::now()))
+        });
+        assert!(cache.entries.get(&key).is_some());
+        // a pair that was never invalidated on its own
+        let other = ("service".to_string(), "other".to_string(), None);
+        let before = cache.generation("service", "other");
+        cache.invalidate_all();
+        cache.update_if_current(other.clone(), before, |cached| {
+            cached.secret = Some((b"stale".to_vec(), Instant::now()))
+        });
+        assert!(cache.entries.get(&other).is_none());
+        assert_eq!(cache.generations.len(), 1);
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_rename_invalidates_both_names() {
+        let store: Arc<CredentialStore> =
+            CachingStore::new(crate::sample::Store::new().unwrap()).unwrap();
+        let old = store.build("service", "old", None).unwrap();
+        old.set_password("password").unwrap();
+        assert_eq!(old.get_password().unwrap(), "password");
+        old.rename("service", "new").unwrap();
+        assert!(matches!(old.get_password(), Err(Error::NoEntry)));
+        let new = store.build("service", "new", None).unwrap();
+        assert_eq!(new.get_password().unwrap(), "password");
+        new.rename("service", "old").unwrap();
+        assert!(matches!(new.get_password(), Err(Error::NoEntry)));
+        assert_eq!(old.get_password().unwrap(), "password");
+    }
+
+    #[test]
+    fn test_store_wide_operations() {
+        let mock = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> = CachingStore::new(mock.clone()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "password");
+        assert_eq!(store.count().unwrap(), 1);
+        let all = store.all().unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all[0].as_any().downcast_ref::<CachingCred>().is_some());
+        assert_eq!(store.clear().unwrap(), 1);
+        // the cached secret went with the credential
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    }
+}
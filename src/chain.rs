@@ -0,0 +1,541 @@
+/*!
+
+# Chain store
+
+This module provides a credential store combinator that layers several
+credential stores in order. Entries built by the combinator write to the
+first store, but their reads fall through the stores in order until one
+of them has the credential.
+
+This can serve, for example, as a read-through cache over a slow store,
+with writes landing in the cache:
+
+```rust
+# use keyring_core::{api::CredentialStoreApi, chain::ChainStore, mock};
+let cache = mock::Store::new().unwrap();
+let slow = mock::Store::new().unwrap();
+slow.build("my-service", "my-user", None)
+    .unwrap()
+    .set_password("slow password")
+    .unwrap();
+let store = ChainStore::new(vec![cache, slow]).unwrap();
+let entry = store.build("my-service", "my-user", None).unwrap();
+assert_eq!(entry.get_password().unwrap(), "slow password");
+entry.set_password("cached password").unwrap();
+assert_eq!(entry.get_password().unwrap(), "cached password");
+```
+
+A store is considered not to have a credential when an operation on it
+fails with a [NoEntry](Error::NoEntry) error. Any other error is returned
+as is, without trying the later stores.
+
+Searches are run in every store that supports them, and the entries
+they return belong to whichever store found them.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::{CredentialApi, CredentialStoreApi};
+use crate::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, Entry, Error, Result,
+};
+
+/// A store that writes to the first of its stores and reads from the first that has a credential.
+pub struct ChainStore {
+    pub id: String,
+    pub stores: Vec<Arc<CredentialStore>>,
+}
+
+impl std::fmt::Debug for ChainStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChainStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("stores", &self.stores)
+            .finish()
+    }
+}
+
+impl ChainStore {
+    /// Chain the given `stores`, in order.
+    ///
+    /// Returns an [Invalid](Error::Invalid) error if there are no stores.
+    pub fn new(stores: Vec<Arc<CredentialStore>>) -> Result<Arc<Self>> {
+        if stores.is_empty() {
+            return Err(Error::Invalid(
+                "stores".to_string(),
+                "must not be empty".to_string(),
+            ));
+        }
+        Ok(Arc::new(ChainStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            stores,
+        }))
+    }
+}
+
+/// Rank a persistence by how long it keeps credentials, with unknowns lowest.
+fn durability(persistence: &CredentialPersistence) -> u8 {
+    match persistence {
+        CredentialPersistence::EntryOnly => 1,
+        CredentialPersistence::ProcessOnly => 2,
+        CredentialPersistence::UntilLogout => 3,
+        CredentialPersistence::UntilReboot => 4,
+        CredentialPersistence::UntilDelete => 5,
+        _ => 0,
+    }
+}
+
+impl CredentialStoreApi for ChainStore {
+    fn vendor(&self) -> String {
+        String::from("Chain store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The members are those of each chained store, in order.
+    fn member_vendors(&self) -> Vec<String> {
+        self.stores
+            .iter()
+            .flat_map(|store| store.member_vendors())
+            .collect()
+    }
+
+    /// Build an entry in every chained store.
+    ///
+    /// The modifiers are only passed to the first store, because
+    /// they are used when creating credentials and only the first
+    /// store is written.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let mut creds = Vec::with_capacity(self.stores.len());
+        for (i, store) in self.stores.iter().enumerate() {
+            let mods = if i == 0 { mods } else { None };
+            creds.push(store.build(service, user, mods)?.inner);
+        }
+        Ok(Entry::new_with_credential(Arc::new(ChainCred { creds })))
+    }
+
+    /// Search every chained store, and return all the entries found, in store order.
+    ///
+    /// Stores that don't support search are skipped, unless none of them do.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        let mut found = Vec::new();
+        let mut searched = false;
+        for store in &self.stores {
+            match store.search(spec) {
+                Ok(entries) => {
+                    searched = true;
+                    found.extend(entries);
+                }
+                Err(Error::NotSupportedByStore(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if searched {
+            Ok(found)
+        } else {
+            Err(Error::NotSupportedByStore(self.vendor()))
+        }
+    }
+
+    /// List the credentials in every chained store, in store order.
+    ///
+    /// Unlike a search, this fails if any of the stores can't list its credentials.
+    fn all(&self) -> Result<Vec<Entry>> {
+        let mut found = Vec::new();
+        for store in &self.stores {
+            found.extend(store.all()?);
+        }
+        Ok(found)
+    }
+
+    /// Count the credentials in every chained store.
+    ///
+    /// This fails if any of the stores can't count its credentials.
+    fn count(&self) -> Result<usize> {
+        let mut count = 0;
+        for store in &self.stores {
+            count += store.count()?;
+        }
+        Ok(count)
+    }
+
+    /// Clear every chained store, since a credential may be read from any of them.
+    ///
+    /// The stores are cleared in order, and the first failure stops the clear.
+    fn clear(&self) -> Result<usize> {
+        let mut count = 0;
+        for store in &self.stores {
+            count += store.clear()?;
+        }
+        Ok(count)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// A credential may be read from any of the stores, so
+    /// this is the shortest-lived persistence among them.
+    fn persistence(&self) -> CredentialPersistence {
+        self.stores
+            .iter()
+            .map(|store| store.persistence())
+            .min_by_key(durability)
+            .unwrap_or(CredentialPersistence::Unspecified)
+    }
+
+    /// Credentials are written to the first store, so they have its capabilities,
+    /// except that search works if any store supports it, and credentials
+    /// persist to disk only if they do in every store.
+    fn capabilities(&self) -> Capabilities {
+        let mut caps = self.stores[0].capabilities();
+        for store in &self.stores[1..] {
+            let other = store.capabilities();
+            caps.supports_search |= other.supports_search;
+            caps.persists_to_disk &= other.persists_to_disk;
+        }
+        caps
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential that writes to the first of its credentials and reads from the first that exists.
+#[derive(Debug)]
+pub struct ChainCred {
+    pub creds: Vec<Arc<Credential>>,
+}
+
+impl ChainCred {
+    /// Try a read on each credential in turn, until one doesn't report [NoEntry](Error::NoEntry).
+    fn read<T>(&self, f: impl Fn(&Credential) -> Result<T>) -> Result<T> {
+        for cred in &self.creds {
+            match f(cred.as_ref()) {
+                Err(Error::NoEntry) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::NoEntry)
+    }
+
+    /// The credential that writes go to.
+    fn first(&self) -> &Credential {
+        self.creds[0].as_ref()
+    }
+}
+
+impl CredentialApi for ChainCred {
+    /// Writes always go to the first credential.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.first().set_secret(secret)
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        self.read(|cred| cred.get_secret())
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.read(|cred| cred.get_attributes())
+    }
+
+    /// See the API docs.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        self.read(|cred| cred.attribute_keys())
+    }
+
+    /// See the API docs.
+    fn has_empty_secret(&self) -> Result<bool> {
+        self.read(|cred| cred.has_empty_secret())
+    }
+
+    /// See the API docs.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_created())
+    }
+
+    /// See the API docs.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_modified())
+    }
+
+    /// Writes always go to the first credential.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.first().update_attributes(attrs)
+    }
+
+    /// Writes always go to the first credential, so if it exists, this compares against it.
+    ///
+    /// If it doesn't, `expected` is compared against the secret that's read
+    /// through the chain, and a match creates the first credential with
+    /// the `new` secret. A delete of a credential that only a later store
+    /// has fails with a [NoEntry](Error::NoEntry) error, as
+    /// [delete_credential](CredentialApi::delete_credential) does.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        if self.first().exists()? {
+            return self.first().compare_and_set(expected, new);
+        }
+        let current = match self.read(|cred| cred.get_secret()) {
+            Ok(current) => Some(current),
+            Err(Error::NoEntry) => None,
+            Err(e) => return Err(e),
+        };
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+        match (current, new) {
+            // nothing anywhere, so creating it goes as in the first store
+            (None, new) => self.first().compare_and_set(None, new),
+            // the first credential may have been created since it was checked
+            (Some(_), Some(new)) => self.first().compare_and_set(None, Some(new)),
+            (Some(_), None) => Err(Error::NoEntry),
+        }
+    }
+
+    /// Deletes always go to the first credential.
+    fn delete_credential(&self) -> Result<()> {
+        self.first().delete_credential()
+    }
+
+    /// Renames always go to the first credential.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        self.first().rename(new_service, new_user)
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper comes from the first store that has the credential.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        for cred in &self.creds {
+            match cred.get_credential() {
+                Err(Error::NoEntry) => continue,
+                Ok(None) => return Ok(Some(cred.clone())),
+                result => return result,
+            }
+        }
+        Err(Error::NoEntry)
+    }
+
+    /// See the API docs.
+    fn exists(&self) -> Result<bool> {
+        for cred in &self.creds {
+            if cred.exists()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.first().get_specifiers()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::ChainStore;
+    use crate::{CredentialStore, Error, mock};
+
+    #[test]
+    fn test_empty_chain() {
+        assert!(matches!(
+            ChainStore::new(vec![]),
+            Err(Error::Invalid(key, _)) if key == "stores"
+        ));
+    }
+
+    #[test]
+    fn test_reads_fall_through() {
+        let first: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let second: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            ChainStore::new(vec![first.clone(), second.clone()]).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        assert!(!entry.exists().unwrap());
+        let below = second.build("service", "user", None).unwrap();
+        below.set_password("second").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        assert!(entry.exists().unwrap());
+        entry.set_password("first").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "first");
+        assert_eq!(below.get_password().unwrap(), "second");
+        let top = first.build("service", "user", None).unwrap();
+        assert_eq!(top.get_password().unwrap(), "first");
+        entry.delete_credential().unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        assert_eq!(store.member_vendors().len(), 2);
+    }
+
+    #[test]
+    fn test_other_errors_do_not_fall_through() {
+        let first: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let second: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> = ChainStore::new(vec![first, second.clone()]).unwrap();
+        second
+            .build("service", "user", None)
+            .unwrap()
+            .set_password("second")
+            .unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let chained: &super::ChainCred = entry.as_any().downcast_ref().unwrap();
+        let mock: &mock::Cred = chained.creds[0].as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.get_password(),
+            Err(Error::NoStorageAccess(_))
+        ));
+        assert_eq!(entry.get_password().unwrap(), "second");
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_mock_over_sample() {
+        use std::collections::HashMap;
+
+        use crate::{CredentialPersistence, sample};
+
+        let cache: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let sample: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            ChainStore::new(vec![cache.clone(), sample.clone()]).unwrap();
+        sample
+            .build("service", "user1", None)
+            .unwrap()
+            .set_password("sample")
+            .unwrap();
+        store
+            .build("service", "user2", None)
+            .unwrap()
+            .set_password("cache")
+            .unwrap();
+        let entry = store.build("service", "user1", None).unwrap();
+        assert_eq!(entry.get_password().unwrap(), "sample");
+        assert!(entry.get_attributes().unwrap().contains_key("uuid"));
+        let wrapper = entry.get_credential().unwrap();
+        assert_eq!(wrapper.get_password().unwrap(), "sample");
+        let found = store
+            .search(&HashMap::from([("service", "service")]))
+            .unwrap();
+        let mut passwords: Vec<String> =
+            found.iter().filter_map(|e| e.get_password().ok()).collect();
+        passwords.sort();
+        assert_eq!(passwords, vec!["cache", "sample"]);
+        assert!(matches!(
+            store.persistence(),
+            CredentialPersistence::ProcessOnly
+        ));
+        let caps = store.capabilities();
+        assert!(caps.supports_search);
+        assert!(!caps.supports_attributes);
+        assert!(!caps.persists_to_disk);
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_rename_goes_to_first() {
+        use crate::sample;
+
+        let first: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let second: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            ChainStore::new(vec![first.clone(), second.clone()]).unwrap();
+        let entry = store.build("service", "old", None).unwrap();
+        entry.set_password("first").unwrap();
+        let below = second.build("service", "old", None).unwrap();
+        below.set_password("second").unwrap();
+        entry.rename("service", "new").unwrap();
+        let moved = first.build("service", "new", None).unwrap();
+        assert_eq!(moved.get_password().unwrap(), "first");
+        assert_eq!(below.get_password().unwrap(), "second");
+    }
+
+    #[test]
+    fn test_store_wide_operations() {
+        let first: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let second: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            ChainStore::new(vec![first.clone(), second.clone()]).unwrap();
+        store
+            .build("service", "user1", None)
+            .unwrap()
+            .set_password("first")
+            .unwrap();
+        second
+            .build("service", "user2", None)
+            .unwrap()
+            .set_password("second")
+            .unwrap();
+        // building through the chain builds in both mock stores, which count what's built
+        assert_eq!(store.count().unwrap(), 3);
+        assert_eq!(store.all().unwrap().len(), 3);
+        assert_eq!(store.clear().unwrap(), 3);
+        assert_eq!(first.count().unwrap() + second.count().unwrap(), 0);
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_update_with_from_a_later_store() {
+        use crate::sample;
+
+        let top: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let bottom: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            ChainStore::new(vec![top.clone(), bottom.clone()]).unwrap();
+        let below = bottom.build("service", "user", None).unwrap();
+        below.set_password("old").unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert_eq!(entry.get_password().unwrap(), "old");
+        entry
+            .update_with(
+                |current| {
+                    assert_eq!(current.as_deref(), Some(b"old".as_slice()));
+                    Some(b"new".to_vec())
+                },
+                5,
+            )
+            .unwrap();
+        assert_eq!(entry.get_password().unwrap(), "new");
+        let above = top.build("service", "user", None).unwrap();
+        assert_eq!(above.get_password().unwrap(), "new");
+        assert_eq!(below.get_password().unwrap(), "old");
+        assert!(!entry.compare_and_set(Some(b"old"), Some(b"newer")).unwrap());
+        assert!(matches!(
+            store
+                .build("service", "other", None)
+                .unwrap()
+                .compare_and_set(Some(b"old"), None),
+            Ok(false)
+        ));
+    }
+}
@@ -68,10 +68,124 @@ pub enum Error {
     /// This indicates that the requested operation is unsupported by the
     /// store handling the request. The vendor of the store is the value.
     NotSupportedByStore(String),
+    /// This indicates that the credential can't be changed,
+    /// because it was made immutable when its secret was set.
+    Immutable,
+    /// This indicates that an operation gave up before it could complete,
+    /// for example because other writers kept changing a credential.
+    /// The value describes what was attempted.
+    Timeout(String),
+    /// This indicates that the credential store behind an entry has been dropped,
+    /// so the entry can no longer be used. (See [WeakStore](crate::weak::WeakStore).)
+    StoreGone,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The kind of an [Error], without its payload.
+///
+/// There is one kind for each variant of `Error`, with the same name,
+/// so callers can compare and hash kinds where they can't match on errors.
+/// Like `Error`, this enum is non-exhaustive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    PlatformFailure,
+    NoStorageAccess,
+    NoEntry,
+    BadEncoding,
+    BadDataFormat,
+    TooLong,
+    Invalid,
+    Ambiguous,
+    NoDefaultStore,
+    NotSupportedByStore,
+    Immutable,
+    Timeout,
+    StoreGone,
+}
+
+impl Error {
+    /// The kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PlatformFailure(_) => ErrorKind::PlatformFailure,
+            Error::NoStorageAccess(_) => ErrorKind::NoStorageAccess,
+            Error::NoEntry => ErrorKind::NoEntry,
+            Error::BadEncoding(_) => ErrorKind::BadEncoding,
+            Error::BadDataFormat(..) => ErrorKind::BadDataFormat,
+            Error::TooLong(..) => ErrorKind::TooLong,
+            Error::Invalid(..) => ErrorKind::Invalid,
+            Error::Ambiguous(_) => ErrorKind::Ambiguous,
+            Error::NoDefaultStore => ErrorKind::NoDefaultStore,
+            Error::NotSupportedByStore(_) => ErrorKind::NotSupportedByStore,
+            Error::Immutable => ErrorKind::Immutable,
+            Error::Timeout(_) => ErrorKind::Timeout,
+            Error::StoreGone => ErrorKind::StoreGone,
+        }
+    }
+
+    /// Whether the operation that failed with this error might succeed if retried.
+    ///
+    /// Failures to reach the platform store ([NoStorageAccess](Error::NoStorageAccess),
+    /// for example a locked keychain, and [PlatformFailure](Error::PlatformFailure))
+    /// and operations that gave up ([Timeout](Error::Timeout)) are transient.
+    /// All other errors are permanent: retrying without changing
+    /// anything will fail the same way.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::NoStorageAccess | ErrorKind::PlatformFailure | ErrorKind::Timeout
+        )
+    }
+
+    /// Whether this is an [Ambiguous](Error::Ambiguous) error.
+    pub fn is_ambiguous(&self) -> bool {
+        self.kind() == ErrorKind::Ambiguous
+    }
+
+    /// The wrappers for the matching credentials, if this is
+    /// an [Ambiguous](Error::Ambiguous) error.
+    pub fn ambiguous_entries(&self) -> Option<&[Entry]> {
+        match self {
+            Error::Ambiguous(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Make a copy of this error.
+    ///
+    /// Every variant is copied exactly, except that the platform errors
+    /// attached to [PlatformFailure](Error::PlatformFailure),
+    /// [NoStorageAccess](Error::NoStorageAccess), and
+    /// [BadDataFormat](Error::BadDataFormat) are replaced by errors
+    /// that only carry their `Display` text. So the copy is lossy:
+    /// a platform error in it can't be downcast to its original type,
+    /// and it has no source. The entries of an
+    /// [Ambiguous](Error::Ambiguous) error are cloned, so they refer to
+    /// the same credentials as the originals.
+    pub fn clone_shallow(&self) -> Error {
+        fn stringify(err: &PlatformError) -> PlatformError {
+            Box::from(err.to_string())
+        }
+        match self {
+            Error::PlatformFailure(err) => Error::PlatformFailure(stringify(err)),
+            Error::NoStorageAccess(err) => Error::NoStorageAccess(stringify(err)),
+            Error::NoEntry => Error::NoEntry,
+            Error::BadEncoding(bytes) => Error::BadEncoding(bytes.clone()),
+            Error::BadDataFormat(bytes, err) => Error::BadDataFormat(bytes.clone(), stringify(err)),
+            Error::TooLong(name, len) => Error::TooLong(name.clone(), *len),
+            Error::Invalid(attr, reason) => Error::Invalid(attr.clone(), reason.clone()),
+            Error::Ambiguous(entries) => Error::Ambiguous(entries.clone()),
+            Error::NoDefaultStore => Error::NoDefaultStore,
+            Error::NotSupportedByStore(vendor) => Error::NotSupportedByStore(vendor.clone()),
+            Error::Immutable => Error::Immutable,
+            Error::Timeout(what) => Error::Timeout(what.clone()),
+            Error::StoreGone => Error::StoreGone,
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -107,6 +221,9 @@ impl std::fmt::Display for Error {
             Error::NotSupportedByStore(vendor) => {
                 write!(f, "The store ({vendor}) does not support this operation",)
             }
+            Error::Immutable => write!(f, "The credential is immutable and can't be changed"),
+            Error::Timeout(what) => write!(f, "Gave up before completing: {what}"),
+            Error::StoreGone => write!(f, "The credential store has been dropped"),
         }
     }
 }
@@ -127,6 +244,17 @@ pub fn decode_password(bytes: Vec<u8>) -> Result<String> {
     String::from_utf8(bytes).map_err(|err| Error::BadEncoding(err.into_bytes()))
 }
 
+/// Try to interpret a byte slice as a big-endian `u64` counter
+pub fn decode_counter(bytes: &[u8]) -> Result<u64> {
+    match <[u8; 8]>::try_from(bytes) {
+        Ok(array) => Ok(u64::from_be_bytes(array)),
+        Err(_) => Err(Error::BadDataFormat(
+            bytes.to_vec(),
+            Box::from("a counter must be 8 bytes long"),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +271,144 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_clone_shallow() {
+        use std::sync::Arc;
+
+        use crate::{CredentialStore, mock};
+
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let io = || std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        let errors = vec![
+            Error::PlatformFailure(Box::new(io())),
+            Error::NoStorageAccess(Box::new(io())),
+            Error::NoEntry,
+            Error::BadEncoding(vec![0xff]),
+            Error::BadDataFormat(vec![1, 2], Box::new(io())),
+            Error::TooLong("user".to_string(), 8),
+            Error::Invalid("service".to_string(), "empty".to_string()),
+            Error::Ambiguous(vec![entry.clone(), entry.clone()]),
+            Error::NoDefaultStore,
+            Error::NotSupportedByStore("vendor".to_string()),
+            Error::Immutable,
+            Error::Timeout("update".to_string()),
+            Error::StoreGone,
+        ];
+        for err in errors {
+            let copy = err.clone_shallow();
+            // bad data format errors display their platform error's Debug text
+            if !matches!(err, Error::BadDataFormat(..)) {
+                assert_eq!(copy.to_string(), err.to_string());
+            }
+            assert_eq!(std::mem::discriminant(&copy), std::mem::discriminant(&err));
+            match (&err, &copy) {
+                (Error::PlatformFailure(e), Error::PlatformFailure(c))
+                | (Error::NoStorageAccess(e), Error::NoStorageAccess(c)) => {
+                    assert!(e.downcast_ref::<std::io::Error>().is_some());
+                    assert!(c.downcast_ref::<std::io::Error>().is_none());
+                    assert_eq!(c.to_string(), "gone");
+                }
+                (Error::BadDataFormat(eb, _), Error::BadDataFormat(cb, c)) => {
+                    assert_eq!(eb, cb);
+                    assert_eq!(c.to_string(), "gone");
+                }
+                (Error::Ambiguous(e), Error::Ambiguous(c)) => {
+                    assert_eq!(c.len(), 2);
+                    assert!(e[0] == c[0]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_kind() {
+        let cases = [
+            (
+                Error::PlatformFailure(Box::from("x")),
+                ErrorKind::PlatformFailure,
+            ),
+            (
+                Error::NoStorageAccess(Box::from("x")),
+                ErrorKind::NoStorageAccess,
+            ),
+            (Error::NoEntry, ErrorKind::NoEntry),
+            (Error::BadEncoding(vec![]), ErrorKind::BadEncoding),
+            (
+                Error::BadDataFormat(vec![], Box::from("x")),
+                ErrorKind::BadDataFormat,
+            ),
+            (Error::TooLong("user".to_string(), 8), ErrorKind::TooLong),
+            (
+                Error::Invalid("a".to_string(), "b".to_string()),
+                ErrorKind::Invalid,
+            ),
+            (Error::Ambiguous(vec![]), ErrorKind::Ambiguous),
+            (Error::NoDefaultStore, ErrorKind::NoDefaultStore),
+            (
+                Error::NotSupportedByStore("v".to_string()),
+                ErrorKind::NotSupportedByStore,
+            ),
+            (Error::Immutable, ErrorKind::Immutable),
+            (Error::Timeout("t".to_string()), ErrorKind::Timeout),
+            (Error::StoreGone, ErrorKind::StoreGone),
+        ];
+        let mut kinds = std::collections::HashSet::new();
+        for (err, kind) in cases {
+            assert_eq!(err.kind(), kind, "wrong kind for {err:?}");
+            assert!(kinds.insert(kind), "duplicate kind {kind:?}");
+        }
+    }
+
+    #[test]
+    fn test_transient() {
+        let cases = [
+            (Error::PlatformFailure(Box::from("x")), true),
+            (Error::NoStorageAccess(Box::from("x")), true),
+            (Error::NoEntry, false),
+            (Error::BadEncoding(vec![]), false),
+            (Error::BadDataFormat(vec![], Box::from("x")), false),
+            (Error::TooLong("user".to_string(), 8), false),
+            (Error::Invalid("a".to_string(), "b".to_string()), false),
+            (Error::Ambiguous(vec![]), false),
+            (Error::NoDefaultStore, false),
+            (Error::NotSupportedByStore("v".to_string()), false),
+            (Error::Immutable, false),
+            (Error::Timeout("t".to_string()), true),
+            (Error::StoreGone, false),
+        ];
+        for (err, transient) in cases {
+            assert_eq!(err.is_transient(), transient, "wrong class for {err:?}");
+            assert_eq!(err.is_ambiguous(), matches!(err, Error::Ambiguous(_)));
+            assert_eq!(err.ambiguous_entries().is_some(), err.is_ambiguous());
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_entries() {
+        use std::sync::Arc;
+
+        use crate::{CredentialStore, mock};
+
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let err = Error::Ambiguous(vec![entry.clone(), entry.clone()]);
+        let entries = err.ambiguous_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0] == entry);
+    }
+
+    #[test]
+    fn test_decode_counter() {
+        assert_eq!(decode_counter(&[0, 0, 0, 0, 0, 0, 1, 2]).unwrap(), 258);
+        assert_eq!(decode_counter(&u64::MAX.to_be_bytes()).unwrap(), u64::MAX);
+        for bytes in [vec![], vec![1u8; 7], vec![1u8; 9]] {
+            match decode_counter(&bytes) {
+                Err(Error::BadDataFormat(data, _)) => assert_eq!(data, bytes),
+                other => panic!("Bad counter ({bytes:?}) decode gave wrong result: {other:?}"),
+            }
+        }
+    }
 }
@@ -0,0 +1,423 @@
+/*!
+
+# Fallback-on-lock store
+
+This module provides a credential store combinator that wraps a primary
+credential store and a fallback store. Entries built by the combinator
+read from the primary store but, if the primary store reports that it
+can't be accessed (typically because it is locked), they read from the
+fallback store instead. Writes always go to the primary store.
+
+This keeps an app usable while a keychain is locked, provided the app has
+copied the credentials it needs into the fallback store (for example, an
+in-memory cache) while the keychain was unlocked:
+
+```rust
+# use keyring_core::{Entry, api::CredentialStoreApi, fallback::FallbackOnLockStore, mock};
+let primary = mock::Store::new().unwrap();
+let cache = mock::Store::new().unwrap();
+let cached = cache.build("my-service", "my-user", None).unwrap();
+cached.set_password("cached password").unwrap();
+keyring_core::set_default_store(FallbackOnLockStore::new(primary, cache).unwrap());
+let entry = Entry::new("my-service", "my-user").unwrap();
+entry.set_password("keychain password").unwrap();
+// while the keychain is unlocked, reads come from it
+assert_eq!(entry.get_password().unwrap(), "keychain password");
+```
+
+The primary store is considered locked when one of its operations fails
+with a [NoStorageAccess](Error::NoStorageAccess) error. Any other error
+from the primary store is returned as is.
+
+Only entries built by the combinator fall back. Searches fall back
+to the fallback store when the primary is locked, but the entries they
+return belong to whichever store was searched.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::{CredentialApi, CredentialStoreApi};
+use crate::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, Entry, Error, Result,
+};
+
+/// A store that reads from a fallback store when its primary store is locked.
+pub struct FallbackOnLockStore {
+    pub id: String,
+    pub primary: Arc<CredentialStore>,
+    pub fallback: Arc<CredentialStore>,
+}
+
+impl std::fmt::Debug for FallbackOnLockStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackOnLockStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("primary", &self.primary)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl FallbackOnLockStore {
+    /// Combine the `primary` store with a `fallback` store used while it's locked.
+    pub fn new(primary: Arc<CredentialStore>, fallback: Arc<CredentialStore>) -> Result<Arc<Self>> {
+        Ok(Arc::new(FallbackOnLockStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            primary,
+            fallback,
+        }))
+    }
+}
+
+impl CredentialStoreApi for FallbackOnLockStore {
+    fn vendor(&self) -> String {
+        String::from("Fallback-on-lock store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The members are those of the primary store followed by those of the fallback.
+    fn member_vendors(&self) -> Vec<String> {
+        let mut vendors = self.primary.member_vendors();
+        vendors.extend(self.fallback.member_vendors());
+        vendors
+    }
+
+    /// Build an entry in both the primary and the fallback store.
+    ///
+    /// The modifiers are only passed to the primary store, because
+    /// they are used when creating credentials and the fallback store
+    /// is never written.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let primary = self.primary.build(service, user, mods)?;
+        let fallback = self.fallback.build(service, user, None)?;
+        Ok(Entry::new_with_credential(Arc::new(FallbackCred {
+            primary: primary.inner,
+            fallback: fallback.inner,
+        })))
+    }
+
+    /// Search the primary store or, if it's locked, the fallback store.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        match self.primary.search(spec) {
+            Err(Error::NoStorageAccess(_)) => self.fallback.search(spec),
+            result => result,
+        }
+    }
+
+    /// List the credentials in the primary store or, if it's locked, the fallback store.
+    fn all(&self) -> Result<Vec<Entry>> {
+        match self.primary.all() {
+            Err(Error::NoStorageAccess(_)) => self.fallback.all(),
+            result => result,
+        }
+    }
+
+    /// Count the credentials in the primary store or, if it's locked, the fallback store.
+    fn count(&self) -> Result<usize> {
+        match self.primary.count() {
+            Err(Error::NoStorageAccess(_)) => self.fallback.count(),
+            result => result,
+        }
+    }
+
+    /// Clears always go to the primary store.
+    fn clear(&self) -> Result<usize> {
+        self.primary.clear()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// Credentials are written to the primary store, so they have its persistence.
+    fn persistence(&self) -> CredentialPersistence {
+        self.primary.persistence()
+    }
+
+    /// Credentials are written to the primary store, so they have its capabilities.
+    fn capabilities(&self) -> Capabilities {
+        self.primary.capabilities()
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential that reads from its fallback while its primary is locked.
+#[derive(Debug)]
+pub struct FallbackCred {
+    pub primary: Arc<Credential>,
+    pub fallback: Arc<Credential>,
+}
+
+impl FallbackCred {
+    /// Try a read on the primary credential, then on the fallback if the primary is locked.
+    fn read<T>(&self, f: impl Fn(&Credential) -> Result<T>) -> Result<T> {
+        match f(self.primary.as_ref()) {
+            Err(Error::NoStorageAccess(_)) => f(self.fallback.as_ref()),
+            result => result,
+        }
+    }
+
+    /// Get a wrapper with `f`, keeping the other credential of the pair.
+    ///
+    /// If the primary is locked, the wrapper's primary is this credential's.
+    fn wrap(
+        &self,
+        f: impl Fn(&Credential) -> Result<Option<Arc<Credential>>>,
+    ) -> Result<Option<Arc<Credential>>> {
+        match f(self.primary.as_ref()) {
+            Ok(None) => Ok(None),
+            Ok(Some(primary)) => Ok(Some(Arc::new(FallbackCred {
+                primary,
+                fallback: self.fallback.clone(),
+            }))),
+            Err(Error::NoStorageAccess(_)) => {
+                let fallback = f(self.fallback.as_ref())?.unwrap_or_else(|| self.fallback.clone());
+                Ok(Some(Arc::new(FallbackCred {
+                    primary: self.primary.clone(),
+                    fallback,
+                })))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl CredentialApi for FallbackCred {
+    /// Writes always go to the primary credential.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.primary.set_secret(secret)
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        self.read(|cred| cred.get_secret())
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.read(|cred| cred.get_attributes())
+    }
+
+    /// See the API docs.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        self.read(|cred| cred.attribute_keys())
+    }
+
+    /// See the API docs.
+    fn has_empty_secret(&self) -> Result<bool> {
+        self.read(|cred| cred.has_empty_secret())
+    }
+
+    /// See the API docs.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_created())
+    }
+
+    /// See the API docs.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        self.read(|cred| cred.get_modified())
+    }
+
+    /// Writes always go to the primary credential.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.primary.update_attributes(attrs)
+    }
+
+    /// Writes always go to the primary credential, so this compares against it.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        self.primary.compare_and_set(expected, new)
+    }
+
+    /// Deletes always go to the primary credential.
+    fn delete_credential(&self) -> Result<()> {
+        self.primary.delete_credential()
+    }
+
+    /// Renames always go to the primary credential.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        self.primary.rename(new_service, new_user)
+    }
+
+    /// See the API docs.
+    ///
+    /// A wrapper from the primary store keeps this credential's fallback.
+    /// If the primary is locked, the wrapper keeps this credential's primary,
+    /// and reads from the fallback store's wrapper, so writes through it
+    /// still go to the primary store.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        self.wrap(|cred| cred.get_credential())
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper is made as it is by [get_credential](CredentialApi::get_credential).
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        self.wrap(|cred| cred.get_primary_credential())
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.primary.get_specifiers()
+    }
+
+    /// See the API docs.
+    fn is_specifier(&self) -> bool {
+        self.primary.is_specifier()
+    }
+
+    /// See the API docs.
+    fn is_wrapper(&self) -> bool {
+        self.primary.is_wrapper()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::{FallbackCred, FallbackOnLockStore};
+    use crate::{CredentialStore, Error, mock};
+
+    fn lock(entry: &crate::Entry) {
+        let cred: &FallbackCred = entry.as_any().downcast_ref().unwrap();
+        let mock: &mock::Cred = cred.primary.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+    }
+
+    #[test]
+    fn test_reads_fall_back_when_locked() {
+        let primary: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let fallback: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            FallbackOnLockStore::new(primary, fallback.clone()).unwrap();
+        let cached = fallback.build("service", "user", None).unwrap();
+        cached.set_password("cached").unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("primary").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "primary");
+        lock(&entry);
+        assert_eq!(entry.get_password().unwrap(), "cached");
+        assert_eq!(entry.get_password().unwrap(), "primary");
+        lock(&entry);
+        assert!(!entry.has_empty_secret().unwrap());
+        lock(&entry);
+        let wrapper = entry.get_credential().unwrap();
+        lock(&wrapper);
+        assert_eq!(wrapper.get_password().unwrap(), "cached");
+        // a wrapper got while the primary was locked still writes to the primary
+        lock(&entry);
+        let wrapper = entry.get_credential().unwrap();
+        wrapper.set_password("new").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "new");
+        assert_eq!(cached.get_password().unwrap(), "cached");
+    }
+
+    #[test]
+    fn test_writes_go_to_primary() {
+        let primary: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let fallback: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            FallbackOnLockStore::new(primary.clone(), fallback.clone()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("primary").unwrap();
+        let cached = fallback.build("service", "user", None).unwrap();
+        assert!(matches!(cached.get_password(), Err(Error::NoEntry)));
+        lock(&entry);
+        assert!(matches!(
+            entry.set_password("while locked"),
+            Err(Error::NoStorageAccess(_))
+        ));
+        assert!(matches!(cached.get_password(), Err(Error::NoEntry)));
+        let direct = primary.build("service", "user", None).unwrap();
+        assert_eq!(direct.get_password().unwrap(), "primary");
+        entry.delete_credential().unwrap();
+        assert!(matches!(direct.get_password(), Err(Error::NoEntry)));
+    }
+
+    #[test]
+    fn test_other_errors_do_not_fall_back() {
+        let primary: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let fallback: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            FallbackOnLockStore::new(primary, fallback.clone()).unwrap();
+        fallback
+            .build("service", "user", None)
+            .unwrap()
+            .set_password("cached")
+            .unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        assert_eq!(store.member_vendors().len(), 2);
+        let found = store.search(&HashMap::new()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(matches!(found[0].get_password(), Err(Error::NoEntry)));
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_rename_goes_to_primary() {
+        use crate::sample;
+
+        let primary: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let fallback: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            FallbackOnLockStore::new(primary.clone(), fallback.clone()).unwrap();
+        let entry = store.build("service", "old", None).unwrap();
+        entry.set_password("primary").unwrap();
+        let cached = fallback.build("service", "old", None).unwrap();
+        cached.set_password("cached").unwrap();
+        entry.rename("service", "new").unwrap();
+        let moved = primary.build("service", "new", None).unwrap();
+        assert_eq!(moved.get_password().unwrap(), "primary");
+        assert_eq!(cached.get_password().unwrap(), "cached");
+    }
+
+    #[test]
+    fn test_store_wide_operations() {
+        let primary: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let fallback: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            FallbackOnLockStore::new(primary.clone(), fallback.clone()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("primary").unwrap();
+        for user in ["user1", "user2"] {
+            let cached = fallback.build("service", user, None).unwrap();
+            cached.set_password("cached").unwrap();
+        }
+        assert_eq!(store.count().unwrap(), 1);
+        assert_eq!(store.all().unwrap().len(), 1);
+        assert_eq!(store.clear().unwrap(), 1);
+        assert_eq!(primary.count().unwrap(), 0);
+        // the mock fallback store counts the entry built in it, too
+        assert_eq!(fallback.count().unwrap(), 3);
+    }
+}
@@ -26,57 +26,278 @@ to be both Send and Sync, the underlying credential
 stores may not handle access to a single credential
 from different threads reliably.
 See the documentation of each credential store for details.
+
+## Logging
+
+This crate logs its operations with the `log` crate. If the `tracing`
+feature is enabled, each operation on an entry that reads or writes its
+credential also runs in a `tracing` span named for the operation, with
+the entry's `service`, `user`, and store `vendor` as fields, so those
+calls can be correlated with the rest of a request.
  */
 
-use log::debug;
-use std::collections::HashMap;
+use log::{debug, warn};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 pub mod api;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod attributes;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod chain;
 pub mod error;
 
+pub mod fallback;
 pub mod mock;
+pub mod modifiers;
+pub mod recording;
+mod secret;
+pub mod test_util;
+pub mod weak;
 
 #[cfg(feature = "sample")]
 pub mod sample;
 
-pub use api::{Credential, CredentialPersistence, CredentialStore};
-pub use error::{Error, Result};
+pub use api::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, SetOutcome, StoreKind,
+};
+pub use error::{Error, ErrorKind, Result};
+#[cfg(feature = "zeroize")]
+pub use secret::Secret;
+
+/// Enter a span for an operation on an entry, if the `tracing` feature is enabled.
+///
+/// The span is named for the operation and has the entry's
+/// service name, username, and store vendor as fields.
+#[cfg(feature = "tracing")]
+macro_rules! entry_span {
+    ($entry:expr, $op:literal) => {{
+        let (service, user) = $entry.get_specifiers().unwrap_or_default();
+        let vendor = $entry.vendor().unwrap_or_default();
+        tracing::debug_span!($op, service, user, vendor).entered()
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! entry_span {
+    ($entry:expr, $op:literal) => {
+        NoSpan
+    };
+}
+
+/// What [entry_span] returns when the `tracing` feature isn't enabled.
+#[cfg(not(feature = "tracing"))]
+struct NoSpan;
 
 #[derive(Default, Debug)]
 struct DefaultStore {
-    inner: Option<Arc<CredentialStore>>,
+    /// The stack of pushed stores, whose top is the default store.
+    inner: Vec<Arc<CredentialStore>>,
+    fallback: Option<Arc<CredentialStore>>,
+}
+
+impl DefaultStore {
+    /// The store in effect: the explicit default if set, else the fallback.
+    fn effective(&self) -> Option<&Arc<CredentialStore>> {
+        self.inner.last().or(self.fallback.as_ref())
+    }
 }
 
-static DEFAULT_STORE: std::sync::RwLock<DefaultStore> =
-    std::sync::RwLock::new(DefaultStore { inner: None });
+static DEFAULT_STORE: std::sync::RwLock<DefaultStore> = std::sync::RwLock::new(DefaultStore {
+    inner: Vec::new(),
+    fallback: None,
+});
+
+/// Recover from a poisoned lock on the default store.
+///
+/// The default store is a stack of stores plus a fallback, which are
+/// only changed by pushes, pops, truncations, and assignments. A panic
+/// can't leave any of those half done (and an `init` function that panics
+/// hasn't pushed anything yet), so every store left in it is one that
+/// was really set, and it's safe to keep using it.
+fn recover<G>(poisoned: std::sync::PoisonError<G>) -> G {
+    warn!("recovering the default store from a thread that panicked while holding its lock");
+    DEFAULT_STORE.clear_poison();
+    poisoned.into_inner()
+}
+
+fn read_default_store() -> std::sync::RwLockReadGuard<'static, DefaultStore> {
+    DEFAULT_STORE.read().unwrap_or_else(recover)
+}
+
+fn write_default_store() -> std::sync::RwLockWriteGuard<'static, DefaultStore> {
+    DEFAULT_STORE.write().unwrap_or_else(recover)
+}
+
+thread_local! {
+    static THREAD_DEFAULT_STORE: RefCell<Option<Arc<CredentialStore>>> = const { RefCell::new(None) };
+}
+
+/// The default store in effect on this thread, if any.
+///
+/// This is the thread's own default store if it has one,
+/// else the default store, else the fallback default store.
+fn effective_default_store() -> Option<Arc<CredentialStore>> {
+    if let Some(store) = get_thread_default_store() {
+        return Some(store);
+    }
+    let guard = read_default_store();
+    guard.effective().cloned()
+}
 
 /// Set the credential store used by default to create entries.
 ///
 /// This is meant for use by clients who use one credential store.
 /// If you are using multiple credential stores and want
 /// precise control over which credential is in which store,
-/// you may prefer to have your store build entries directly.
+/// you may prefer to have your store build entries directly,
+/// for example with [Entry::new_in].
 ///
 /// This will block waiting for all other threads currently creating entries
 /// to complete what they are doing. It's really meant to be called
 /// at startup before creating any entries.
+///
+/// If stores have been [pushed](push_default_store),
+/// this replaces the one on top.
 pub fn set_default_store(new: Arc<CredentialStore>) {
     debug!("setting the default credential store to {new:?}");
-    let mut guard = DEFAULT_STORE
-        .write()
-        .expect("Poisoned RwLock in keyring_core::set_default_store: please report a bug!");
-    guard.inner = Some(new);
+    let mut guard = write_default_store();
+    guard.inner.pop();
+    guard.inner.push(new);
+}
+
+/// Temporarily install a credential store as the default.
+///
+/// The store is pushed on top of the default store (if any), and
+/// the returned guard restores the previous default when it is dropped:
+///
+/// ```rust
+/// # use keyring_core::{mock, get_default_store, push_default_store};
+/// {
+///     let _guard = push_default_store(mock::Store::new().unwrap());
+///     assert!(get_default_store().is_some());
+/// }
+/// // the previous default (if any) is back in effect here
+/// ```
+///
+/// Guards are meant to be dropped in the reverse order of their pushes.
+/// If one is dropped out of order, the stores pushed after it are released
+/// along with its own, and a guard dropped after that restores the
+/// value that was the default when its store was pushed.
+pub fn push_default_store(new: Arc<CredentialStore>) -> DefaultStoreGuard {
+    debug!("pushing {new:?} as the default credential store");
+    let mut guard = write_default_store();
+    let previous = guard.inner.last().cloned();
+    let depth = guard.inner.len();
+    guard.inner.push(new);
+    DefaultStoreGuard { depth, previous }
+}
+
+/// Restores the previous default store when dropped.
+///
+/// See [push_default_store].
+#[must_use = "the pushed store is popped as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct DefaultStoreGuard {
+    depth: usize,
+    previous: Option<Arc<CredentialStore>>,
+}
+
+impl Drop for DefaultStoreGuard {
+    fn drop(&mut self) {
+        debug!(
+            "restoring the default credential store to {:?}",
+            self.previous
+        );
+        let mut guard = write_default_store();
+        guard.inner.truncate(self.depth);
+        if guard.inner.len() < self.depth {
+            // a guard pushed before this one was dropped first
+            if let Some(previous) = self.previous.take() {
+                guard.inner.push(previous);
+            }
+        }
+    }
+}
+
+/// Get the default credential store, setting it first if it hasn't been set.
+///
+/// If a default store has been set with [set_default_store]
+/// (or by an earlier call to this function), that store is returned
+/// and `init` is not called. Otherwise, `init` is called to make the
+/// store, which becomes the default store and is returned.
+/// A [fallback default store](set_fallback_default_store)
+/// doesn't count as having set the default store.
+///
+/// Unlike [set_default_store], this is idempotent, so it can be called
+/// from each place that needs the default store without a `std::sync::Once`.
+/// The lock on the default store is held while `init` runs, so `init` is
+/// called at most once no matter how many threads call this at the same
+/// time. This means `init` must not itself use the default store.
+///
+/// # Errors
+///
+/// If `init` fails, its error is returned and no default store is set.
+pub fn get_or_init_default_store(
+    init: impl FnOnce() -> Result<Arc<CredentialStore>>,
+) -> Result<Arc<CredentialStore>> {
+    debug!("getting or initializing the default credential store");
+    let mut guard = write_default_store();
+    if let Some(store) = guard.inner.last() {
+        return Ok(store.clone());
+    }
+    let store = init()?;
+    debug!("initialized the default credential store to {store:?}");
+    guard.inner.push(store.clone());
+    Ok(store)
 }
 
 /// Get the default credential store.
+///
+/// If this thread has its own [default store](set_thread_default_store),
+/// that is returned. Otherwise, if no default store has been set, this returns
+/// the [fallback default store](set_fallback_default_store), if any.
 pub fn get_default_store() -> Option<Arc<CredentialStore>> {
     debug!("getting the default credential store");
-    let guard = DEFAULT_STORE
-        .read()
-        .expect("Poisoned RwLock in keyring_core::get_default_store: please report a bug!");
-    guard.inner.clone()
+    effective_default_store()
+}
+
+/// Get the default credential store, waiting no longer than `timeout` to do so.
+///
+/// This is like [get_default_store], except that it doesn't wait
+/// indefinitely if another thread is holding the lock on the default
+/// store (for example, because it is in the middle of replacing it).
+///
+/// # Errors
+///
+/// Returns a [Timeout](Error::Timeout) error if the lock on the
+/// default store can't be acquired within `timeout`.
+pub fn try_get_default_store(timeout: Duration) -> Result<Option<Arc<CredentialStore>>> {
+    debug!("getting the default credential store within {timeout:?}");
+    if let Some(store) = get_thread_default_store() {
+        return Ok(Some(store));
+    }
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        match DEFAULT_STORE.try_read() {
+            Ok(guard) => return Ok(guard.effective().cloned()),
+            Err(std::sync::TryLockError::Poisoned(poisoned)) => {
+                return Ok(recover(poisoned).effective().cloned());
+            }
+            Err(std::sync::TryLockError::WouldBlock) => {
+                if std::time::Instant::now() >= deadline {
+                    return Err(Error::Timeout(format!(
+                        "lock on the default store after {timeout:?}"
+                    )));
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
 }
 
 /// Release the default credential store.
@@ -86,12 +307,159 @@ pub fn get_default_store() -> Option<Arc<CredentialStore>> {
 /// is kept in a static variable, not releasing it will cause
 /// your credential store never to be released, which may have
 /// unintended side effects.
+///
+/// If stores have been [pushed](push_default_store), this releases
+/// the one on top, and the one below it becomes the default again.
 pub fn unset_default_store() -> Option<Arc<CredentialStore>> {
     debug!("unsetting the default credential store");
-    let mut guard = DEFAULT_STORE
-        .write()
-        .expect("Poisoned RwLock in keyring_core::unset_default_store: please report a bug!");
-    guard.inner.take()
+    let mut guard = write_default_store();
+    guard.inner.pop()
+}
+
+/// Set the credential store used by default when no default store has been set.
+///
+/// This is meant for use by libraries that want to work without any setup
+/// by their clients, while still letting apps choose the default store.
+/// The precedence is:
+///
+/// 1. the store set on the current thread with [set_thread_default_store], if any;
+/// 2. otherwise, the store set with [set_default_store], if any;
+/// 3. otherwise, the fallback store set with this function, if any;
+/// 4. otherwise, there is no default store, and operations that need one fail
+///    with a [NoDefaultStore](Error::NoDefaultStore) error.
+pub fn set_fallback_default_store(new: Arc<CredentialStore>) {
+    debug!("setting the fallback default credential store to {new:?}");
+    let mut guard = write_default_store();
+    guard.fallback = Some(new);
+}
+
+/// Release the fallback default credential store.
+///
+/// This returns the old value for the fallback store and forgets what it was.
+/// It doesn't affect a default store set with [set_default_store].
+pub fn unset_fallback_default_store() -> Option<Arc<CredentialStore>> {
+    debug!("unsetting the fallback default credential store");
+    let mut guard = write_default_store();
+    guard.fallback.take()
+}
+
+/// Set the credential store used by default on the current thread.
+///
+/// While it is set, this store takes precedence over both the
+/// [default store](set_default_store) and the
+/// [fallback default store](set_fallback_default_store) for every
+/// operation on this thread that uses the default store, such as
+/// [Entry::new] and [Entry::search]. Other threads are not affected.
+///
+/// This is meant for tests that run in parallel, each of which
+/// wants its own default store.
+pub fn set_thread_default_store(new: Arc<CredentialStore>) {
+    debug!("setting the thread's default credential store to {new:?}");
+    THREAD_DEFAULT_STORE.with(|cell| *cell.borrow_mut() = Some(new));
+}
+
+/// Get the credential store used by default on the current thread, if one has been set.
+///
+/// Unlike [get_default_store], this doesn't fall back to the
+/// process-wide default stores.
+pub fn get_thread_default_store() -> Option<Arc<CredentialStore>> {
+    THREAD_DEFAULT_STORE.with(|cell| cell.borrow().clone())
+}
+
+/// Release the credential store used by default on the current thread.
+///
+/// This returns the old value and forgets what it was, so that
+/// the process-wide default stores are used again on this thread.
+pub fn unset_thread_default_store() -> Option<Arc<CredentialStore>> {
+    debug!("unsetting the thread's default credential store");
+    THREAD_DEFAULT_STORE.with(|cell| cell.borrow_mut().take())
+}
+
+/// List the credential stores that this crate is holding on to.
+///
+/// Each store is described by a pair of its role and its vendor.
+/// The roles are `default`, for the store set with [set_default_store],
+/// and `fallback`, for the store set with [set_fallback_default_store].
+/// Stores that aren't set are left out.
+pub fn installed_stores() -> Vec<(String, String)> {
+    debug!("listing the installed credential stores");
+    let guard = read_default_store();
+    let mut result = Vec::new();
+    if let Some(store) = guard.inner.last() {
+        result.push(("default".to_string(), store.vendor()));
+    }
+    if let Some(store) = guard.fallback.as_ref() {
+        result.push(("fallback".to_string(), store.vendor()));
+    }
+    result
+}
+
+/// Get the persistence of the default credential store.
+///
+/// This is a shortcut for calling [persistence](api::CredentialStoreApi::persistence)
+/// on the result of [get_default_store], so clients can tell
+/// whether the credentials they create by default will be saved.
+///
+/// # Errors
+///
+/// Returns a [NoDefaultStore][Error::NoDefaultStore] error
+/// if neither a default store nor a fallback default store has been set.
+pub fn default_store_persistence() -> Result<CredentialPersistence> {
+    debug!("getting the persistence of the default credential store");
+    match effective_default_store() {
+        Some(store) => Ok(store.persistence()),
+        None => Err(Error::NoDefaultStore),
+    }
+}
+
+/// Update the same attributes on each of the given entries.
+///
+/// Each entry is updated as by [update_attributes](Entry::update_attributes),
+/// and a failure on one entry doesn't stop the others from being updated.
+/// Returns each entry paired with the result of its update,
+/// in the order the entries were given.
+pub fn update_attributes_many(
+    entries: &[Entry],
+    attrs: &HashMap<&str, &str>,
+) -> Vec<(Entry, Result<()>)> {
+    debug!("update attributes on {} entries", entries.len());
+    entries
+        .iter()
+        .map(|entry| {
+            let result = entry.update_attributes(attrs);
+            let entry = Entry {
+                inner: entry.inner.clone(),
+            };
+            (entry, result)
+        })
+        .collect()
+}
+
+/// Check each entry's secret against a candidate.
+///
+/// Each pair is checked as by [verify_secret](Entry::verify_secret),
+/// and a failure to read one entry's secret doesn't stop the others
+/// from being checked. Returns each entry paired with the result of
+/// its check, in the order the pairs were given.
+pub fn verify_many(pairs: &[(&Entry, &[u8])]) -> Vec<(Entry, Result<bool>)> {
+    debug!("verify secrets of {} entries", pairs.len());
+    pairs
+        .iter()
+        .map(|(entry, candidate)| {
+            let result = entry.verify_secret(candidate);
+            let entry = Entry {
+                inner: entry.inner.clone(),
+            };
+            (entry, result)
+        })
+        .collect()
+}
+
+#[cfg(feature = "fingerprint")]
+fn fingerprint(secret: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(secret).into()
 }
 
 fn build_default_credential(
@@ -99,21 +467,39 @@ fn build_default_credential(
     user: &str,
     attrs: Option<&HashMap<&str, &str>>,
 ) -> Result<Entry> {
-    let guard = DEFAULT_STORE
-        .read()
-        .expect("Poisoned RwLock in keyring-core::build_default_credential: please report a bug!");
-    match guard.inner.as_ref() {
+    match effective_default_store() {
         Some(store) => store.build(service, user, attrs),
         None => Err(Error::NoDefaultStore),
     }
 }
 
+/// Attribute values merged across several credentials.
+///
+/// Each attribute name maps to the set of distinct values seen for it.
+/// See [merged_attributes](Entry::merged_attributes).
+pub type MergedAttributes = HashMap<String, BTreeSet<String>>;
+
 /// A named entry in a credential store.
-#[derive(Debug)]
+///
+/// Cloning an entry gives another handle on the same credential.
+#[derive(Debug, Clone)]
 pub struct Entry {
     inner: Arc<Credential>,
 }
 
+/// Entries are equal if they refer to the same underlying credential.
+///
+/// Equality means "same credential," not "same secret": entries for
+/// different credentials with identical secrets are not equal. Equality
+/// is decided by the store (see
+/// [is_same_credential](api::CredentialApi::is_same_credential)), and some
+/// stores can't decide it, so an entry need not be equal even to itself.
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.is_same_credential(other.inner.as_ref())
+    }
+}
+
 impl Entry {
     /// Create an entry for the given `service` and `user`.
     ///
@@ -126,7 +512,11 @@ impl Entry {
     /// acceptable to the default credential store.
     ///
     /// Returns a [NoDefaultStore][Error::NoDefaultStore] error
-    /// if the default credential store has not been set.
+    /// if neither a default store nor a fallback default store has been set.
+    ///
+    /// The entry is built by the store that is the default when this is
+    /// called, and it keeps using that store for its whole life. Changing
+    /// or unsetting the default store later has no effect on it.
     pub fn new(service: &str, user: &str) -> Result<Entry> {
         debug!("creating entry with service {service}, user {user}");
         let entry = build_default_credential(service, user, None)?;
@@ -139,7 +529,8 @@ impl Entry {
     /// The default credential builder is used.
     ///
     /// See the documentation for each credential store to understand what
-    /// modifiers may be specified for that store.
+    /// modifiers may be specified for that store. As with [new](Entry::new),
+    /// the entry keeps using the store that was the default when it was built.
     ///
     /// # Errors
     ///
@@ -148,7 +539,7 @@ impl Entry {
     /// acceptable to the default credential store.
     ///
     /// Returns a [NoDefaultStore][Error::NoDefaultStore] error
-    /// if the default credential store has not been set.
+    /// if neither a default store nor a fallback default store has been set.
     pub fn new_with_modifiers(
         service: &str,
         user: &str,
@@ -178,18 +569,74 @@ impl Entry {
     /// if the `spec` value is not acceptable to the default credential store.
     ///
     /// Returns a [NoDefaultStore][Error::NoDefaultStore] error
-    /// if the default credential store has not been set.
+    /// if neither a default store nor a fallback default store has been set.
     pub fn search(spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
         debug!("searching for {spec:?}");
-        let guard = DEFAULT_STORE.read().expect(
-            "Poisoned RwLock in keyring-core::search_for_credentials: please report a bug!",
-        );
-        match guard.inner.as_ref() {
+        match effective_default_store() {
             Some(store) => store.search(spec),
             None => Err(Error::NoDefaultStore),
         }
     }
 
+    /// Create an entry for the given `service` and `user` in the given `store`.
+    ///
+    /// This is like [new](Entry::new), but the entry is built by `store`,
+    /// and the default store is never consulted (it needn't even be set).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [Invalid][Error::Invalid] error
+    /// if the `service` or `user` values are not
+    /// acceptable to the store.
+    pub fn new_in(store: &Arc<CredentialStore>, service: &str, user: &str) -> Result<Entry> {
+        debug!("creating entry with service {service}, user {user} in {store:?}");
+        let entry = store.build(service, user, None)?;
+        debug!("created entry {:?}", entry.inner);
+        Ok(entry)
+    }
+
+    /// Create an entry for the given `service` and `user` in the given `store`,
+    /// passing store-specific modifiers.
+    ///
+    /// This is like [new_with_modifiers](Entry::new_with_modifiers),
+    /// but the entry is built by `store`, and the default store is never consulted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [Invalid][Error::Invalid] error
+    /// if the `service`, `user`, or `modifier` pairs are not
+    /// acceptable to the store.
+    pub fn new_in_with_modifiers(
+        store: &Arc<CredentialStore>,
+        service: &str,
+        user: &str,
+        modifiers: &HashMap<&str, &str>,
+    ) -> Result<Entry> {
+        debug!(
+            "creating entry with service {service}, user {user}, and mods {modifiers:?} in {store:?}"
+        );
+        let entry = store.build(service, user, Some(modifiers))?;
+        debug!("created entry {:?}", entry.inner);
+        Ok(entry)
+    }
+
+    /// Search for credentials in the given `store`, returning entries that wrap any found.
+    ///
+    /// This is like [search](Entry::search), but `store` is searched,
+    /// and the default store is never consulted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [Invalid][Error::Invalid] error
+    /// if the `spec` value is not acceptable to the store.
+    pub fn search_in(
+        store: &Arc<CredentialStore>,
+        spec: &HashMap<&str, &str>,
+    ) -> Result<Vec<Entry>> {
+        debug!("searching for {spec:?} in {store:?}");
+        store.search(spec)
+    }
+
     /// Set the password for this entry.
     ///
     /// If a credential for this entry already exists in the store,
@@ -210,10 +657,42 @@ impl Entry {
     /// all stores support empty passwords, and some have length limits),
     /// then an [Invalid](Error::Invalid) error is returned.
     pub fn set_password(&self, password: &str) -> Result<()> {
+        let _span = entry_span!(self, "set_password");
         debug!("set password for entry {:?}", self.inner);
         self.inner.set_password(password)
     }
 
+    /// Retrieve the secret saved for this entry, in a [Secret]
+    /// that wipes it from memory when dropped.
+    ///
+    /// Use this instead of [get_secret](Entry::get_secret) when
+    /// the secret shouldn't linger in memory after it's used.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_secret](Entry::get_secret).
+    #[cfg(feature = "zeroize")]
+    pub fn get_secret_zeroizing(&self) -> Result<Secret> {
+        let _span = entry_span!(self, "get_secret_zeroizing");
+        debug!("get zeroizing secret from entry {:?}", self.inner);
+        Ok(Secret::new(self.inner.get_secret()?))
+    }
+
+    /// Set the password for this entry, and report whether
+    /// a credential was created or an existing one updated.
+    ///
+    /// Not every store can tell which happened; those that can't
+    /// report [SetOutcome::Unspecified].
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [set_password](Entry::set_password).
+    pub fn set_password_reporting(&self, password: &str) -> Result<SetOutcome> {
+        let _span = entry_span!(self, "set_password_reporting");
+        debug!("set password (reporting) for entry {:?}", self.inner);
+        self.inner.set_secret_reporting(password.as_bytes())
+    }
+
     /// Set the secret for this entry.
     ///
     /// If a credential for this entry already exists in the store,
@@ -234,10 +713,238 @@ impl Entry {
     /// all stores support empty passwords, and some have length limits),
     /// then an [Invalid](Error::Invalid) error is returned.
     pub fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        let _span = entry_span!(self, "set_secret");
         debug!("set secret for entry {:?}", self.inner);
         self.inner.set_secret(secret)
     }
 
+    /// Set the password for this entry, unless it already has that password.
+    ///
+    /// Returns whether the password was written. This allows
+    /// idempotent callers to tell whether anything changed.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [set_password](Entry::set_password).
+    pub fn set_password_if_changed(&self, password: &str) -> Result<bool> {
+        let _span = entry_span!(self, "set_password_if_changed");
+        debug!("set password if changed for entry {:?}", self.inner);
+        self.inner.set_secret_if_changed(password.as_bytes())
+    }
+
+    /// Set the secret for this entry, unless it already has that secret.
+    ///
+    /// Returns whether the secret was written. This allows
+    /// idempotent callers to tell whether anything changed.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [set_secret](Entry::set_secret).
+    pub fn set_secret_if_changed(&self, secret: &[u8]) -> Result<bool> {
+        let _span = entry_span!(self, "set_secret_if_changed");
+        debug!("set secret if changed for entry {:?}", self.inner);
+        self.inner.set_secret_if_changed(secret)
+    }
+
+    /// Replace the secret saved for this entry, but only if it is `expected`.
+    ///
+    /// An `expected` value of `None` means that there must be no credential
+    /// for this entry, and a `new` value of `None` deletes the credential.
+    /// Returns whether the secret was as expected (and so was replaced).
+    /// Whether the comparison and the change are atomic depends on the store.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [set_secret](Entry::set_secret)
+    /// or [delete_credential](Entry::delete_credential), whichever applies.
+    pub fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        let _span = entry_span!(self, "compare_and_set");
+        debug!("compare and set secret for entry {:?}", self.inner);
+        self.inner.compare_and_set(expected, new)
+    }
+
+    /// Update the secret saved for this entry by applying `f` to it.
+    ///
+    /// The function is given the current secret (or `None` if there is no
+    /// credential) and returns the new one (or `None` to delete the credential).
+    /// The update is made with [compare_and_set](Entry::compare_and_set), so if
+    /// another writer changes the secret after it was read, `f` is applied
+    /// again to the changed secret, up to `max_attempts` times in all.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [Timeout](Error::Timeout) error if the secret was changed
+    /// by another writer on every attempt. Otherwise, the error cases are
+    /// those of [get_secret](Entry::get_secret) and
+    /// [compare_and_set](Entry::compare_and_set).
+    pub fn update_with<F: FnMut(Option<Vec<u8>>) -> Option<Vec<u8>>>(
+        &self,
+        mut f: F,
+        max_attempts: usize,
+    ) -> Result<()> {
+        let _span = entry_span!(self, "update_with");
+        debug!("update secret for entry {:?}", self.inner);
+        for _ in 0..max_attempts {
+            let current = match self.inner.get_secret() {
+                Ok(current) => Some(current),
+                Err(Error::NoEntry) => None,
+                Err(e) => return Err(e),
+            };
+            let new = f(current.clone());
+            if self
+                .inner
+                .compare_and_set(current.as_deref(), new.as_deref())?
+            {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout(format!(
+            "update of secret after {max_attempts} attempts"
+        )))
+    }
+
+    /// Treat the secret for this entry as a counter and add `delta` to it.
+    ///
+    /// The secret is interpreted as a big-endian `u64`. Returns the new value
+    /// of the counter. If there is no credential for this entry,
+    /// one is created with a counter value of `delta`.
+    ///
+    /// Whether concurrent increments are atomic depends on the store;
+    /// see the documentation of each credential store for details.
+    ///
+    /// # Errors
+    ///
+    /// If the secret is not 8 bytes long,
+    /// returns a [BadDataFormat](Error::BadDataFormat) error.
+    ///
+    /// If the addition would overflow the counter,
+    /// returns an [Invalid](Error::Invalid) error.
+    ///
+    /// Other error cases are the same as for [set_secret](Entry::set_secret).
+    pub fn increment_secret_u64(&self, delta: u64) -> Result<u64> {
+        let _span = entry_span!(self, "increment_secret_u64");
+        debug!("increment secret by {delta} for entry {:?}", self.inner);
+        self.inner.increment_secret_u64(delta)
+    }
+
+    /// Tell whether this entry's credential exists but has an empty secret.
+    ///
+    /// # Errors
+    ///
+    /// If there is no credential for this entry,
+    /// returns a [NoEntry](Error::NoEntry) error (rather than `true`).
+    ///
+    /// Other error cases are the same as for [get_secret](Entry::get_secret).
+    pub fn has_empty_secret(&self) -> Result<bool> {
+        let _span = entry_span!(self, "has_empty_secret");
+        debug!("check for an empty secret in entry {:?}", self.inner);
+        self.inner.has_empty_secret()
+    }
+
+    /// Serialize a value as JSON and save it as the secret for this entry.
+    ///
+    /// # Errors
+    ///
+    /// If the value can't be serialized, returns an [Invalid](Error::Invalid) error.
+    ///
+    /// Other error cases are the same as for [set_secret](Entry::set_secret).
+    #[cfg(feature = "serde")]
+    pub fn set_json<T: serde::Serialize>(&self, value: &T) -> Result<()> {
+        let _span = entry_span!(self, "set_json");
+        debug!("set json secret in entry {:?}", self.inner);
+        let secret = serde_json::to_vec(value)
+            .map_err(|e| Error::Invalid("value".to_string(), e.to_string()))?;
+        self.inner.set_secret(&secret)
+    }
+
+    /// Retrieve the secret for this entry and deserialize it from JSON.
+    ///
+    /// # Errors
+    ///
+    /// If the secret can't be deserialized as a `T`, returns a
+    /// [BadDataFormat](Error::BadDataFormat) error containing the secret.
+    ///
+    /// Other error cases are the same as for [get_secret](Entry::get_secret).
+    #[cfg(feature = "serde")]
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let _span = entry_span!(self, "get_json");
+        debug!("get json secret from entry {:?}", self.inner);
+        let secret = self.inner.get_secret()?;
+        serde_json::from_slice(&secret).map_err(|e| Error::BadDataFormat(secret, Box::from(e)))
+    }
+
+    /// Decode a base64 string and save the bytes as the secret for this entry.
+    ///
+    /// The string must be in standard base64 encoding, with padding.
+    ///
+    /// # Errors
+    ///
+    /// If the string isn't valid base64, returns an [Invalid](Error::Invalid) error.
+    ///
+    /// Other error cases are the same as for [set_secret](Entry::set_secret).
+    #[cfg(feature = "base64")]
+    pub fn set_password_base64(&self, b64: &str) -> Result<()> {
+        use base64::prelude::{BASE64_STANDARD, Engine};
+
+        let _span = entry_span!(self, "set_password_base64");
+        debug!("set base64 secret in entry {:?}", self.inner);
+        let secret = BASE64_STANDARD
+            .decode(b64)
+            .map_err(|e| Error::Invalid("b64".to_string(), e.to_string()))?;
+        self.inner.set_secret(&secret)
+    }
+
+    /// Retrieve the secret for this entry as a base64 string.
+    ///
+    /// Unlike [get_password](Entry::get_password), this works for any
+    /// secret, whether or not it's UTF-8. The string is in standard
+    /// base64 encoding, with padding.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_secret](Entry::get_secret).
+    #[cfg(feature = "base64")]
+    pub fn get_password_base64(&self) -> Result<String> {
+        use base64::prelude::{BASE64_STANDARD, Engine};
+
+        let _span = entry_span!(self, "get_password_base64");
+        debug!("get base64 secret from entry {:?}", self.inner);
+        Ok(BASE64_STANDARD.encode(self.inner.get_secret()?))
+    }
+
+    /// Compute a fingerprint of the secret saved for this entry.
+    ///
+    /// The fingerprint is the SHA-256 hash of the secret, so two secrets
+    /// have the same fingerprint just when they are the same. It can be kept
+    /// and compared to detect changes without keeping the secret itself.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_secret](Entry::get_secret).
+    #[cfg(feature = "fingerprint")]
+    pub fn secret_fingerprint(&self) -> Result<[u8; 32]> {
+        let _span = entry_span!(self, "secret_fingerprint");
+        debug!("get secret fingerprint from entry {:?}", self.inner);
+        self.with_secret(fingerprint)
+    }
+
+    /// Retrieve the password saved for this entry, along with
+    /// the [fingerprint](Entry::secret_fingerprint) of its secret.
+    ///
+    /// The secret is read only once, and both results come from it.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_password](Entry::get_password).
+    #[cfg(feature = "fingerprint")]
+    pub fn get_password_and_fingerprint(&self) -> Result<(String, [u8; 32])> {
+        let _span = entry_span!(self, "get_password_and_fingerprint");
+        debug!("get password and fingerprint from entry {:?}", self.inner);
+        let secret = self.inner.get_secret()?;
+        let fingerprint = fingerprint(&secret);
+        Ok((error::decode_password(secret)?, fingerprint))
+    }
+
     /// Retrieve the password saved for this entry.
     ///
     /// # Errors
@@ -259,6 +966,7 @@ impl Entry {
     /// containing the data as a byte array if the password is
     /// not a valid UTF-8 string.
     pub fn get_password(&self) -> Result<String> {
+        let _span = entry_span!(self, "get_password");
         debug!("get password from entry {:?}", self.inner);
         self.inner.get_password()
     }
@@ -279,10 +987,50 @@ impl Entry {
     /// and the underlying credential has been deleted,
     /// returns a [NoEntry](Error::NoEntry) error.
     pub fn get_secret(&self) -> Result<Vec<u8>> {
+        let _span = entry_span!(self, "get_secret");
         debug!("get secret from entry {:?}", self.inner);
         self.inner.get_secret()
     }
 
+    /// Call `f` with a borrowed view of the secret saved for this entry.
+    ///
+    /// This avoids copying the secret for stores that can lend it out
+    /// directly. Such stores may be holding a lock while `f` runs,
+    /// so `f` must not call back into the credential store (for example,
+    /// by reading or writing this or any other entry), because that
+    /// may deadlock.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_secret](Entry::get_secret).
+    pub fn with_secret<T>(&self, f: impl FnOnce(&[u8]) -> T) -> Result<T> {
+        let _span = entry_span!(self, "with_secret");
+        debug!("borrow secret from entry {:?}", self.inner);
+        let mut f = Some(f);
+        let mut result = None;
+        self.inner.with_secret(&mut |secret| {
+            if let Some(f) = f.take() {
+                result = Some(f(secret));
+            }
+        })?;
+        Ok(result.expect("Credential store didn't lend the secret: please report a bug!"))
+    }
+
+    /// Check whether the secret saved for this entry is `candidate`.
+    ///
+    /// The comparison takes time that depends only on the lengths
+    /// of the secret and the candidate, and the secret is borrowed
+    /// as by [with_secret](Entry::with_secret) rather than copied.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_secret](Entry::get_secret).
+    pub fn verify_secret(&self, candidate: &[u8]) -> Result<bool> {
+        let _span = entry_span!(self, "verify_secret");
+        debug!("verify secret for entry {:?}", self.inner);
+        self.with_secret(|secret| api::constant_time_eq(secret, candidate))
+    }
+
     /// Get the store-specific decorations on this entry's credential.
     ///
     /// See the documentation for each credential store
@@ -303,10 +1051,61 @@ impl Entry {
     /// and the underlying credential has been deleted,
     /// returns a [NoEntry](Error::NoEntry) error.
     pub fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        let _span = entry_span!(self, "get_attributes");
         debug!("get attributes from entry {:?}", self.inner);
         self.inner.get_attributes()
     }
 
+    /// Get the password and the attributes of this entry's credential together.
+    ///
+    /// In stores that support it, both come from a single look at the
+    /// credential, so they are consistent even if another thread is
+    /// changing it. See
+    /// [get_secret_and_attributes](api::CredentialApi::get_secret_and_attributes).
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_password](Entry::get_password).
+    pub fn get_password_and_attributes(&self) -> Result<(String, HashMap<String, String>)> {
+        let _span = entry_span!(self, "get_password_and_attributes");
+        debug!("get password and attributes from entry {:?}", self.inner);
+        let (secret, attrs) = self.inner.get_secret_and_attributes()?;
+        Ok((error::decode_password(secret)?, attrs))
+    }
+
+    /// Get the names of the store-specific decorations on this entry's credential.
+    ///
+    /// This is useful when only the names are needed, because
+    /// some stores can supply them without fetching the values.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_attributes](Entry::get_attributes).
+    pub fn attribute_keys(&self) -> Result<Vec<String>> {
+        let _span = entry_span!(self, "attribute_keys");
+        debug!("get attribute keys from entry {:?}", self.inner);
+        self.inner.attribute_keys()
+    }
+
+    /// Get the age of the secret in this entry's credential.
+    ///
+    /// The age is measured from the time the credential was last modified
+    /// or, if the store doesn't record that, from the time it was created.
+    /// Returns `None` if the store records neither time.
+    ///
+    /// # Errors
+    ///
+    /// The error cases are the same as for [get_attributes](Entry::get_attributes).
+    pub fn secret_age(&self) -> Result<Option<Duration>> {
+        let _span = entry_span!(self, "secret_age");
+        debug!("get secret age from entry {:?}", self.inner);
+        let date = match self.inner.get_modified()? {
+            Some(date) => Some(date),
+            None => self.inner.get_created()?,
+        };
+        Ok(date.map(|date| SystemTime::now().duration_since(date).unwrap_or_default()))
+    }
+
     /// Update the store-specific decorations on this entry's credential.
     ///
     /// See the documentation for each credential store
@@ -330,6 +1129,7 @@ impl Entry {
     /// and the underlying credential has been deleted,
     /// returns a [NoEntry](Error::NoEntry) error.
     pub fn update_attributes(&self, attributes: &HashMap<&str, &str>) -> Result<()> {
+        let _span = entry_span!(self, "update_attributes");
         debug!(
             "update attributes for entry {:?} from map {attributes:?}",
             self.inner
@@ -356,10 +1156,37 @@ impl Entry {
     /// and the underlying credential has been deleted,
     /// returns a [NoEntry](Error::NoEntry) error.
     pub fn delete_credential(&self) -> Result<()> {
+        let _span = entry_span!(self, "delete_credential");
         debug!("delete entry {:?}", self.inner);
         self.inner.delete_credential()
     }
 
+    /// Move the matching credential for this entry to a new service and user.
+    ///
+    /// The secret is moved by the store, without being read into this process.
+    /// This entry is not changed, so afterwards it no longer finds the credential;
+    /// build a new entry for the new service and user to use it.
+    ///
+    /// # Errors
+    ///
+    /// The errors for a missing or ambiguous credential are the same as
+    /// for [delete_credential](Entry::delete_credential), which see.
+    ///
+    /// If there is already a credential for the new service and user,
+    /// and the store can't hold more than one credential for them,
+    /// returns an [Invalid](Error::Invalid) error.
+    ///
+    /// If the store can't move credentials, returns a
+    /// [NotSupportedByStore](Error::NotSupportedByStore) error.
+    pub fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        let _span = entry_span!(self, "rename");
+        debug!(
+            "rename entry {:?} to service {new_service}, user {new_user}",
+            self.inner
+        );
+        self.inner.rename(new_service, new_user)
+    }
+
     /// Get a wrapper for the currently matching credential.
     ///
     /// # Errors
@@ -376,6 +1203,7 @@ impl Entry {
     /// and the underlying credential has been deleted,
     /// returns a [NoEntry](Error::NoEntry) error.
     pub fn get_credential(&self) -> Result<Entry> {
+        let _span = entry_span!(self, "get_credential");
         debug!("get credential for entry {:?}", self.inner);
         match self.inner.get_credential() {
             Ok(Some(inner)) => Ok(Entry { inner }),
@@ -386,11 +1214,221 @@ impl Entry {
         }
     }
 
+    /// Tell whether there is a credential for this entry, without reading its secret.
+    ///
+    /// This is cheaper than calling [get_secret](Entry::get_secret) and
+    /// checking for a [NoEntry](Error::NoEntry) error, and in some stores
+    /// it avoids prompting the user to unlock their keychain.
+    ///
+    /// # Errors
+    ///
+    /// If this entry is a specifier,
+    /// and there is more than one matching credential in the store,
+    /// returns an [Ambiguous](Error::Ambiguous) error.
+    pub fn exists(&self) -> Result<bool> {
+        let _span = entry_span!(self, "exists");
+        debug!("check existence of entry {:?}", self.inner);
+        self.inner.exists()
+    }
+
+    /// Get a wrapper for the primary credential matched by this entry.
+    ///
+    /// This is like [get_credential](Entry::get_credential), except that
+    /// an ambiguous entry doesn't produce an error: the store picks
+    /// one of the matching credentials as the primary one, and it
+    /// always picks the same one. See the store's docs for which that is.
+    ///
+    /// # Errors
+    ///
+    /// If there is no matching credential, returns
+    /// a [NoEntry](Error::NoEntry) error.
+    pub fn primary_credential(&self) -> Result<Entry> {
+        let _span = entry_span!(self, "primary_credential");
+        debug!("get primary credential for entry {:?}", self.inner);
+        match self.inner.get_primary_credential()? {
+            Some(inner) => Ok(Entry { inner }),
+            None => Ok(Entry {
+                inner: self.inner.clone(),
+            }),
+        }
+    }
+
+    /// Get a wrapper for the one matching credential that has the given attribute value.
+    ///
+    /// If this entry is ambiguous, the matching credentials are narrowed
+    /// down to those whose attribute `key` has the value `value`. If this
+    /// entry is not ambiguous, its credential is returned only if
+    /// its attribute matches.
+    ///
+    /// # Errors
+    ///
+    /// If no matching credential has the given attribute value,
+    /// returns a [NoEntry](Error::NoEntry) error.
+    ///
+    /// If more than one matching credential has the given attribute value,
+    /// returns an [Ambiguous](Error::Ambiguous) error listing just those credentials.
+    ///
+    /// Other errors are as for [get_credential](Entry::get_credential).
+    pub fn resolve_by_attribute(&self, key: &str, value: &str) -> Result<Entry> {
+        let _span = entry_span!(self, "resolve_by_attribute");
+        debug!("resolve entry {:?} by attribute {key}={value}", self.inner);
+        let candidates = match self.get_credential() {
+            Ok(entry) => vec![entry],
+            Err(Error::Ambiguous(entries)) => entries,
+            Err(e) => return Err(e),
+        };
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            match candidate.get_attributes() {
+                Ok(attrs) if attrs.get(key).is_some_and(|v| v == value) => matches.push(candidate),
+                Ok(_) | Err(Error::NoEntry) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        match matches.len() {
+            0 => Err(Error::NoEntry),
+            1 => Ok(matches.remove(0)),
+            _ => Err(Error::Ambiguous(matches)),
+        }
+    }
+
+    /// Get the attributes of all the credentials matching this entry, merged together.
+    ///
+    /// Each attribute name is mapped to the set of distinct values it has
+    /// across the matching credentials, so a set with more than one value
+    /// shows that the credentials disagree. If this entry is not ambiguous,
+    /// each set holds the one value of its credential's attribute.
+    ///
+    /// # Errors
+    ///
+    /// If there are no matching credentials, returns a [NoEntry](Error::NoEntry) error.
+    ///
+    /// Other errors are as for [get_credential](Entry::get_credential).
+    pub fn merged_attributes(&self) -> Result<MergedAttributes> {
+        let _span = entry_span!(self, "merged_attributes");
+        debug!("get merged attributes for entry {:?}", self.inner);
+        let candidates = match self.get_credential() {
+            Ok(entry) => vec![entry],
+            Err(Error::Ambiguous(entries)) => entries,
+            Err(e) => return Err(e),
+        };
+        let mut found = false;
+        let mut merged = MergedAttributes::new();
+        for candidate in candidates {
+            match candidate.get_attributes() {
+                Ok(attrs) => {
+                    found = true;
+                    for (key, value) in attrs {
+                        merged.entry(key).or_default().insert(value);
+                    }
+                }
+                Err(Error::NoEntry) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if found {
+            Ok(merged)
+        } else {
+            Err(Error::NoEntry)
+        }
+    }
+
     /// Get the `<service, user>` pair for this entry, if any.
     pub fn get_specifiers(&self) -> Option<(String, String)> {
         self.inner.get_specifiers()
     }
 
+    /// Get the store that built this entry, if it's known.
+    ///
+    /// Not every store's entries know their store; see
+    /// [get_store](api::CredentialApi::get_store).
+    pub fn store(&self) -> Option<Arc<CredentialStore>> {
+        self.inner.get_store()
+    }
+
+    /// Get the vendor of the store that built this entry, if it's known.
+    ///
+    /// See [vendor](api::CredentialStoreApi::vendor).
+    pub fn vendor(&self) -> Option<String> {
+        self.store().map(|store| store.vendor())
+    }
+
+    /// Get the ID of the store that built this entry, if it's known.
+    ///
+    /// See [id](api::CredentialStoreApi::id).
+    pub fn store_id(&self) -> Option<String> {
+        self.store().map(|store| store.id())
+    }
+
+    /// Describe the credential this entry refers to, so it can be
+    /// found again later, perhaps in another run.
+    ///
+    /// Returns `None` if the entry doesn't know its store or has no specifiers.
+    pub fn descriptor(&self) -> Option<EntryDescriptor> {
+        let store = self.store()?;
+        let (service, user) = self.get_specifiers()?;
+        Some(EntryDescriptor {
+            vendor: store.vendor(),
+            store_id: store.id(),
+            service,
+            user,
+            uuid: self.inner.get_wrapped_uuid(),
+        })
+    }
+
+    /// Build an entry in `store` for the credential a descriptor describes.
+    ///
+    /// The descriptor's store ID isn't checked, since stores get a new ID
+    /// each time they are created, but its vendor must be `store`'s.
+    /// If the descriptor names a UUID, the entry is a wrapper for that
+    /// credential; otherwise, it's a specifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [Invalid](Error::Invalid) error if the vendor doesn't match.
+    /// If the descriptor names a UUID and that credential has been deleted,
+    /// returns a [NoEntry](Error::NoEntry) error.
+    pub fn from_descriptor(store: &Arc<CredentialStore>, d: &EntryDescriptor) -> Result<Entry> {
+        if store.vendor() != d.vendor {
+            return Err(Error::Invalid(
+                "vendor".to_string(),
+                format!("is not {}", store.vendor()),
+            ));
+        }
+        let entry = store.build(&d.service, &d.user, None)?;
+        let Some(uuid) = d.uuid.as_ref() else {
+            return Ok(entry);
+        };
+        let candidates = match entry.get_credential() {
+            Ok(wrapper) => vec![wrapper],
+            Err(Error::Ambiguous(wrappers)) => wrappers,
+            Err(e) => return Err(e),
+        };
+        candidates
+            .into_iter()
+            .find(|e| e.inner.get_wrapped_uuid().as_ref() == Some(uuid))
+            .ok_or(Error::NoEntry)
+    }
+
+    /// Tell whether this entry is a specifier.
+    ///
+    /// Setting the secret of a specifier creates a credential
+    /// if there isn't one for its service and user.
+    /// An entry can be both a specifier and a [wrapper](Entry::is_wrapper).
+    pub fn is_specifier(&self) -> bool {
+        self.inner.is_specifier()
+    }
+
+    /// Tell whether this entry is a wrapper.
+    ///
+    /// A wrapper refers to one existing credential, and it can't create
+    /// a credential if that one is deleted. The entries returned by
+    /// [get_credential](Entry::get_credential), by searches, and in
+    /// [Ambiguous](Error::Ambiguous) errors are wrappers.
+    pub fn is_wrapper(&self) -> bool {
+        self.inner.is_wrapper()
+    }
+
     /// Return a reference to the inner store-specific object in this entry.
     ///
     /// The reference is of the [Any](std::any::Any) type, so it can be
@@ -400,5 +1438,99 @@ impl Entry {
     }
 }
 
+/// A description of the credential an entry refers to, without its secret.
+///
+/// See [Entry::descriptor] and [Entry::from_descriptor]. If the `serde`
+/// feature is enabled, descriptors can be serialized, for example to
+/// keep a list of known credentials in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryDescriptor {
+    /// The vendor of the store that built the entry, which must match
+    /// the vendor of the store the entry is rebuilt in
+    pub vendor: String,
+    /// The ID of the store that built the entry (informational only,
+    /// since stores get a new ID each time they are created)
+    pub store_id: String,
+    /// The service name of the entry
+    pub service: String,
+    /// The username of the entry
+    pub user: String,
+    /// The UUID of the credential, if the entry is a wrapper that has one
+    pub uuid: Option<String>,
+}
+
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md", readme);
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_entry_spans() {
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        use crate::{CredentialStore, mock};
+
+        type Spans = Arc<Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+        struct Fields<'a>(&'a mut HashMap<String, String>);
+
+        impl Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{value:?}"));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.insert(field.name().to_string(), value.to_string());
+            }
+        }
+
+        struct Capture(Spans);
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = HashMap::new();
+                span.record(&mut Fields(&mut fields));
+                let mut spans = self.0.lock().unwrap();
+                spans.push((span.metadata().name().to_string(), fields));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = Spans::default();
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        tracing::subscriber::with_default(Capture(spans.clone()), || {
+            assert_eq!(entry.get_password().unwrap(), "password");
+        });
+        let spans = spans.lock().unwrap();
+        assert_eq!(spans.len(), 1);
+        let (name, fields) = &spans[0];
+        assert_eq!(name, "get_password");
+        assert_eq!(fields["service"], "service");
+        assert_eq!(fields["user"], "user");
+        assert!(fields.contains_key("vendor"));
+    }
+}
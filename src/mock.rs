@@ -5,7 +5,8 @@
 To facilitate testing of clients, this crate provides a Mock credential store
 that is platform-independent, provides no persistence, and allows the client
 to specify the return values (including errors) for each call. The credentials
-in this store have no attributes at all.
+in this store have no attributes unless a test gives them some with
+[set_attributes_from](Cred::set_attributes_from).
 
 To use this credential store instead of the default, make this call during
 application startup _before_ creating any entries:
@@ -36,29 +37,98 @@ let val = entry.get_password().expect("the error has been cleared");
 assert_eq!(val, "test", "the error did not affect the password");
 ```
 
+To script a sequence of failures, call [queue_error](Cred::queue_error)
+once for each: the queued errors are returned one per call, in order,
+after which the mock works as usual.
+
+To test how clients handle [Ambiguous](Error::Ambiguous) errors, call
+[make_ambiguous](Store::make_ambiguous) on the store to give a service
+and user several credentials.
+
  */
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::api::{CredentialApi, CredentialStoreApi};
-use crate::{Credential, CredentialPersistence, Entry, Error, Result};
+use crate::secret::scrub;
+use crate::{
+    Capabilities, Credential, CredentialPersistence, Entry, Error, Result, SetOutcome, StoreKind,
+};
 
 /// The concrete mock credential
 ///
 /// Mocks use an internal mutability pattern since entries are read-only.
 /// The mutex is used to make sure these are Sync.
+///
+/// The credentials built by the store have no `uuid`. The ones made by
+/// [make_ambiguous](Store::make_ambiguous) have one, which tells apart
+/// credentials that share the same service and user.
 #[derive(Debug)]
 pub struct Cred {
     pub specifiers: (String, String),
+    pub uuid: Option<String>,
     pub inner: Mutex<RefCell<CredData>>,
+    pub calls: CallLog,
+}
+
+/// The calls recorded by a mock store, or `None` if it isn't recording.
+///
+/// The store shares this with each of its credentials.
+pub type CallLog = Arc<Mutex<Option<Vec<MockCall>>>>;
+
+/// A call on a mock credential, as recorded by its store.
+///
+/// See [enable_recording](Store::enable_recording). Both
+/// [set_secret](CredentialApi::set_secret) and
+/// [set_secret_reporting](CredentialApi::set_secret_reporting)
+/// are recorded as a `SetSecret`. Calls to methods that the mock
+/// doesn't implement itself are recorded as the calls that their
+/// default implementations make.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    SetSecret {
+        service: String,
+        user: String,
+        len: usize,
+    },
+    GetSecret {
+        service: String,
+        user: String,
+    },
+    HasEmptySecret {
+        service: String,
+        user: String,
+    },
+    GetAttributes {
+        service: String,
+        user: String,
+    },
+    UpdateAttributes {
+        service: String,
+        user: String,
+        keys: Vec<String>,
+    },
+    Delete {
+        service: String,
+        user: String,
+    },
+    GetCredential {
+        service: String,
+        user: String,
+    },
 }
 
 /// The (in-memory) persisted data for a mock credential.
 ///
 /// We keep a password but, unlike most credentials stores,
-/// we also keep an intended error to return on the next call.
+/// we also keep an intended error to return on the next call,
+/// and a queue of errors to return on the calls after that.
+/// The attributes are empty unless a test sets them.
+/// A credential that has been made ambiguous also keeps the
+/// credentials that share its service and user.
 ///
 /// (Everything about this structure is public for transparency.
 /// Most credential store implementations hide their internals.)
@@ -66,6 +136,19 @@ pub struct Cred {
 pub struct CredData {
     pub secret: Option<Vec<u8>>,
     pub error: Option<Error>,
+    pub errors: VecDeque<Error>,
+    pub attributes: HashMap<String, String>,
+    pub ambiguous: Vec<Arc<Cred>>,
+}
+
+impl CredData {
+    /// Take the error to return from the next call, if any.
+    ///
+    /// An error set with [set_error](Cred::set_error) comes first,
+    /// then the ones queued with [queue_error](Cred::queue_error).
+    fn take_error(&mut self) -> Option<Error> {
+        self.error.take().or_else(|| self.errors.pop_front())
+    }
 }
 
 impl CredentialApi for Cred {
@@ -75,14 +158,197 @@ impl CredentialApi for Cred {
     /// and the secret will _not_ be set.  The error will
     /// be cleared, so calling again will set the secret.
     fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.record(|service, user| MockCall::SetSecret {
+            service,
+            user,
+            len: secret.len(),
+        });
+        self.set_secret_unrecorded(secret)
+    }
+
+    /// See the API docs.
+    ///
+    /// The credential is created if it had no secret.
+    fn set_secret_reporting(&self, secret: &[u8]) -> Result<SetOutcome> {
+        self.record(|service, user| MockCall::SetSecret {
+            service,
+            user,
+            len: secret.len(),
+        });
+        self.set_secret_reporting_unrecorded(secret)
+    }
+
+    /// See the API docs.
+    ///
+    /// If there is an error set in the mock, it will
+    /// be returned instead of a secret. The existing
+    /// secret will not change.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        self.record(|service, user| MockCall::GetSecret { service, user });
+        self.get_secret_unrecorded()
+    }
+
+    /// See the API docs.
+    ///
+    /// Empty secrets are allowed. If there is an error
+    /// set in the mock, it will be returned and cleared.
+    fn has_empty_secret(&self) -> Result<bool> {
+        self.record(|service, user| MockCall::HasEmptySecret { service, user });
+        self.has_empty_secret_unrecorded()
+    }
+
+    /// See the API docs.
+    ///
+    /// The attributes are those given by
+    /// [set_attributes_from](Cred::set_attributes_from), if any.
+    /// If there is an error in the mock, it's returned instead and cleared.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.record(|service, user| MockCall::GetAttributes { service, user });
+        self.get_attributes_unrecorded()
+    }
+
+    /// See the API docs.
+    ///
+    /// Only attributes given by [set_attributes_from](Cred::set_attributes_from)
+    /// can be updated; any others are [Invalid](Error::Invalid). A mock
+    /// credential that hasn't been given attributes doesn't support updating
+    /// them, so it returns a [NotSupportedByStore](Error::NotSupportedByStore) error.
+    /// If there is an error in the mock, it's returned instead and cleared.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        let mut keys: Vec<String> = attrs.keys().map(|k| k.to_string()).collect();
+        keys.sort();
+        self.record(|service, user| MockCall::UpdateAttributes {
+            service,
+            user,
+            keys,
+        });
+        self.update_attributes_unrecorded(attrs)
+    }
+
+    /// See the API docs.
+    ///
+    /// If there is an error, it will be returned and
+    /// cleared. Calling again will delete the cred.
+    fn delete_credential(&self) -> Result<()> {
+        self.record(|service, user| MockCall::Delete { service, user });
+        self.delete_credential_unrecorded()
+    }
+
+    /// See the API docs.
+    ///
+    /// If there is an error in the mock, it's returned instead and cleared.
+    /// Calling again will retry the operation.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        self.record(|service, user| MockCall::GetCredential { service, user });
+        self.get_credential_unrecorded()
+    }
+
+    /// See the API docs.
+    ///
+    /// A mock store builds every entry for a service and user
+    /// around the same credential, so entries are the same if
+    /// they share it.
+    fn is_same_credential(&self, other: &Credential) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Cred>()
+            .is_some_and(|other| self.specifiers == other.specifiers && std::ptr::eq(self, other))
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some(self.specifiers.clone())
+    }
+
+    /// Every mock credential is both a specifier and its own wrapper,
+    /// except one that has been made ambiguous, which is only a specifier.
+    fn is_wrapper(&self) -> bool {
+        self.uuid.is_some() || self.matches().is_empty()
+    }
+
+    /// The UUID of a mock made by [make_ambiguous](Store::make_ambiguous).
+    fn get_wrapped_uuid(&self) -> Option<String> {
+        self.uuid.clone()
+    }
+
+    /// Return this mock credential concrete object
+    /// wrapped in the [Any](std::any::Any) trait,
+    /// so it can be downcast.
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// Expose the concrete debug formatter for use via the [Credential] trait
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl Cred {
+    fn new(service: &str, user: &str, uuid: Option<String>, calls: CallLog) -> Self {
+        Cred {
+            specifiers: (service.to_string(), user.to_string()),
+            uuid,
+            inner: Mutex::new(RefCell::new(Default::default())),
+            calls,
+        }
+    }
+
+    /// The credentials sharing this one's service and user that exist.
+    fn matches(&self) -> Vec<Arc<Cred>> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for matches: please report a bug!");
+        inner.get_mut().ambiguous.clone()
+    }
+
+    /// Find the credential that an operation on this one applies to.
+    ///
+    /// If this credential has been made ambiguous, any error set in it
+    /// is returned first. Then, if more than one of the credentials that
+    /// share its service and user exist, they are ambiguous; if just
+    /// one exists, the operation applies to that one. Otherwise
+    /// (returned as `None`) the operation applies to this credential.
+    fn resolve(&self) -> Result<Option<Arc<Cred>>> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for resolve: please report a bug!");
+        let data = inner.get_mut();
+        if data.ambiguous.is_empty() {
+            return Ok(None);
+        }
+        if let Some(err) = data.take_error() {
+            return Err(err);
+        }
+        let candidates = data.ambiguous.clone();
+        drop(inner);
+        let mut live: Vec<Arc<Cred>> = candidates.into_iter().filter(|c| c.has_secret()).collect();
+        match live.len() {
+            0 => Ok(None),
+            1 => Ok(live.pop()),
+            _ => Err(Error::Ambiguous(
+                live.into_iter().map(|inner| Entry { inner }).collect(),
+            )),
+        }
+    }
+
+    fn set_secret_unrecorded(&self, secret: &[u8]) -> Result<()> {
+        if let Some(cred) = self.resolve()? {
+            return cred.set_secret_unrecorded(secret);
+        }
         let mut inner = self
             .inner
             .lock()
             .expect("Can't access mock data for set_secret: please report a bug!");
         let data = inner.get_mut();
-        let err = data.error.take();
+        let err = data.take_error();
         match err {
             None => {
+                if let Some(old) = data.secret.as_mut() {
+                    scrub(old);
+                }
                 data.secret = Some(secret.to_vec());
                 Ok(())
             }
@@ -90,18 +356,38 @@ impl CredentialApi for Cred {
         }
     }
 
-    /// See the API docs.
-    ///
-    /// If there is an error set in the mock, it will
-    /// be returned instead of a secret. The existing
-    /// secret will not change.
-    fn get_secret(&self) -> Result<Vec<u8>> {
+    fn set_secret_reporting_unrecorded(&self, secret: &[u8]) -> Result<SetOutcome> {
+        if let Some(cred) = self.resolve()? {
+            return cred.set_secret_reporting_unrecorded(secret);
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for set_secret_reporting: please report a bug!");
+        let data = inner.get_mut();
+        let err = data.take_error();
+        match err {
+            None => match data.secret.replace(secret.to_vec()) {
+                Some(mut old) => {
+                    scrub(&mut old);
+                    Ok(SetOutcome::Updated)
+                }
+                None => Ok(SetOutcome::Created),
+            },
+            Some(err) => Err(err),
+        }
+    }
+
+    fn get_secret_unrecorded(&self) -> Result<Vec<u8>> {
+        if let Some(cred) = self.resolve()? {
+            return cred.get_secret_unrecorded();
+        }
         let mut inner = self
             .inner
             .lock()
             .expect("Can't access mock data for get: please report a bug!");
         let data = inner.get_mut();
-        let err = data.error.take();
+        let err = data.take_error();
         match err {
             None => match &data.secret {
                 None => Err(Error::NoEntry),
@@ -111,21 +397,90 @@ impl CredentialApi for Cred {
         }
     }
 
-    /// See the API docs.
-    ///
-    /// If there is an error, it will be returned and
-    /// cleared. Calling again will delete the cred.
-    fn delete_credential(&self) -> Result<()> {
+    fn has_empty_secret_unrecorded(&self) -> Result<bool> {
+        if let Some(cred) = self.resolve()? {
+            return cred.has_empty_secret_unrecorded();
+        }
         let mut inner = self
             .inner
             .lock()
-            .expect("Can't access mock data for delete: please report a bug!");
+            .expect("Can't access mock data for has_empty_secret: please report a bug!");
+        let data = inner.get_mut();
+        let err = data.take_error();
+        match err {
+            None => match &data.secret {
+                None => Err(Error::NoEntry),
+                Some(val) => Ok(val.is_empty()),
+            },
+            Some(err) => Err(err),
+        }
+    }
+
+    fn get_attributes_unrecorded(&self) -> Result<HashMap<String, String>> {
+        if let Some(cred) = self.resolve()? {
+            return cred.get_attributes_unrecorded();
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for get_attributes: please report a bug!");
         let data = inner.get_mut();
-        let err = data.error.take();
+        let err = data.take_error();
         match err {
             None => match data.secret {
-                Some(_) => {
-                    data.secret = None;
+                Some(_) => Ok(data.attributes.clone()),
+                None => Err(Error::NoEntry),
+            },
+            Some(err) => Err(err),
+        }
+    }
+
+    fn update_attributes_unrecorded(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        if let Some(cred) = self.resolve()? {
+            return cred.update_attributes_unrecorded(attrs);
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for update_attributes: please report a bug!");
+        let data = inner.get_mut();
+        if let Some(err) = data.take_error() {
+            return Err(err);
+        }
+        if data.secret.is_none() {
+            return Err(Error::NoEntry);
+        }
+        if data.attributes.is_empty() {
+            return Err(Error::NotSupportedByStore(
+                "No attributes can be updated".to_string(),
+            ));
+        }
+        if let Some(key) = attrs.keys().find(|k| !data.attributes.contains_key(**k)) {
+            return Err(Error::Invalid(
+                key.to_string(),
+                "is not an attribute of this mock credential".to_string(),
+            ));
+        }
+        for (key, value) in attrs {
+            data.attributes.insert(key.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+
+    fn delete_credential_unrecorded(&self) -> Result<()> {
+        if let Some(cred) = self.resolve()? {
+            return cred.delete_credential_unrecorded();
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for delete: please report a bug!");
+        let data = inner.get_mut();
+        let err = data.take_error();
+        match err {
+            None => match data.secret.take() {
+                Some(mut old) => {
+                    scrub(&mut old);
                     Ok(())
                 }
                 None => Err(Error::NoEntry),
@@ -134,17 +489,16 @@ impl CredentialApi for Cred {
         }
     }
 
-    /// See the API docs.
-    ///
-    /// If there is an error in the mock, it's returned instead and cleared.
-    /// Calling again will retry the operation.
-    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+    fn get_credential_unrecorded(&self) -> Result<Option<Arc<Credential>>> {
+        if let Some(cred) = self.resolve()? {
+            return Ok(Some(cred));
+        }
         let mut inner = self
             .inner
             .lock()
             .expect("Can't access mock data for get_credential: please report a bug!");
         let data = inner.get_mut();
-        let err = data.error.take();
+        let err = data.take_error();
         match err {
             None => match data.secret {
                 Some(_) => Ok(None),
@@ -154,25 +508,52 @@ impl CredentialApi for Cred {
         }
     }
 
-    /// See the API docs.
-    fn get_specifiers(&self) -> Option<(String, String)> {
-        Some(self.specifiers.clone())
+    /// Record a call on this credential, if its store is recording calls.
+    fn record(&self, call: impl FnOnce(String, String) -> MockCall) {
+        let mut calls = self
+            .calls
+            .lock()
+            .expect("Can't access mock store calls: please report a bug!");
+        if let Some(calls) = calls.as_mut() {
+            calls.push(call(self.specifiers.0.clone(), self.specifiers.1.clone()));
+        }
     }
 
-    /// Return this mock credential concrete object
-    /// wrapped in the [Any](std::any::Any) trait,
-    /// so it can be downcast.
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+    /// The credentials that searches find for this one.
+    ///
+    /// These are the existing credentials that share its service and user
+    /// if it has been made ambiguous, and otherwise just this one.
+    fn members(self: &Arc<Self>) -> Vec<Arc<Cred>> {
+        let live: Vec<Arc<Cred>> = self
+            .matches()
+            .into_iter()
+            .filter(|c| c.has_secret())
+            .collect();
+        if live.is_empty() {
+            vec![self.clone()]
+        } else {
+            live
+        }
     }
 
-    /// Expose the concrete debug formatter for use via the [Credential] trait
-    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(self, f)
+    fn scrub_secret(&self) {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for clear: please report a bug!");
+        if let Some(mut old) = inner.get_mut().secret.take() {
+            scrub(&mut old);
+        }
+    }
+
+    fn has_secret(&self) -> bool {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for has_secret: please report a bug!");
+        inner.get_mut().secret.is_some()
     }
-}
 
-impl Cred {
     /// Set an error to be returned from this mock credential.
     ///
     /// Error returns always take precedence over the normal
@@ -186,6 +567,39 @@ impl Cred {
         let data = inner.get_mut();
         data.error = Some(err);
     }
+
+    /// Give this mock credential attributes, replacing any it had.
+    ///
+    /// This is meant for test setup: it works whether or not the
+    /// credential exists, and doesn't consume any error set in the mock.
+    /// The attributes are reported by [get_attributes](Entry::get_attributes)
+    /// while the credential exists, and only these attributes can be updated.
+    pub fn set_attributes_from(&self, map: &HashMap<&str, &str>) {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for set_attributes_from: please report a bug!");
+        let data = inner.get_mut();
+        data.attributes = map
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+    }
+
+    /// Queue an error to be returned from this mock credential.
+    ///
+    /// Queued errors are returned one per call, in the order they
+    /// were queued, after any error set with [set_error](Cred::set_error).
+    /// Once the queue is empty, the mock works as usual. This makes it
+    /// easy to script a sequence of failures, for example to test retries.
+    pub fn queue_error(&self, err: Error) {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock data for queue_error: please report a bug!");
+        let data = inner.get_mut();
+        data.errors.push_back(err);
+    }
 }
 
 /// The builder for mock credentials.
@@ -197,6 +611,7 @@ impl Cred {
 pub struct Store {
     pub id: String,
     pub inner: Mutex<RefCell<Vec<Arc<Cred>>>>,
+    pub calls: CallLog,
 }
 
 impl std::fmt::Debug for Store {
@@ -220,8 +635,78 @@ impl Store {
                     .as_secs_f64()
             ),
             inner: Mutex::new(RefCell::new(Vec::new())),
+            calls: Arc::new(Mutex::new(None)),
         }))
     }
+
+    /// Start recording the calls made on this store's credentials.
+    ///
+    /// Recording is off until this is called. Calling it again
+    /// while recording keeps the calls recorded so far.
+    pub fn enable_recording(&self) {
+        let mut calls = self
+            .calls
+            .lock()
+            .expect("Can't access mock store calls: please report a bug!");
+        calls.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the calls recorded so far, in the order they were made.
+    ///
+    /// Recording continues, starting from an empty log.
+    /// If recording isn't on, no calls are returned.
+    pub fn take_calls(&self) -> Vec<MockCall> {
+        let mut calls = self
+            .calls
+            .lock()
+            .expect("Can't access mock store calls: please report a bug!");
+        calls.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Make the service and user ambiguous by giving them `count` more credentials.
+    ///
+    /// Each of the new credentials is a wrapper with its own `uuid`
+    /// and an empty secret. While more than one of them exists, operations on
+    /// an entry built for the service and user fail with an
+    /// [Ambiguous](Error::Ambiguous) error that holds entries for them, as
+    /// they do in stores that allow several credentials per service and user.
+    /// Once only one of them exists, operations apply to that one.
+    pub fn make_ambiguous(&self, service: &str, user: &str, count: usize) {
+        static NEXT_UUID: AtomicU64 = AtomicU64::new(1);
+        let specifier = self.get_or_create(service, user);
+        let mut inner = specifier
+            .inner
+            .lock()
+            .expect("Can't access mock data for make_ambiguous: please report a bug!");
+        let data = inner.get_mut();
+        for _ in 0..count {
+            let uuid = format!("mock-{:016x}", NEXT_UUID.fetch_add(1, Ordering::Relaxed));
+            let cred = Cred::new(service, user, Some(uuid), self.calls.clone());
+            cred.inner
+                .lock()
+                .expect("Can't access mock data for make_ambiguous: please report a bug!")
+                .get_mut()
+                .secret = Some(Vec::new());
+            data.ambiguous.push(Arc::new(cred));
+        }
+    }
+
+    /// Get the credential for the service and user, creating it if need be.
+    fn get_or_create(&self, service: &str, user: &str) -> Arc<Cred> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock store data: please report a bug!");
+        let creds = inner.get_mut();
+        for cred in creds.iter() {
+            if service == cred.specifiers.0 && user == cred.specifiers.1 {
+                return cred.clone();
+            }
+        }
+        let cred = Arc::new(Cred::new(service, user, None, self.calls.clone()));
+        creds.push(cred.clone());
+        cred
+    }
 }
 
 impl CredentialStoreApi for Store {
@@ -233,6 +718,10 @@ impl CredentialStoreApi for Store {
         self.id.clone()
     }
 
+    fn kind(&self) -> StoreKind {
+        StoreKind::Mock
+    }
+
     /// Build a mock credential for the service and user. No modifiers are allowed.
     ///
     /// Since mocks don't persist beyond the life of their entry, all mocks
@@ -248,24 +737,9 @@ impl CredentialStoreApi for Store {
                 "The mock store doesn't allow modifiers".to_string(),
             ));
         }
-        let mut inner = self
-            .inner
-            .lock()
-            .expect("Can't access mock store data: please report a bug!");
-        let creds = inner.get_mut();
-        for cred in creds.iter() {
-            if service == cred.specifiers.0 && user == cred.specifiers.1 {
-                return Ok(Entry {
-                    inner: cred.clone(),
-                });
-            }
-        }
-        let cred = Arc::new(Cred {
-            specifiers: (service.to_string(), user.to_string()),
-            inner: Mutex::new(RefCell::new(Default::default())),
-        });
-        creds.push(cred.clone());
-        Ok(Entry { inner: cred })
+        Ok(Entry {
+            inner: self.get_or_create(service, user),
+        })
     }
 
     /// Search for mock credentials matching the spec.
@@ -288,13 +762,65 @@ impl CredentialStoreApi for Store {
             if !cred.specifiers.1.as_str().contains(usr) {
                 continue;
             }
-            result.push(Entry {
-                inner: cred.clone(),
-            });
+            result.extend(cred.members().into_iter().map(|inner| Entry { inner }));
         }
         Ok(result)
     }
 
+    /// Get entries for all the mock credentials.
+    ///
+    /// There is one for each service and user that an entry has been built for,
+    /// whether or not a password has been set on it, except that one that
+    /// has been [made ambiguous](Store::make_ambiguous) has one for each
+    /// of its credentials that exists.
+    fn all(&self) -> Result<Vec<Entry>> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock store data: please report a bug!");
+        Ok(inner
+            .get_mut()
+            .iter()
+            .flat_map(|cred| cred.members())
+            .map(|inner| Entry { inner })
+            .collect())
+    }
+
+    /// Count the mock credentials.
+    ///
+    /// These are the credentials that [all](CredentialStoreApi::all) gets entries for.
+    fn count(&self) -> Result<usize> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock store data: please report a bug!");
+        Ok(inner
+            .get_mut()
+            .iter()
+            .map(|cred| cred.members().len())
+            .sum())
+    }
+
+    /// Delete all the mock credentials.
+    ///
+    /// The store forgets them, and their secrets are scrubbed, so
+    /// entries built earlier find nothing. The number deleted is
+    /// what [count](CredentialStoreApi::count) would have returned.
+    fn clear(&self) -> Result<usize> {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Can't access mock store data: please report a bug!");
+        let creds = std::mem::take(inner.get_mut());
+        let count = creds.iter().map(|cred| cred.members().len()).sum();
+        for cred in creds.iter() {
+            for cred in cred.matches().iter().chain(std::iter::once(cred)) {
+                cred.scrub_secret();
+            }
+        }
+        Ok(count)
+    }
+
     /// Get an [Any][std::any::Any] reference to the mock credential builder.
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -305,6 +831,20 @@ impl CredentialStoreApi for Store {
         CredentialPersistence::ProcessOnly
     }
 
+    /// Mock credentials can be searched for.
+    ///
+    /// Attributes aren't reported, because mock credentials only have them
+    /// when a test [provides them](Cred::set_attributes_from), and then
+    /// only the attributes provided can be updated.
+    /// (Likewise, ambiguity can be [simulated](Store::make_ambiguous), but
+    /// building an entry never creates it, so it isn't reported.)
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_search: true,
+            ..Capabilities::default()
+        }
+    }
+
     /// Expose the concrete debug formatter
     /// for use via the [CredentialStore](crate::CredentialStore) trait
     fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -316,8 +856,11 @@ impl CredentialStoreApi for Store {
 mod tests {
     use std::sync::{Arc, Once};
 
-    use super::{Cred, HashMap, Store};
-    use crate::{CredentialPersistence, CredentialStore, Entry, Error, get_default_store};
+    use super::{Cred, CredentialStoreApi, HashMap, MockCall, Store};
+    use crate::{
+        Capabilities, CredentialPersistence, CredentialStore, Entry, Error, SetOutcome, StoreKind,
+        get_default_store,
+    };
 
     static SET_STORE: Once = Once::new();
 
@@ -481,6 +1024,250 @@ mod tests {
         test_round_trip_secret("non-ascii password", &entry, secret.as_slice());
     }
 
+    #[test]
+    fn test_with_secret() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(
+            entry.with_secret(|s| s.len()),
+            Err(Error::NoEntry)
+        ));
+        entry.set_secret(&[1, 2, 3]).unwrap();
+        assert_eq!(entry.with_secret(|s| s.to_vec()).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_has_empty_secret() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(entry.has_empty_secret(), Err(Error::NoEntry)));
+        entry.set_secret(&[]).unwrap();
+        assert!(entry.has_empty_secret().unwrap());
+        entry.set_secret(&[1]).unwrap();
+        assert!(!entry.has_empty_secret().unwrap());
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.has_empty_secret(),
+            Err(Error::NoStorageAccess(_))
+        ));
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_password_and_fingerprint() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(
+            entry.get_password_and_fingerprint(),
+            Err(Error::NoEntry)
+        ));
+        entry.set_password("このきれいな花は桜です").unwrap();
+        let (password, fingerprint) = entry.get_password_and_fingerprint().unwrap();
+        assert_eq!(password, "このきれいな花は桜です");
+        assert_eq!(fingerprint, entry.secret_fingerprint().unwrap());
+        entry.set_password("different").unwrap();
+        assert_ne!(fingerprint, entry.secret_fingerprint().unwrap());
+        entry.set_secret(&[0xff, 0xfe]).unwrap();
+        assert!(matches!(
+            entry.get_password_and_fingerprint(),
+            Err(Error::BadEncoding(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Token {
+            access: String,
+            expires: u64,
+        }
+
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let token = Token {
+            access: "abc".to_string(),
+            expires: 3600,
+        };
+        entry.set_json(&token).unwrap();
+        assert_eq!(entry.get_json::<Token>().unwrap(), token);
+        entry.set_password("not json").unwrap();
+        match entry.get_json::<Token>() {
+            Err(Error::BadDataFormat(data, _)) => assert_eq!(data, b"not json"),
+            other => panic!("Bad json gave wrong result: {other:?}"),
+        }
+        let bad_key = std::collections::BTreeMap::from([((1, 2), 3)]);
+        assert!(matches!(
+            entry.set_json(&bad_key),
+            Err(Error::Invalid(_, _))
+        ));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_round_trip() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let secret: Vec<u8> = (0..32).map(|_| fastrand::u8(..)).chain([0xff]).collect();
+        entry.set_secret(&secret).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::BadEncoding(_))));
+        let b64 = entry.get_password_base64().unwrap();
+        entry.set_password("something else").unwrap();
+        entry.set_password_base64(&b64).unwrap();
+        assert_eq!(entry.get_secret().unwrap(), secret);
+        assert!(matches!(
+            entry.set_password_base64("not base64!"),
+            Err(Error::Invalid(_, _))
+        ));
+        assert_eq!(entry.get_secret().unwrap(), secret);
+    }
+
+    #[test]
+    fn test_set_if_changed() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(entry.set_password_if_changed("first").unwrap());
+        assert!(!entry.set_password_if_changed("first").unwrap());
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.set_password_if_changed("second"),
+            Err(Error::NoStorageAccess(_))
+        ));
+        assert_eq!(entry.get_password().unwrap(), "first");
+        assert!(entry.set_password_if_changed("second").unwrap());
+        assert_eq!(entry.get_password().unwrap(), "second");
+    }
+
+    #[test]
+    fn test_increment_secret() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert_eq!(entry.increment_secret_u64(2).unwrap(), 2);
+        assert_eq!(entry.increment_secret_u64(2).unwrap(), 4);
+        entry.set_secret(&[1]).unwrap();
+        assert!(matches!(
+            entry.increment_secret_u64(1),
+            Err(Error::BadDataFormat(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_specifier_and_wrapper() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(entry.is_specifier());
+        assert!(entry.is_wrapper());
+    }
+
+    #[test]
+    fn test_exists() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(!entry.exists().unwrap());
+        entry.set_password("password").unwrap();
+        assert!(entry.exists().unwrap());
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::Ambiguous(vec![]));
+        assert!(matches!(entry.exists(), Err(Error::Ambiguous(_))));
+        assert!(entry.exists().unwrap());
+        entry.delete_credential().unwrap();
+        assert!(!entry.exists().unwrap());
+    }
+
+    #[test]
+    fn test_same_credential() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        let wrapper = entry.get_credential().unwrap();
+        assert!(entry == wrapper);
+        assert!(entry == store.build("service", "user", None).unwrap());
+        assert!(entry != store.build("service", "other", None).unwrap());
+        let other: Arc<CredentialStore> = Store::new().unwrap();
+        let elsewhere = other.build("service", "user", None).unwrap();
+        elsewhere.set_password("password").unwrap();
+        assert!(entry != elsewhere);
+    }
+
+    #[test]
+    fn test_count() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        assert_eq!(store.count().unwrap(), 0);
+        store.build("service", "user1", None).unwrap();
+        store.build("service", "user2", None).unwrap();
+        store.build("service", "user1", None).unwrap();
+        assert_eq!(store.count().unwrap(), 2);
+        let all = store.all().unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all[0] == store.build("service", "user1", None).unwrap());
+    }
+
+    #[test]
+    fn test_clear() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user1", None).unwrap();
+        entry.set_password("password").unwrap();
+        store.build("service", "user2", None).unwrap();
+        assert_eq!(store.clear().unwrap(), 2);
+        assert_eq!(store.count().unwrap(), 0);
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        let rebuilt = store.build("service", "user1", None).unwrap();
+        assert!(matches!(rebuilt.get_password(), Err(Error::NoEntry)));
+        assert_eq!(store.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_set_password_reporting() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        let outcome = entry.set_password_reporting("first").unwrap();
+        assert_eq!(outcome, SetOutcome::Created);
+        let outcome = entry.set_password_reporting("second").unwrap();
+        assert_eq!(outcome, SetOutcome::Updated);
+        assert_eq!(entry.get_password().unwrap(), "second");
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.set_password_reporting("third"),
+            Err(Error::NoStorageAccess(_))
+        ));
+        entry.delete_credential().unwrap();
+        let outcome = entry.set_password_reporting("fourth").unwrap();
+        assert_eq!(outcome, SetOutcome::Created);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_secret_zeroizing() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_secret_zeroizing(), Err(Error::NoEntry)));
+        entry.set_secret(b"first").unwrap();
+        entry.set_secret(b"second").unwrap();
+        assert_eq!(entry.get_secret_zeroizing().unwrap().as_bytes(), b"second");
+        entry.delete_credential().unwrap();
+        assert!(matches!(entry.get_secret_zeroizing(), Err(Error::NoEntry)));
+    }
+
+    #[test]
+    fn test_compare_and_set() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(!entry.compare_and_set(Some(b"old"), Some(b"new")).unwrap());
+        assert!(entry.compare_and_set(None, Some(b"old")).unwrap());
+        assert!(!entry.compare_and_set(None, Some(b"new")).unwrap());
+        assert!(entry.compare_and_set(Some(b"old"), Some(b"new")).unwrap());
+        assert_eq!(entry.get_secret().unwrap(), b"new");
+        assert!(entry.compare_and_set(Some(b"new"), None).unwrap());
+        assert!(matches!(entry.get_secret(), Err(Error::NoEntry)));
+        entry
+            .update_with(|current| Some(current.unwrap_or_default()), 1)
+            .unwrap();
+        assert!(entry.get_secret().unwrap().is_empty());
+    }
+
     #[test]
     fn test_update() {
         let name = generate_random_string();
@@ -521,6 +1308,189 @@ mod tests {
         assert!(matches!(entry.get_password(), Err(Error::NoEntry)))
     }
 
+    #[test]
+    fn test_get_update_attributes() {
+        let name = generate_random_string();
+        let entry = entry_new(&name, &name);
+        assert!(matches!(entry.get_attributes(), Err(Error::NoEntry)));
+        entry.set_password("password").unwrap();
+        assert!(entry.get_attributes().unwrap().is_empty());
+        let comment_map = HashMap::from([("comment", "some comment")]);
+        assert!(matches!(
+            entry.update_attributes(&comment_map),
+            Err(Error::NotSupportedByStore(_))
+        ));
+        let mock: &Cred = entry.inner.as_any().downcast_ref().unwrap();
+        mock.set_attributes_from(&HashMap::from([("comment", ""), ("label", "mine")]));
+        let attrs = entry.get_attributes().unwrap();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs.get("label").unwrap(), "mine");
+        assert!(matches!(
+            entry.update_attributes(&HashMap::from([("foo", "bar")])),
+            Err(Error::Invalid(_, _))
+        ));
+        entry.update_attributes(&comment_map).unwrap();
+        assert_eq!(
+            entry.get_attributes().unwrap().get("comment").unwrap(),
+            "some comment"
+        );
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.get_attributes(),
+            Err(Error::NoStorageAccess(_))
+        ));
+        entry.delete_credential().unwrap();
+        assert!(matches!(entry.get_attributes(), Err(Error::NoEntry)));
+        assert!(matches!(
+            entry.update_attributes(&comment_map),
+            Err(Error::NoEntry)
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let store = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("original").unwrap();
+        store.make_ambiguous("service", "user", 2);
+        assert!(!entry.is_wrapper());
+        let wrappers = match entry.get_password() {
+            Err(Error::Ambiguous(wrappers)) => wrappers,
+            other => panic!("expected an ambiguous error, got {other:?}"),
+        };
+        assert_eq!(wrappers.len(), 2);
+        assert!(matches!(
+            entry.set_password("new"),
+            Err(Error::Ambiguous(_))
+        ));
+        assert!(matches!(
+            entry.delete_credential(),
+            Err(Error::Ambiguous(_))
+        ));
+        let first: &Cred = wrappers[0].as_any().downcast_ref().unwrap();
+        let second: &Cred = wrappers[1].as_any().downcast_ref().unwrap();
+        assert!(first.uuid.is_some() && first.uuid != second.uuid);
+        assert_eq!(wrappers[0].get_specifiers(), entry.get_specifiers());
+        assert!(wrappers[0].is_wrapper());
+        assert_eq!(wrappers[0].get_password().unwrap(), "");
+        wrappers[0].set_password("first").unwrap();
+        assert_eq!(wrappers[0].get_password().unwrap(), "first");
+        assert_eq!(store.search(&HashMap::new()).unwrap().len(), 2);
+        assert_eq!(store.count().unwrap(), 2);
+        wrappers[1].delete_credential().unwrap();
+        // with one left, the entry resolves to it
+        assert_eq!(entry.get_password().unwrap(), "first");
+        let resolved = entry.get_credential().unwrap();
+        assert!(resolved == wrappers[0]);
+        entry.delete_credential().unwrap();
+        assert!(matches!(wrappers[0].get_password(), Err(Error::NoEntry)));
+        // with none left, the entry's own credential shows through
+        assert_eq!(entry.get_password().unwrap(), "original");
+        assert_eq!(store.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_ambiguous_error_precedence() {
+        let store = Store::new().unwrap();
+        store.make_ambiguous("service", "user", 3);
+        let entry = store.build("service", "user", None).unwrap();
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_error(Error::NoStorageAccess("locked".into()));
+        assert!(matches!(
+            entry.get_password(),
+            Err(Error::NoStorageAccess(_))
+        ));
+        assert!(matches!(entry.get_attributes(), Err(Error::Ambiguous(w)) if w.len() == 3));
+        assert!(matches!(entry.exists(), Err(Error::Ambiguous(_))));
+    }
+
+    #[test]
+    fn test_recording() {
+        let store = Store::new().unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("unrecorded").unwrap();
+        assert!(store.take_calls().is_empty());
+        store.enable_recording();
+        entry.set_password("password").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "password");
+        let mock: &Cred = entry.as_any().downcast_ref().unwrap();
+        mock.set_attributes_from(&HashMap::from([("label", "")]));
+        entry
+            .update_attributes(&HashMap::from([("label", "mine")]))
+            .unwrap();
+        entry.delete_credential().unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        let service = "service".to_string();
+        let user = "user".to_string();
+        assert_eq!(
+            store.take_calls(),
+            vec![
+                MockCall::SetSecret {
+                    service: service.clone(),
+                    user: user.clone(),
+                    len: 8
+                },
+                MockCall::GetSecret {
+                    service: service.clone(),
+                    user: user.clone()
+                },
+                MockCall::UpdateAttributes {
+                    service: service.clone(),
+                    user: user.clone(),
+                    keys: vec!["label".to_string()]
+                },
+                MockCall::Delete {
+                    service: service.clone(),
+                    user: user.clone()
+                },
+                MockCall::GetSecret {
+                    service: service.clone(),
+                    user: user.clone()
+                },
+            ]
+        );
+        assert!(store.take_calls().is_empty());
+    }
+
+    #[test]
+    fn test_recording_ambiguous() {
+        let store = Store::new().unwrap();
+        store.make_ambiguous("service", "user", 2);
+        store.enable_recording();
+        let entry = store.build("service", "user", None).unwrap();
+        let wrappers = match entry.get_password() {
+            Err(Error::Ambiguous(wrappers)) => wrappers,
+            other => panic!("expected an ambiguous error, got {other:?}"),
+        };
+        wrappers[0].delete_credential().unwrap();
+        // the remaining credential is used, but the call is recorded once
+        entry.get_password().unwrap();
+        let calls = store.take_calls();
+        assert_eq!(calls.len(), 3);
+        assert!(matches!(calls[1], MockCall::Delete { .. }));
+        assert_eq!(calls[0], calls[2]);
+    }
+
+    #[test]
+    fn test_queue_error() {
+        let name = generate_random_string();
+        let entry = entry_new(&name, &name);
+        let mock: &Cred = entry.inner.as_any().downcast_ref().unwrap();
+        mock.queue_error(Error::NoStorageAccess("locked".into()));
+        mock.queue_error(Error::NoStorageAccess("still locked".into()));
+        let first = entry.set_password("password").unwrap_err();
+        assert!(first.is_transient());
+        let second = entry.set_password("password").unwrap_err();
+        assert!(second.is_transient());
+        entry.set_password("password").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "password");
+        mock.queue_error(Error::NoEntry);
+        mock.set_error(Error::TooLong("mock".to_string(), 3));
+        assert!(matches!(entry.get_password(), Err(Error::TooLong(_, 3))));
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        assert_eq!(entry.get_password().unwrap(), "password");
+    }
+
     #[test]
     fn test_search() {
         let store: Arc<CredentialStore> = Store::new().unwrap();
@@ -552,6 +1522,12 @@ mod tests {
         assert_eq!(all.len(), 2);
     }
 
+    #[test]
+    fn test_kind() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        assert_eq!(store.kind(), StoreKind::Mock);
+    }
+
     #[test]
     fn test_persistence() {
         let store: Arc<CredentialStore> = Store::new().unwrap();
@@ -560,4 +1536,19 @@ mod tests {
             CredentialPersistence::ProcessOnly
         ))
     }
+
+    #[test]
+    fn test_capabilities() {
+        let store: Arc<CredentialStore> = Store::new().unwrap();
+        assert_eq!(
+            store.capabilities(),
+            Capabilities {
+                supports_search: true,
+                supports_attributes: false,
+                supports_ambiguity: false,
+                persists_to_disk: false,
+                supports_secret_bytes: true,
+            }
+        );
+    }
 }
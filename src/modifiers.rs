@@ -0,0 +1,275 @@
+/*!
+
+# Default modifiers store
+
+This module provides a credential store combinator that wraps another
+credential store and merges a fixed set of default modifiers into every
+call to [build](CredentialStoreApi::build). Modifiers passed explicitly
+to a build call take precedence over the defaults.
+
+This is useful for apps that want every credential they create to get
+the same store-specific treatment (for example, being made immutable)
+without passing modifiers at each call site:
+
+```rust
+# #[cfg(feature = "sample")] {
+# use std::collections::HashMap;
+# use keyring_core::{Entry, Error, sample, modifiers::DefaultModifiersStore};
+let defaults = HashMap::from([("immutable", "true")]);
+let store = DefaultModifiersStore::new(sample::Store::new().unwrap(), &defaults).unwrap();
+keyring_core::set_default_store(store);
+let entry = Entry::new("my-service", "my-user").unwrap();
+entry.set_password("first").unwrap();
+assert!(matches!(entry.set_password("second"), Err(Error::Immutable)));
+# }
+```
+
+The defaults can only be modifiers that the wrapped store accepts
+when building. In the sample store, for example, these are `immutable`,
+`idempotency-key`, and `force-create` (whose value becomes the comment
+of the credential it creates), so a default `force-create` modifier is
+how an app can tag each credential it makes with a comment.
+
+The defaults are applied to every build, so a default modifier that
+does something when an entry is built does it for every entry. For
+example, a default `force-create` modifier in the sample store creates
+a new credential each time an entry is built, so the second entry built
+for a service and user makes its credentials ambiguous.
+
+All other store operations are passed through to the wrapped store,
+and the entries returned are those of the wrapped store.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::CredentialStoreApi;
+use crate::attributes::externalize_attributes;
+use crate::{Capabilities, CredentialPersistence, CredentialStore, Entry, Result};
+
+/// A store that applies default modifiers to every entry it builds.
+pub struct DefaultModifiersStore {
+    pub id: String,
+    pub inner: Arc<CredentialStore>,
+    pub defaults: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for DefaultModifiersStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultModifiersStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("inner", &self.inner)
+            .field("defaults", &self.defaults)
+            .finish()
+    }
+}
+
+impl DefaultModifiersStore {
+    /// Wrap the `inner` store so that its builds use the given `defaults`.
+    ///
+    /// The defaults are not checked here; the wrapped store will
+    /// reject them at build time if they are not acceptable.
+    pub fn new(inner: Arc<CredentialStore>, defaults: &HashMap<&str, &str>) -> Result<Arc<Self>> {
+        Ok(Arc::new(DefaultModifiersStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            inner,
+            defaults: externalize_attributes(defaults),
+        }))
+    }
+}
+
+impl CredentialStoreApi for DefaultModifiersStore {
+    fn vendor(&self) -> String {
+        String::from("Default modifiers store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// Build an entry in the wrapped store.
+    ///
+    /// The default modifiers are merged with the given ones,
+    /// and the given ones win if both specify the same key.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let mut merged: HashMap<&str, &str> = self
+            .defaults
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        if let Some(mods) = mods {
+            merged.extend(mods.iter().map(|(k, v)| (*k, *v)));
+        }
+        if merged.is_empty() {
+            self.inner.build(service, user, None)
+        } else {
+            self.inner.build(service, user, Some(&merged))
+        }
+    }
+
+    /// The credentials live in the wrapped store, so report its members.
+    fn member_vendors(&self) -> Vec<String> {
+        self.inner.member_vendors()
+    }
+
+    /// Search the wrapped store.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        self.inner.search(spec)
+    }
+
+    /// List the credentials in the wrapped store.
+    fn all(&self) -> Result<Vec<Entry>> {
+        self.inner.all()
+    }
+
+    /// Count the credentials in the wrapped store.
+    fn count(&self) -> Result<usize> {
+        self.inner.count()
+    }
+
+    /// Clear the wrapped store.
+    fn clear(&self) -> Result<usize> {
+        self.inner.clear()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// Credentials live in the wrapped store, so they have its persistence.
+    fn persistence(&self) -> CredentialPersistence {
+        self.inner.persistence()
+    }
+
+    /// Credentials live in the wrapped store, so they have its capabilities.
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::DefaultModifiersStore;
+    use crate::{CredentialPersistence, CredentialStore, Error, StoreKind, mock};
+
+    #[test]
+    fn test_no_defaults_passes_through() {
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(mock::Store::new().unwrap(), &HashMap::new()).unwrap();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("test").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "test");
+        assert_eq!(store.search(&HashMap::new()).unwrap().len(), 1);
+        assert!(matches!(
+            store.persistence(),
+            CredentialPersistence::ProcessOnly
+        ));
+    }
+
+    #[test]
+    fn test_kind_is_other() {
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(mock::Store::new().unwrap(), &HashMap::new()).unwrap();
+        assert_eq!(store.kind(), StoreKind::Other(store.vendor()));
+    }
+
+    #[test]
+    fn test_member_vendors() {
+        let inner: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(inner.clone(), &HashMap::new()).unwrap();
+        assert_eq!(inner.member_vendors(), vec![inner.vendor()]);
+        assert_eq!(store.member_vendors(), vec![inner.vendor()]);
+        let outer: Arc<CredentialStore> =
+            DefaultModifiersStore::new(store, &HashMap::new()).unwrap();
+        assert_eq!(outer.member_vendors(), vec![inner.vendor()]);
+    }
+
+    #[test]
+    fn test_defaults_are_checked_by_inner_store() {
+        let defaults = HashMap::from([("target", "doesn't matter")]);
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(mock::Store::new().unwrap(), &defaults).unwrap();
+        assert!(matches!(
+            store.build("service", "user", None),
+            Err(Error::NotSupportedByStore(_))
+        ));
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_default_and_explicit_modifiers() {
+        use crate::sample;
+
+        let defaults = HashMap::from([("immutable", "true")]);
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(sample::Store::new().unwrap(), &defaults).unwrap();
+        let entry = store.build("service1", "user1", None).unwrap();
+        entry.set_password("first").unwrap();
+        assert!(matches!(
+            entry.set_password("second"),
+            Err(Error::Immutable)
+        ));
+        let explicit = HashMap::from([("immutable", "false")]);
+        let entry = store.build("service2", "user2", Some(&explicit)).unwrap();
+        entry.set_password("first").unwrap();
+        entry.set_password("second").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        // building doesn't create credentials, so the defaults don't make them ambiguous
+        let again = store.build("service1", "user1", None).unwrap();
+        assert_eq!(again.get_password().unwrap(), "first");
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_default_and_explicit_comments() {
+        use crate::sample;
+
+        let defaults = HashMap::from([("force-create", "made by app 1.0")]);
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(sample::Store::new().unwrap(), &defaults).unwrap();
+        let entry = store.build("service1", "user1", None).unwrap();
+        entry.set_password("password").unwrap();
+        let attrs = entry.get_attributes().unwrap();
+        assert_eq!(attrs["comment"], "made by app 1.0");
+        let explicit = HashMap::from([("force-create", "made by hand")]);
+        let entry = store.build("service2", "user2", Some(&explicit)).unwrap();
+        let attrs = entry.get_attributes().unwrap();
+        assert_eq!(attrs["comment"], "made by hand");
+    }
+
+    #[test]
+    fn test_store_wide_operations_are_forwarded() {
+        let inner: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let store: Arc<CredentialStore> =
+            DefaultModifiersStore::new(inner.clone(), &HashMap::new()).unwrap();
+        for user in ["user1", "user2"] {
+            let entry = store.build("service", user, None).unwrap();
+            entry.set_password("password").unwrap();
+        }
+        assert_eq!(store.count().unwrap(), 2);
+        assert_eq!(store.all().unwrap().len(), 2);
+        assert_eq!(store.clear().unwrap(), 2);
+        assert_eq!(inner.count().unwrap(), 0);
+    }
+}
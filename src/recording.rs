@@ -0,0 +1,718 @@
+/*!
+
+# Recording and replay stores
+
+This module provides a pair of credential stores for testing clients
+offline. A [RecordingStore] wraps a real store and logs each operation
+on its entries, along with the result, in a [Transcript]. A [ReplayStore]
+built from that transcript then serves the recorded results without
+any real store, so a session recorded once against a real backend
+can be replayed deterministically in tests:
+
+```rust
+# use std::sync::Arc;
+# use keyring_core::{CredentialStore, Error, api::CredentialStoreApi, mock};
+# use keyring_core::recording::{RecordingStore, ReplayStore};
+let recorder = RecordingStore::new(mock::Store::new().unwrap()).unwrap();
+let entry = recorder.build("my-service", "my-user", None).unwrap();
+entry.set_password("my password").unwrap();
+assert_eq!(entry.get_password().unwrap(), "my password");
+let replay = ReplayStore::from_transcript(recorder.transcript()).unwrap();
+let entry = replay.build("my-service", "my-user", None).unwrap();
+entry.set_password("my password").unwrap();
+assert_eq!(entry.get_password().unwrap(), "my password");
+// the transcript has been used up
+assert!(matches!(entry.get_password(), Err(Error::Invalid(_, _))));
+```
+
+The operations recorded are building an entry, and setting, getting,
+deleting, and renaming its credential and getting and updating its attributes.
+Other operations on entries (such as
+[get_password](crate::Entry::get_password)) are built from these, so
+they are recorded too. Searches are not recorded, and the replay store
+doesn't support them.
+
+A replay store serves each call with the first recorded result, not yet
+served, of the same operation on the same service and user. Calls
+that have no such result fail with an [Invalid](Error::Invalid) error.
+Errors are recorded by kind and description only, so replayed
+[Ambiguous](Error::Ambiguous) errors don't contain any entries, and
+platform errors are replayed as [PlatformFailure](Error::PlatformFailure)
+errors containing their description.
+
+Transcripts contain secrets, both those written and those read. Use
+[Transcript::map_secrets] to redact or encrypt them before saving a
+transcript, and (if they were encrypted) to decrypt them before replay.
+With the `serde` feature, transcripts can be serialized and deserialized.
+ */
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::{CredentialApi, CredentialStoreApi};
+use crate::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, Entry, Error, Result,
+};
+
+/// An operation on an entry, with its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Call {
+    Build,
+    SetSecret(Vec<u8>),
+    GetSecret,
+    GetAttributes,
+    UpdateAttributes(HashMap<String, String>),
+    DeleteCredential,
+    /// The new service and user.
+    Rename(String, String),
+}
+
+impl Call {
+    fn name(&self) -> &'static str {
+        match self {
+            Call::Build => "build",
+            Call::SetSecret(_) => "set_secret",
+            Call::GetSecret => "get_secret",
+            Call::GetAttributes => "get_attributes",
+            Call::UpdateAttributes(_) => "update_attributes",
+            Call::DeleteCredential => "delete_credential",
+            Call::Rename(_, _) => "rename",
+        }
+    }
+}
+
+/// The result of an operation on an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// The operation succeeded and had no value.
+    Done,
+    Secret(Vec<u8>),
+    Attributes(HashMap<String, String>),
+    Failed(RecordedError),
+}
+
+/// An error, as recorded in a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordedError {
+    NoEntry,
+    /// The value is the number of matching credentials.
+    Ambiguous(usize),
+    NoStorageAccess(String),
+    Invalid(String, String),
+    NotSupportedByStore(String),
+    Immutable,
+    /// Any other error, with its description.
+    Other(String),
+}
+
+impl From<&Error> for RecordedError {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::NoEntry => RecordedError::NoEntry,
+            Error::Ambiguous(entries) => RecordedError::Ambiguous(entries.len()),
+            Error::NoStorageAccess(err) => RecordedError::NoStorageAccess(err.to_string()),
+            Error::Invalid(attr, reason) => RecordedError::Invalid(attr.clone(), reason.clone()),
+            Error::NotSupportedByStore(vendor) => {
+                RecordedError::NotSupportedByStore(vendor.clone())
+            }
+            Error::Immutable => RecordedError::Immutable,
+            err => RecordedError::Other(err.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for Error {
+    fn from(err: RecordedError) -> Self {
+        match err {
+            RecordedError::NoEntry => Error::NoEntry,
+            RecordedError::Ambiguous(_) => Error::Ambiguous(vec![]),
+            RecordedError::NoStorageAccess(err) => Error::NoStorageAccess(Box::from(err)),
+            RecordedError::Invalid(attr, reason) => Error::Invalid(attr, reason),
+            RecordedError::NotSupportedByStore(vendor) => Error::NotSupportedByStore(vendor),
+            RecordedError::Immutable => Error::Immutable,
+            RecordedError::Other(err) => Error::PlatformFailure(Box::from(err)),
+        }
+    }
+}
+
+/// One operation on an entry and its result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record {
+    pub service: String,
+    pub user: String,
+    pub call: Call,
+    pub outcome: Outcome,
+}
+
+/// The operations recorded by a [RecordingStore], in the order they were done.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transcript {
+    pub records: Vec<Record>,
+}
+
+impl Transcript {
+    /// Replace every secret in the transcript with the result of `f` on it.
+    ///
+    /// This covers both the secrets written and the secrets read.
+    /// Use it to redact secrets (by replacing them with something fixed)
+    /// or to encrypt or decrypt them.
+    pub fn map_secrets(&mut self, mut f: impl FnMut(&[u8]) -> Vec<u8>) {
+        for record in self.records.iter_mut() {
+            if let Call::SetSecret(secret) = &mut record.call {
+                *secret = f(secret);
+            }
+            if let Outcome::Secret(secret) = &mut record.outcome {
+                *secret = f(secret);
+            }
+        }
+    }
+}
+
+fn new_id() -> String {
+    format!(
+        "Crate version {}, Instantiated at {}",
+        env!("CARGO_PKG_VERSION"),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::new(0, 0))
+            .as_secs_f64()
+    )
+}
+
+/// A store that records the operations on its entries.
+pub struct RecordingStore {
+    pub id: String,
+    pub inner: Arc<CredentialStore>,
+    log: Arc<Mutex<Transcript>>,
+}
+
+impl std::fmt::Debug for RecordingStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl RecordingStore {
+    /// Record the operations on entries built by `inner`.
+    pub fn new(inner: Arc<CredentialStore>) -> Result<Arc<Self>> {
+        Ok(Arc::new(RecordingStore {
+            id: new_id(),
+            inner,
+            log: Arc::new(Mutex::new(Transcript::default())),
+        }))
+    }
+
+    /// A copy of the operations recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.log
+            .lock()
+            .expect("Poisoned transcript in RecordingStore: please report a bug!")
+            .clone()
+    }
+}
+
+impl CredentialStoreApi for RecordingStore {
+    fn vendor(&self) -> String {
+        String::from("Recording store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The members are those of the recorded store.
+    fn member_vendors(&self) -> Vec<String> {
+        self.inner.member_vendors()
+    }
+
+    /// Build an entry in the recorded store, and record the result.
+    ///
+    /// The modifiers are passed to the recorded store, but they aren't recorded.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let cred = RecordingCred {
+            log: self.log.clone(),
+            service: service.to_string(),
+            user: user.to_string(),
+            inner: None,
+        };
+        let entry = self.inner.build(service, user, mods);
+        cred.record(Call::Build, entry.as_ref().map(|_| Outcome::Done));
+        let inner = Some(entry?.inner);
+        Ok(Entry::new_with_credential(Arc::new(RecordingCred {
+            inner,
+            ..cred
+        })))
+    }
+
+    /// Search the recorded store, without recording the search.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        self.inner.search(spec)
+    }
+
+    /// List the credentials in the recorded store, without recording the listing.
+    fn all(&self) -> Result<Vec<Entry>> {
+        self.inner.all()
+    }
+
+    /// Count the credentials in the recorded store, without recording the count.
+    fn count(&self) -> Result<usize> {
+        self.inner.count()
+    }
+
+    /// Clear the recorded store, without recording the clear.
+    fn clear(&self) -> Result<usize> {
+        self.inner.clear()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn persistence(&self) -> CredentialPersistence {
+        self.inner.persistence()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential whose operations are recorded.
+pub struct RecordingCred {
+    log: Arc<Mutex<Transcript>>,
+    pub service: String,
+    pub user: String,
+    inner: Option<Arc<Credential>>,
+}
+
+impl std::fmt::Debug for RecordingCred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingCred")
+            .field("service", &self.service)
+            .field("user", &self.user)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl RecordingCred {
+    fn inner(&self) -> &Arc<Credential> {
+        self.inner
+            .as_ref()
+            .expect("RecordingCred used before it was built: please report a bug!")
+    }
+
+    fn record(&self, call: Call, outcome: std::result::Result<Outcome, &Error>) {
+        let outcome = outcome.unwrap_or_else(|err| Outcome::Failed(err.into()));
+        self.log
+            .lock()
+            .expect("Poisoned transcript in RecordingCred: please report a bug!")
+            .records
+            .push(Record {
+                service: self.service.clone(),
+                user: self.user.clone(),
+                call,
+                outcome,
+            });
+    }
+}
+
+impl CredentialApi for RecordingCred {
+    /// See the API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        let result = self.inner().set_secret(secret);
+        let outcome = result.as_ref().map(|_| Outcome::Done);
+        self.record(Call::SetSecret(secret.to_vec()), outcome);
+        result
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        let result = self.inner().get_secret();
+        let outcome = result.as_ref().map(|s| Outcome::Secret(s.clone()));
+        self.record(Call::GetSecret, outcome);
+        result
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        let result = self.inner().get_attributes();
+        let outcome = result.as_ref().map(|a| Outcome::Attributes(a.clone()));
+        self.record(Call::GetAttributes, outcome);
+        result
+    }
+
+    /// See the API docs.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        let result = self.inner().update_attributes(attrs);
+        let call = Call::UpdateAttributes(
+            attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        self.record(call, result.as_ref().map(|_| Outcome::Done));
+        result
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        let result = self.inner().delete_credential();
+        let outcome = result.as_ref().map(|_| Outcome::Done);
+        self.record(Call::DeleteCredential, outcome);
+        result
+    }
+
+    /// See the API docs.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        let result = self.inner().rename(new_service, new_user);
+        let call = Call::Rename(new_service.to_string(), new_user.to_string());
+        self.record(call, result.as_ref().map(|_| Outcome::Done));
+        result
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper is recorded under the same service and user as this credential.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let inner = match self.inner().get_credential()? {
+            Some(wrapper) => wrapper,
+            None => return Ok(None),
+        };
+        Ok(Some(Arc::new(RecordingCred {
+            log: self.log.clone(),
+            service: self.service.clone(),
+            user: self.user.clone(),
+            inner: Some(inner),
+        })))
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    /// See the API docs.
+    fn is_specifier(&self) -> bool {
+        self.inner().is_specifier()
+    }
+
+    /// See the API docs.
+    fn is_wrapper(&self) -> bool {
+        self.inner().is_wrapper()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A store that replays the results in a [Transcript].
+pub struct ReplayStore {
+    pub id: String,
+    records: Arc<Mutex<Vec<Option<Record>>>>,
+}
+
+impl std::fmt::Debug for ReplayStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl ReplayStore {
+    /// Create a store that replays the given transcript.
+    pub fn from_transcript(transcript: Transcript) -> Result<Arc<Self>> {
+        Ok(Arc::new(ReplayStore {
+            id: new_id(),
+            records: Arc::new(Mutex::new(
+                transcript.records.into_iter().map(Some).collect(),
+            )),
+        }))
+    }
+}
+
+/// Serve a call from the first unserved record that matches it.
+fn replay(
+    records: &Mutex<Vec<Option<Record>>>,
+    service: &str,
+    user: &str,
+    call: &Call,
+) -> Result<Outcome> {
+    let mut records = records
+        .lock()
+        .expect("Poisoned transcript in ReplayStore: please report a bug!");
+    let found = records.iter_mut().find(|record| {
+        record.as_ref().is_some_and(|record| {
+            record.service == service
+                && record.user == user
+                && std::mem::discriminant(&record.call) == std::mem::discriminant(call)
+        })
+    });
+    match found.and_then(Option::take) {
+        Some(record) => match record.outcome {
+            Outcome::Failed(err) => Err(err.into()),
+            outcome => Ok(outcome),
+        },
+        None => Err(Error::Invalid(
+            call.name().to_string(),
+            format!("no recorded result for <{service}, {user}>"),
+        )),
+    }
+}
+
+impl CredentialStoreApi for ReplayStore {
+    fn vendor(&self) -> String {
+        String::from("Replay store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// Replay the building of an entry.
+    ///
+    /// The modifiers are ignored.
+    fn build(&self, service: &str, user: &str, _: Option<&HashMap<&str, &str>>) -> Result<Entry> {
+        replay(&self.records, service, user, &Call::Build)?;
+        Ok(Entry::new_with_credential(Arc::new(ReplayCred {
+            records: self.records.clone(),
+            service: service.to_string(),
+            user: user.to_string(),
+        })))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// Nothing is stored.
+    fn persistence(&self) -> CredentialPersistence {
+        CredentialPersistence::EntryOnly
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential whose operations are served from a transcript.
+pub struct ReplayCred {
+    records: Arc<Mutex<Vec<Option<Record>>>>,
+    pub service: String,
+    pub user: String,
+}
+
+impl std::fmt::Debug for ReplayCred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayCred")
+            .field("service", &self.service)
+            .field("user", &self.user)
+            .finish()
+    }
+}
+
+impl ReplayCred {
+    fn replay(&self, call: Call) -> Result<Outcome> {
+        replay(&self.records, &self.service, &self.user, &call)
+    }
+}
+
+/// The error for a recorded outcome that doesn't fit the call it was recorded for.
+fn mismatch(outcome: Outcome) -> Error {
+    Error::BadDataFormat(
+        vec![],
+        Box::from(format!("unexpected recorded outcome: {outcome:?}")),
+    )
+}
+
+impl CredentialApi for ReplayCred {
+    /// See the API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        match self.replay(Call::SetSecret(secret.to_vec()))? {
+            Outcome::Done => Ok(()),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        match self.replay(Call::GetSecret)? {
+            Outcome::Secret(secret) => Ok(secret),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        match self.replay(Call::GetAttributes)? {
+            Outcome::Attributes(attrs) => Ok(attrs),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    fn update_attributes(&self, _: &HashMap<&str, &str>) -> Result<()> {
+        match self.replay(Call::UpdateAttributes(HashMap::new()))? {
+            Outcome::Done => Ok(()),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        match self.replay(Call::DeleteCredential)? {
+            Outcome::Done => Ok(()),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        let call = Call::Rename(new_service.to_string(), new_user.to_string());
+        match self.replay(call)? {
+            Outcome::Done => Ok(()),
+            outcome => Err(mismatch(outcome)),
+        }
+    }
+
+    /// See the API docs.
+    ///
+    /// Wrappers aren't recorded separately from their specifiers,
+    /// so every replayed credential acts as its own wrapper.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        Ok(None)
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    /// Every replayed credential acts as its own wrapper.
+    fn is_wrapper(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::{Call, Outcome, RecordingStore, ReplayStore};
+    use crate::api::CredentialStoreApi;
+    use crate::{CredentialStore, Error, mock};
+
+    #[test]
+    fn test_unexpected_calls() {
+        let recorder = RecordingStore::new(mock::Store::new().unwrap()).unwrap();
+        let entry = recorder.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        let replay: Arc<CredentialStore> =
+            ReplayStore::from_transcript(recorder.transcript()).unwrap();
+        assert!(matches!(
+            replay.build("service", "other", None),
+            Err(Error::Invalid(_, _))
+        ));
+        let entry = replay.build("service", "user", None).unwrap();
+        assert!(matches!(
+            entry.set_password("new"),
+            Err(Error::Invalid(call, _)) if call == "set_secret"
+        ));
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        assert!(matches!(entry.get_password(), Err(Error::Invalid(_, _))));
+        assert!(matches!(
+            replay.search(&HashMap::new()),
+            Err(Error::NotSupportedByStore(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_secrets() {
+        let recorder = RecordingStore::new(mock::Store::new().unwrap()).unwrap();
+        let entry = recorder.build("service", "user", None).unwrap();
+        entry.set_password("secret").unwrap();
+        entry.get_password().unwrap();
+        let mut transcript = recorder.transcript();
+        transcript.map_secrets(|_| b"redacted".to_vec());
+        assert_eq!(
+            transcript.records[1].call,
+            Call::SetSecret(b"redacted".to_vec())
+        );
+        assert_eq!(
+            transcript.records[2].outcome,
+            Outcome::Secret(b"redacted".to_vec())
+        );
+        let replay = ReplayStore::from_transcript(transcript).unwrap();
+        let entry = replay.build("service", "user", None).unwrap();
+        entry.set_password("secret").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "redacted");
+    }
+
+    #[test]
+    fn test_rename_is_recorded() {
+        let recorder = RecordingStore::new(mock::Store::new().unwrap()).unwrap();
+        let entry = recorder.build("service", "user", None).unwrap();
+        assert!(matches!(
+            entry.rename("service", "other"),
+            Err(Error::NotSupportedByStore(_))
+        ));
+        let transcript = recorder.transcript();
+        assert_eq!(
+            transcript.records[1].call,
+            Call::Rename("service".to_string(), "other".to_string())
+        );
+        let replay = ReplayStore::from_transcript(transcript).unwrap();
+        let entry = replay.build("service", "user", None).unwrap();
+        assert!(matches!(
+            entry.rename("service", "other"),
+            Err(Error::NotSupportedByStore(_))
+        ));
+    }
+
+    #[test]
+    fn test_store_wide_operations_are_not_recorded() {
+        let inner: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let recorder = RecordingStore::new(inner.clone()).unwrap();
+        let entry = recorder.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        assert_eq!(recorder.count().unwrap(), 1);
+        assert_eq!(recorder.all().unwrap().len(), 1);
+        assert_eq!(recorder.clear().unwrap(), 1);
+        assert_eq!(inner.count().unwrap(), 0);
+        assert_eq!(recorder.transcript().records.len(), 2);
+    }
+}
@@ -1,14 +1,20 @@
 use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use dashmap::DashMap;
+use dashmap::mapref::one::RefMut;
+#[cfg(feature = "contention-stats")]
+use dashmap::try_result::TryResult;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::store::{CredValue, Store};
-use crate::attributes::parse_attributes;
-use crate::{Credential, Entry, Error, Result, api::CredentialApi};
+use super::schema::RESERVED_ATTRIBUTES;
+use super::store::{CredValue, Store, has_idempotency_key, primary_uuid};
+use crate::api::{CredentialApi, constant_time_eq, increment_counter};
+use crate::secret::scrub;
+use crate::{Credential, CredentialStore, Entry, Error, Result, SetOutcome};
 
 /// Credentials are specified by a pair of service name and username.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -31,6 +37,10 @@ pub struct CredKey {
     pub store: Arc<Store>,
     pub id: CredId,
     pub uuid: Option<String>,
+    /// Whether secrets set through this key make the credential immutable
+    pub immutable: bool,
+    /// The idempotency key recorded on credentials written through this key
+    pub idempotency_key: Option<String>,
 }
 
 impl std::fmt::Debug for CredKey {
@@ -38,6 +48,8 @@ impl std::fmt::Debug for CredKey {
         f.debug_struct("CredKey")
             .field("id", &self.id)
             .field("uuid", &self.uuid)
+            .field("immutable", &self.immutable)
+            .field("idempotency_key", &self.idempotency_key)
             .finish()
     }
 }
@@ -52,6 +64,16 @@ impl CredKey {
     /// It knows about the difference between specifiers and wrappers
     /// and acts accordingly.
     pub fn with_unique_pair<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&String, &mut CredValue) -> T,
+    {
+        let _guard = self.store.read_ids();
+        self.with_unique_pair_unguarded(f)
+    }
+
+    /// The body of [with_unique_pair](CredKey::with_unique_pair), for
+    /// callers that already hold the store's rename lock.
+    fn with_unique_pair_unguarded<T, F>(&self, f: F) -> Result<T>
     where
         F: FnOnce(&String, &mut CredValue) -> T,
     {
@@ -59,13 +81,20 @@ impl CredKey {
             // this is a wrapper, look for the cred, and if found get it, else fail
             Some(key) => match self.store.creds.get(&self.id) {
                 None => Err(Error::NoEntry),
-                Some(pair) => match pair.value().get_mut(key) {
-                    None => Err(Error::NoEntry),
-                    Some(mut cred) => {
-                        let (key, val) = cred.pair_mut();
-                        Ok(f(key, val))
+                Some(pair) => {
+                    let key = if self.store.options.constant_time_probe {
+                        probe_group(pair.value(), key)
+                    } else {
+                        Some(key.clone())
+                    };
+                    match key.and_then(|key| lock_cred(&self.store, pair.value(), &key)) {
+                        None => Err(Error::NoEntry),
+                        Some(mut cred) => {
+                            let (key, val) = cred.pair_mut();
+                            Ok(f(key, val))
+                        }
                     }
-                },
+                }
             },
             // this is a specifier
             None => {
@@ -80,9 +109,15 @@ impl CredKey {
                             0 => Err(Error::NoEntry),
                             // just one current cred, get it
                             1 => {
-                                let mut first = creds.iter_mut().next().unwrap();
-                                let (key, val) = first.pair_mut();
-                                Ok(f(key, val))
+                                let uuid = creds.iter().next().map(|cred| cred.key().clone());
+                                match uuid.and_then(|uuid| lock_cred(&self.store, creds, &uuid)) {
+                                    // the cred was deleted since we counted it
+                                    None => Err(Error::NoEntry),
+                                    Some(mut cred) => {
+                                        let (key, val) = cred.pair_mut();
+                                        Ok(f(key, val))
+                                    }
+                                }
                             }
                             // more than one cred - ambiguous!
                             _ => {
@@ -92,6 +127,8 @@ impl CredKey {
                                         store: self.store.clone(),
                                         id: self.id.clone(),
                                         uuid: Some(cred.key().clone()),
+                                        immutable: false,
+                                        idempotency_key: None,
                                     };
                                     entries.push(Entry::new_with_credential(Arc::new(key)));
                                 }
@@ -112,6 +149,19 @@ impl CredKey {
         self.with_unique_pair(|_, cred| f(cred))
     }
 
+    /// Call `f` with a borrowed view of the secret of the sole credential for this cred.
+    ///
+    /// The secret is not copied: `f` sees it while the store holds
+    /// the lock on the credential. Because of this, `f` must not
+    /// call back into the store (for example, by reading or writing
+    /// this or any other credential), because that may deadlock.
+    pub fn with_secret<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> T,
+    {
+        self.with_unique_cred(|cred| f(&cred.secret))
+    }
+
     /// This returns the UUID of the sole credential for this cred.
     pub fn get_uuid(&self) -> Result<String> {
         self.with_unique_pair(|uuid, _| uuid.to_string())
@@ -119,60 +169,211 @@ impl CredKey {
 
     /// This returns the comment of the sole credential for this cred.
     pub fn get_comment(&self) -> Result<Option<String>> {
-        self.with_unique_pair(|_, cred| cred.comment.clone())
+        self.with_unique_cred(|cred| cred.comment.clone())
     }
-}
 
-impl CredentialApi for CredKey {
-    /// See the API docs.
-    fn set_secret(&self, secret: &[u8]) -> Result<()> {
-        let result = self.with_unique_cred(|cred| cred.secret = secret.to_vec());
+    /// This returns the creation date of the sole credential for this cred.
+    ///
+    /// Only credentials made with the `force-create` modifier have one.
+    pub fn get_creation_date(&self) -> Result<Option<String>> {
+        self.with_unique_cred(|cred| cred.creation_date.clone())
+    }
+
+    /// This returns the modification date of the sole credential for this cred, if it has one.
+    pub fn get_modified_date(&self) -> Result<Option<String>> {
+        self.with_unique_cred(|cred| cred.modified_date.clone())
+    }
+
+    /// Set the secret of a credential found by this key, unless it's immutable.
+    ///
+    /// If this key was built with the `immutable` modifier,
+    /// the credential becomes immutable once its secret is set.
+    fn write_secret(&self, cred: &mut CredValue, secret: &[u8]) -> Result<()> {
+        if cred.immutable {
+            return Err(Error::Immutable);
+        }
+        scrub(&mut cred.secret);
+        cred.secret = secret.to_vec();
+        cred.modified_date = Some(self.store.now());
+        cred.immutable = self.immutable;
+        if self.idempotency_key.is_some() {
+            cred.idempotency_key = self.idempotency_key.clone();
+        }
+        Ok(())
+    }
+
+    /// Delete the credential with the given UUID if its secret is `expected`.
+    ///
+    /// The comparison and the removal are done under a single lock.
+    /// As with [delete_credential](CredentialApi::delete_credential),
+    /// immutable credentials can be deleted.
+    fn remove_if_secret(&self, uuid: &str, expected: &[u8]) -> Result<bool> {
+        let _guard = self.store.read_ids();
+        let Some(creds) = self.store.creds.get(&self.id) else {
+            return Ok(false);
+        };
+        let removed = creds.remove_if(uuid, |_, cred| cred.secret == expected);
+        Ok(removed.is_some())
+    }
+
+    /// Set or delete the credential for this key if there isn't one.
+    ///
+    /// A specifier creates the credential under a single lock
+    /// on the credentials for its ID. A wrapper can't recreate
+    /// its credential, so it fails with a NoEntry error.
+    fn set_if_missing(&self, new: Option<&[u8]>) -> Result<bool> {
+        if self.uuid.is_some() {
+            return match self.get_uuid() {
+                Ok(_) => Ok(false),
+                Err(Error::NoEntry) if new.is_none() => Ok(true),
+                Err(e) => Err(e),
+            };
+        }
+        let _guard = self.store.read_ids();
+        let creds = self.store.creds.entry(self.id.clone()).or_default();
+        if !creds.is_empty() {
+            return Ok(false);
+        }
+        if let Some(new) = new {
+            creds.insert(Uuid::new_v4().to_string(), self.new_value(new));
+        }
+        Ok(true)
+    }
+
+    // These make the changes for the API methods of the same names,
+    // which autosave the store after them, once the locks taken
+    // here have been released.
+
+    pub(super) fn set_secret_reporting_unsaved(&self, secret: &[u8]) -> Result<SetOutcome> {
+        if self.uuid.is_none() {
+            let _guard = self.store.read_ids();
+            if let Some(creds) = self.store.creds.get(&self.id) {
+                if has_idempotency_key(&creds, self.idempotency_key.as_deref()) {
+                    return Ok(SetOutcome::Updated);
+                }
+            }
+        }
+        let result = self.with_unique_cred(|cred| self.write_secret(cred, secret));
         match result {
-            Ok(_) => Ok(()),
+            Ok(result) => result.map(|_| SetOutcome::Updated),
             // a specifier with no credential: create the cred
             Err(Error::NoEntry) if self.uuid.is_none() => {
-                let value = CredValue::new(secret);
+                let _guard = self.store.read_ids();
+                let value = self.new_value(secret);
                 let creds = DashMap::new();
                 creds.insert(Uuid::new_v4().to_string(), value);
                 self.store.creds.insert(self.id.clone(), creds);
-                Ok(())
+                Ok(SetOutcome::Created)
             }
             // a wrapper with no cred or an ambiguous spec
             Err(e) => Err(e),
         }
     }
 
-    /// See the API docs.
-    fn get_secret(&self) -> Result<Vec<u8>> {
-        self.with_unique_cred(|cred| cred.secret.clone())
+    fn set_secret_if_changed_unsaved(&self, secret: &[u8]) -> Result<bool> {
+        let result = self.with_unique_cred(|cred| {
+            if cred.secret == secret {
+                Ok(false)
+            } else {
+                self.write_secret(cred, secret).map(|_| true)
+            }
+        });
+        match result {
+            Ok(result) => result,
+            // a specifier with no credential: create the cred
+            Err(Error::NoEntry) if self.uuid.is_none() => {
+                self.set_secret_reporting_unsaved(secret)?;
+                Ok(true)
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    /// See the API docs.
-    ///
-    /// The possible attributes on credentials in this store are `uuid`, `comment`,
-    /// and `creation-date`.
-    fn get_attributes(&self) -> Result<HashMap<String, String>> {
-        self.with_unique_pair(|uuid, cred| get_attrs(uuid, cred))
+    fn increment_secret_u64_unsaved(&self, delta: u64) -> Result<u64> {
+        let increment = |cred: &mut CredValue| {
+            if cred.immutable {
+                return Err(Error::Immutable);
+            }
+            let value = increment_counter(&cred.secret, delta)?;
+            self.write_secret(cred, &value.to_be_bytes())?;
+            Ok(value)
+        };
+        loop {
+            match self.with_unique_cred(increment) {
+                Ok(result) => return result,
+                // a specifier with no credential: create the cred, unless we lose a race
+                Err(Error::NoEntry) if self.uuid.is_none() => {
+                    let _guard = self.store.read_ids();
+                    let creds = self.store.creds.entry(self.id.clone()).or_default();
+                    if creds.is_empty() {
+                        let value = self.new_value(&delta.to_be_bytes());
+                        creds.insert(Uuid::new_v4().to_string(), value);
+                        return Ok(delta);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 
-    /// See the API docs.
-    ///
-    /// Only the `comment` attribute can be updated.
-    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
-        parse_attributes(&["comment"], Some(attrs))?;
-        self.with_unique_cred(|cred| update_attrs(cred, attrs))
+    fn compare_and_set_unsaved(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        let Some(expected) = expected else {
+            return self.set_if_missing(new);
+        };
+        let result = match new {
+            Some(new) => self.with_unique_cred(|cred| {
+                if cred.secret == expected {
+                    self.write_secret(cred, new).map(|_| true)
+                } else {
+                    Ok(false)
+                }
+            }),
+            None => self
+                .get_uuid()
+                .map(|uuid| self.remove_if_secret(&uuid, expected)),
+        };
+        match result {
+            Ok(result) => result,
+            Err(Error::NoEntry) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
-    /// See the API docs.
-    fn delete_credential(&self) -> Result<()> {
+    fn update_attributes_unsaved(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        for key in RESERVED_ATTRIBUTES {
+            if attrs.contains_key(key) {
+                return Err(Error::Invalid(
+                    key.to_string(),
+                    "can't be updated".to_string(),
+                ));
+            }
+        }
+        let schema = self.store.schema.read().expect("RwLock bug at schema get!");
+        self.with_unique_pair(|uuid, cred| {
+            if cred.immutable {
+                return Err(Error::Immutable);
+            }
+            if let Some(schema) = schema.as_ref() {
+                schema.validate(&get_attrs(uuid, cred), attrs)?;
+            }
+            update_attrs(cred, attrs);
+            cred.modified_date = Some(self.store.now());
+            Ok(())
+        })?
+    }
+
+    pub(super) fn delete_credential_unsaved(&self) -> Result<()> {
         let result = self.with_unique_cred(|_| ());
         match result {
             // there is exactly one matching cred, delete it
             Ok(_) => {
+                let _guard = self.store.read_ids();
                 match self.uuid.as_ref() {
                     // this is a wrapper, delete the credential key from the map
                     Some(uuid) => {
-                        self.store.creds.get(&self.id).unwrap().value().remove(uuid);
+                        if let Some(creds) = self.store.creds.get(&self.id) {
+                            creds.value().remove(uuid);
+                        }
                         Ok(())
                     }
                     // this is a specifier, and there's only credential, delete the map
@@ -187,6 +388,201 @@ impl CredentialApi for CredKey {
         }
     }
 
+    fn rename_unsaved(&self, service: &str, user: &str) -> Result<()> {
+        let target = self.store.cred_id(service, user);
+        let _guard = self
+            .store
+            .rename_lock
+            .write()
+            .expect("RwLock bug at rename!");
+        let uuid = self.with_unique_pair_unguarded(|uuid, _| uuid.clone())?;
+        if target == self.id {
+            return Ok(());
+        }
+        let occupied = self
+            .store
+            .creds
+            .get(&target)
+            .is_some_and(|group| !group.is_empty());
+        if occupied {
+            return Err(Error::Invalid(
+                format!("service {service}, user {user}"),
+                "already has a credential".to_string(),
+            ));
+        }
+        let (removed, now_empty) = match self.store.creds.get(&self.id) {
+            None => (None, false),
+            Some(group) => {
+                let removed = group.remove(&uuid);
+                (removed, group.is_empty())
+            }
+        };
+        let Some((uuid, value)) = removed else {
+            return Err(Error::NoEntry);
+        };
+        if now_empty {
+            self.store.creds.remove(&self.id);
+        }
+        self.store
+            .creds
+            .entry(target)
+            .or_default()
+            .insert(uuid, value);
+        Ok(())
+    }
+
+    /// Make a new credential with the given secret, as set through this key.
+    fn new_value(&self, secret: &[u8]) -> CredValue {
+        let mut value = CredValue::new(secret);
+        value.modified_date = Some(self.store.now());
+        value.immutable = self.immutable;
+        value.idempotency_key = self.idempotency_key.clone();
+        value
+    }
+}
+
+impl CredentialApi for CredKey {
+    /// See the API docs.
+    ///
+    /// If this is a specifier built with an `idempotency-key` modifier,
+    /// and a credential for it was already written with the same key,
+    /// this does nothing and succeeds.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.set_secret_reporting(secret).map(|_| ())
+    }
+
+    /// See the API docs.
+    ///
+    /// An idempotent retry that changes nothing reports an update,
+    /// because the credential already existed.
+    fn set_secret_reporting(&self, secret: &[u8]) -> Result<SetOutcome> {
+        let outcome = self.set_secret_reporting_unsaved(secret)?;
+        self.store.autosave()?;
+        Ok(outcome)
+    }
+
+    /// See the API docs.
+    ///
+    /// The comparison and the write are done under a single lock
+    /// on an existing credential.
+    fn set_secret_if_changed(&self, secret: &[u8]) -> Result<bool> {
+        let changed = self.set_secret_if_changed_unsaved(secret)?;
+        self.store.autosave()?;
+        Ok(changed)
+    }
+
+    /// See the API docs.
+    ///
+    /// Increments are atomic: an existing counter is updated under
+    /// a single lock, and a missing one is created only if no other
+    /// thread has created it first.
+    fn increment_secret_u64(&self, delta: u64) -> Result<u64> {
+        let value = self.increment_secret_u64_unsaved(delta)?;
+        self.store.autosave()?;
+        Ok(value)
+    }
+
+    /// See the API docs.
+    ///
+    /// The comparison and the change are atomic: they are done under a
+    /// single lock on the credential (or, when there's no credential, on
+    /// the credentials for the entry's service and user). If the entry is
+    /// ambiguous, an expected secret produces an Ambiguous error, and an
+    /// expected absence of a credential doesn't match.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        let changed = self.compare_and_set_unsaved(expected, new)?;
+        self.store.autosave()?;
+        Ok(changed)
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        self.with_unique_cred(|cred| cred.secret.clone())
+    }
+
+    /// See the API docs.
+    ///
+    /// The secret is lent out without copying it.
+    fn with_secret(&self, f: &mut dyn FnMut(&[u8])) -> Result<()> {
+        CredKey::with_secret(self, f)
+    }
+
+    /// See the API docs.
+    ///
+    /// Empty secrets are allowed, and credentials made with the
+    /// `force-create` modifier start out with one.
+    fn has_empty_secret(&self) -> Result<bool> {
+        self.with_unique_cred(|cred| cred.secret.is_empty())
+    }
+
+    /// See the API docs.
+    ///
+    /// Every credential has a `uuid` attribute. Others may have `comment`,
+    /// `creation-date`, `modified-date`, `idempotency-key`, and any
+    /// attributes set by clients.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.with_unique_pair(|uuid, cred| get_attrs(uuid, cred))
+    }
+
+    /// See the API docs.
+    ///
+    /// Both are read under a single lock on the credential.
+    fn get_secret_and_attributes(&self) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        self.with_unique_pair(|uuid, cred| (cred.secret.clone(), get_attrs(uuid, cred)))
+    }
+
+    /// See the API docs.
+    ///
+    /// The keys are computed without copying any attribute values.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        self.with_unique_cred(|cred| get_attr_keys(cred))
+    }
+
+    /// See the API docs.
+    ///
+    /// Only credentials made with the `force-create` modifier
+    /// have a creation date; for others this returns `None`.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        parse_date(self.get_creation_date()?)
+    }
+
+    /// See the API docs.
+    ///
+    /// The modification date is recorded whenever the secret or the
+    /// attributes are set, so only credentials made with the
+    /// `force-create` modifier and never changed since lack one.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        parse_date(self.get_modified_date()?)
+    }
+
+    /// See the API docs.
+    ///
+    /// Any attribute but `uuid`, `creation-date`, `modified-date`, and
+    /// `idempotency-key` can be updated, but only on credentials that
+    /// aren't immutable.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.update_attributes_unsaved(attrs)?;
+        self.store.autosave()
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        self.delete_credential_unsaved()?;
+        self.store.autosave()
+    }
+
+    /// See the API docs.
+    ///
+    /// The credential keeps its UUID, so a wrapper for it can be found
+    /// by searching the new service and user for that UUID. Since this
+    /// store only holds several credentials for a service and user when
+    /// they are created ambiguous, a credential can't be moved to a
+    /// service and user that already have one.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        self.rename_unsaved(new_service, new_user)?;
+        self.store.autosave()
+    }
+
     /// See the API docs.
     ///
     /// This always returns a new wrapper, even if this is already a wrapper,
@@ -198,16 +594,100 @@ impl CredentialApi for CredKey {
                 store: self.store.clone(),
                 id: self.id.clone(),
                 uuid: Some(uuid),
+                immutable: self.immutable,
+                idempotency_key: self.idempotency_key.clone(),
+            }))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// See the API docs.
+    ///
+    /// The primary credential is the first one created for this
+    /// entry's service and user, whether this entry is a specifier or a
+    /// wrapper. Like [get_credential](CredentialApi::get_credential),
+    /// this always returns a new wrapper.
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let _guard = self.store.read_ids();
+        let uuid = match self.store.creds.get(&self.id) {
+            None => None,
+            Some(creds) => primary_uuid(creds.value()),
+        };
+        match uuid {
+            None => Err(Error::NoEntry),
+            Some(uuid) => Ok(Some(Arc::new(CredKey {
+                store: self.store.clone(),
+                id: self.id.clone(),
+                uuid: Some(uuid),
+                immutable: self.immutable,
+                idempotency_key: self.idempotency_key.clone(),
             }))),
+        }
+    }
+
+    /// See the API docs.
+    ///
+    /// A specifier just counts the credentials for its service and user.
+    fn exists(&self) -> Result<bool> {
+        {
+            let _guard = self.store.read_ids();
+            match self.store.creds.get(&self.id) {
+                None => return Ok(false),
+                Some(creds) if self.uuid.is_none() && creds.len() < 2 => {
+                    return Ok(creds.len() == 1);
+                }
+                Some(_) => {}
+            }
+        }
+        // a wrapper, or an ambiguous specifier
+        match self.with_unique_pair(|_, _| ()) {
+            Ok(()) => Ok(true),
+            Err(Error::NoEntry) => Ok(false),
             Err(e) => Err(e),
         }
     }
 
+    /// See the API docs.
+    ///
+    /// Keys are the same if they are in the same store and have the same
+    /// service, user, and UUID. So two specifiers for a service and user are
+    /// the same, but a specifier and a wrapper for its credential are not.
+    fn is_same_credential(&self, other: &Credential) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<CredKey>()
+            .is_some_and(|other| {
+                Arc::ptr_eq(&self.store, &other.store)
+                    && self.id == other.id
+                    && self.uuid == other.uuid
+            })
+    }
+
+    /// See the API docs.
+    fn get_store(&self) -> Option<Arc<CredentialStore>> {
+        Some(self.store.clone())
+    }
+
     /// See the API docs.
     fn get_specifiers(&self) -> Option<(String, String)> {
         Some((self.id.service.clone(), self.id.user.clone()))
     }
 
+    /// Keys without a UUID are specifiers.
+    fn is_specifier(&self) -> bool {
+        self.uuid.is_none()
+    }
+
+    /// Keys with a UUID are wrappers.
+    fn is_wrapper(&self) -> bool {
+        self.uuid.is_some()
+    }
+
+    /// See the API docs.
+    fn get_wrapped_uuid(&self) -> Option<String> {
+        self.uuid.clone()
+    }
+
     /// See the API docs.
     fn as_any(&self) -> &dyn Any {
         self
@@ -219,32 +699,117 @@ impl CredentialApi for CredKey {
     }
 }
 
+/// find a credential in a group by comparing its UUID against every UUID in the group
+///
+/// This is a helper function used by constant-time probes. It does the same
+/// work whether or not (and wherever) the UUID is found in the group.
+pub fn probe_group(group: &DashMap<String, CredValue>, uuid: &str) -> Option<String> {
+    let mut found = None;
+    for cred in group.iter() {
+        if constant_time_eq(cred.key().as_bytes(), uuid.as_bytes()) {
+            found = Some(cred.key().clone());
+        }
+    }
+    found
+}
+
+/// lock a credential in a group so it can be read or updated
+///
+/// When the `contention-stats` feature is enabled, this records in the
+/// store's statistics whether the lock was contended and, if it was,
+/// how long it took to acquire.
+fn lock_cred<'a>(
+    store: &Store,
+    group: &'a DashMap<String, CredValue>,
+    uuid: &str,
+) -> Option<RefMut<'a, String, CredValue>> {
+    #[cfg(feature = "contention-stats")]
+    {
+        match group.try_get_mut(uuid) {
+            TryResult::Present(cred) => {
+                store.record_lock_wait(None);
+                Some(cred)
+            }
+            TryResult::Absent => None,
+            TryResult::Locked => {
+                let start = std::time::Instant::now();
+                let cred = group.get_mut(uuid);
+                store.record_lock_wait(Some(start.elapsed()));
+                cred
+            }
+        }
+    }
+    #[cfg(not(feature = "contention-stats"))]
+    {
+        let _ = store;
+        group.get_mut(uuid)
+    }
+}
+
+/// parse a date recorded on a credential
+fn parse_date(date: Option<String>) -> Result<Option<SystemTime>> {
+    match date {
+        Some(date) => match chrono::DateTime::parse_from_rfc2822(&date) {
+            Ok(date) => Ok(Some(date.into())),
+            Err(e) => Err(Error::BadDataFormat(date.into_bytes(), Box::from(e))),
+        },
+        None => Ok(None),
+    }
+}
+
 /// get the attributes on a credential
 ///
 /// This is a helper function used by get_attributes
 pub fn get_attrs(uuid: &str, cred: &CredValue) -> HashMap<String, String> {
     let mut attrs = HashMap::new();
     attrs.insert("uuid".to_string(), uuid.to_string());
-    if cred.creation_date.is_some() {
-        attrs.insert(
-            "creation-date".to_string(),
-            cred.creation_date.as_ref().unwrap().to_string(),
-        );
+    if let Some(creation_date) = &cred.creation_date {
+        attrs.insert("creation-date".to_string(), creation_date.to_string());
     }
-    if cred.comment.is_some() {
-        attrs.insert(
-            "comment".to_string(),
-            cred.comment.as_ref().unwrap().to_string(),
-        );
+    if let Some(modified_date) = &cred.modified_date {
+        attrs.insert("modified-date".to_string(), modified_date.to_string());
+    }
+    if let Some(comment) = &cred.comment {
+        attrs.insert("comment".to_string(), comment.to_string());
     };
+    if let Some(key) = &cred.idempotency_key {
+        attrs.insert("idempotency-key".to_string(), key.to_string());
+    }
+    for (key, value) in cred.attributes.iter() {
+        attrs.insert(key.clone(), value.clone());
+    }
     attrs
 }
 
+/// get the names of the attributes on a credential
+///
+/// This is a helper function used by attribute_keys
+pub fn get_attr_keys(cred: &CredValue) -> Vec<String> {
+    let mut keys = vec!["uuid".to_string()];
+    if cred.creation_date.is_some() {
+        keys.push("creation-date".to_string());
+    }
+    if cred.modified_date.is_some() {
+        keys.push("modified-date".to_string());
+    }
+    if cred.comment.is_some() {
+        keys.push("comment".to_string());
+    }
+    if cred.idempotency_key.is_some() {
+        keys.push("idempotency-key".to_string());
+    }
+    keys.extend(cred.attributes.keys().cloned());
+    keys
+}
+
 /// update the attributes on a credential
 ///
 /// This is a helper function used by update_attributes
 pub fn update_attrs(cred: &mut CredValue, attrs: &HashMap<&str, &str>) {
-    if let Some(comment) = attrs.get("comment") {
-        cred.comment = Some(comment.to_string());
+    for (key, value) in attrs {
+        match *key {
+            "comment" => cred.comment = Some(value.to_string()),
+            _ => _ = cred.attributes.insert(key.to_string(), value.to_string()),
+        }
     }
 }
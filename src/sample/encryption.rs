@@ -0,0 +1,128 @@
+/*!
+
+# Backing file encryption
+
+This module provides the key used to encrypt a sample store's backing file.
+It's only built if the `sample-encryption` feature is specified.
+
+The key is derived from a passphrase with PBKDF2-HMAC-SHA256 and a
+random salt, and each save encrypts the store's serialized credentials
+with ChaCha20-Poly1305 under a fresh random nonce. An encrypted backing
+file holds, in order, the bytes of [MAGIC], the salt, the nonce, and the
+ciphertext, so the same passphrase can be used to open it again.
+
+Like the rest of the sample store, this is meant for testing and as an
+example. It has not been reviewed for security!
+ */
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::Sha256;
+
+use crate::{Error, Result};
+
+/// The bytes an encrypted backing file starts with.
+pub const MAGIC: &[u8] = b"keyring-sample-encrypted-v1\n";
+
+/// The number of PBKDF2 rounds used to derive a key.
+pub const PBKDF2_ROUNDS: u32 = 100_000;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The key that encrypts a backing file, with the salt it was derived with.
+#[derive(Clone)]
+pub struct BackingKey {
+    salt: [u8; SALT_LEN],
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for BackingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackingKey")
+            .field("key", &"[REDACTED]")
+            .finish()
+    }
+}
+
+impl BackingKey {
+    /// Derive a key for a new backing file from a passphrase, with a random salt.
+    pub fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, PBKDF2_ROUNDS, &mut key);
+        BackingKey { salt, key }
+    }
+
+    /// Encrypt the content of a backing file.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| Error::PlatformFailure(Box::from(e.to_string())))?;
+        let mut result = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        result.extend_from_slice(MAGIC);
+        result.extend_from_slice(&self.salt);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    /// Decrypt the content of a backing file with a passphrase.
+    ///
+    /// Returns the plaintext and the key, so the file can be saved
+    /// again with the same key.
+    ///
+    /// Returns a [BadDataFormat](Error::BadDataFormat) error holding `data`
+    /// if it isn't an encrypted backing file or the passphrase is wrong.
+    pub fn open(passphrase: &str, data: &[u8]) -> Result<(Self, Vec<u8>)> {
+        let bad = |reason: &str| Error::BadDataFormat(data.to_vec(), Box::from(reason.to_string()));
+        let Some(rest) = data.strip_prefix(MAGIC) else {
+            return Err(bad("not an encrypted backing file"));
+        };
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(bad("the encrypted backing file is truncated"));
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let key = Self::derive(passphrase, salt.try_into().expect("salt length checked"));
+        let plaintext = key.decrypt(rest, &bad)?;
+        Ok((key, plaintext))
+    }
+
+    /// Decrypt the content of a backing file with this key.
+    ///
+    /// Returns a [BadDataFormat](Error::BadDataFormat) error holding `data`
+    /// if it isn't an encrypted backing file, or if it wasn't encrypted with
+    /// this key. (A file encrypted with the same passphrase but a different
+    /// salt, such as one written by a store that created it afresh, has to
+    /// be opened again with the passphrase.)
+    pub fn reopen(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let bad = |reason: &str| Error::BadDataFormat(data.to_vec(), Box::from(reason.to_string()));
+        let Some(rest) = data.strip_prefix(MAGIC) else {
+            return Err(bad("not an encrypted backing file"));
+        };
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(bad("the encrypted backing file is truncated"));
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        if salt != self.salt {
+            return Err(bad("the file was encrypted with a different key"));
+        }
+        self.decrypt(rest, &bad)
+    }
+
+    /// Decrypt a nonce followed by ciphertext.
+    fn decrypt(&self, data: &[u8], bad: &dyn Fn(&str) -> Error) -> Result<Vec<u8>> {
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| bad("the passphrase is wrong or the file is corrupted"))
+    }
+}
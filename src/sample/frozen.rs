@@ -0,0 +1,286 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::credential::CredKey;
+use super::store::{CredMap, Store};
+use crate::{
+    Credential, Entry,
+    Error::{self, Invalid, NotSupportedByStore},
+    Result,
+    api::{Capabilities, CredentialApi, CredentialPersistence, CredentialStoreApi},
+};
+
+const VENDOR: &str = "Frozen sample store, https://crates.io/crates/keyring-core";
+
+/// A read-only credential store.
+///
+/// The credentials are loaded once, from a document in the
+/// format of a sample store's backing file, and never change.
+/// All reads and searches work as they do in the sample store,
+/// but every mutation fails with a
+/// [NotSupportedByStore](Error::NotSupportedByStore) error.
+pub struct FrozenStore {
+    pub id: String,
+    pub inner: Arc<Store>,
+}
+
+impl std::fmt::Debug for FrozenStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrozenStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("cred-count", &self.inner.creds.len())
+            .finish()
+    }
+}
+
+impl FrozenStore {
+    /// Create a frozen store from the content of a sample store's backing file.
+    ///
+    /// Returns an [Invalid](Error::Invalid) error if the document can't be parsed.
+    pub fn from_document(doc: &str) -> Result<Arc<Self>> {
+        let creds: CredMap =
+            ron::de::from_str(doc).map_err(|e| Invalid("document".to_string(), e.to_string()))?;
+        Ok(Arc::new(FrozenStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            inner: Store::new_internal(creds, None),
+        }))
+    }
+}
+
+impl CredentialStoreApi for FrozenStore {
+    /// See the API docs.
+    fn vendor(&self) -> String {
+        String::from(VENDOR)
+    }
+
+    /// See the API docs.
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// See the API docs.
+    ///
+    /// No modifiers are allowed, because they could only be used
+    /// to create credentials.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        if mods.is_some_and(|m| !m.is_empty()) {
+            return Err(NotSupportedByStore(self.vendor()));
+        }
+        Ok(freeze_entry(self.inner.build(service, user, None)?))
+    }
+
+    /// See the API docs.
+    ///
+    /// Specs are interpreted exactly as they are by the sample store.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        Ok(self
+            .inner
+            .search(spec)?
+            .into_iter()
+            .map(freeze_entry)
+            .collect())
+    }
+
+    /// See the API docs.
+    fn all(&self) -> Result<Vec<Entry>> {
+        Ok(self.inner.all()?.into_iter().map(freeze_entry).collect())
+    }
+
+    /// See the API docs.
+    fn count(&self) -> Result<usize> {
+        Ok(self.inner.len())
+    }
+
+    /// See the API docs.
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// See the API docs.
+    ///
+    /// Frozen credentials are never saved, so they last as long as the process.
+    fn persistence(&self) -> CredentialPersistence {
+        CredentialPersistence::ProcessOnly
+    }
+
+    /// See the API docs.
+    ///
+    /// Frozen credentials can't be changed, so their attributes can't be updated.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_search: true,
+            supports_ambiguity: true,
+            ..Capabilities::default()
+        }
+    }
+
+    /// See the API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A read-only credential in a [FrozenStore].
+///
+/// This wraps a key into the frozen store's (private) sample store.
+#[derive(Debug, Clone)]
+pub struct FrozenCred {
+    pub inner: CredKey,
+}
+
+impl CredentialApi for FrozenCred {
+    /// Frozen credentials can't be changed.
+    fn set_secret(&self, _: &[u8]) -> Result<()> {
+        Err(NotSupportedByStore(String::from(VENDOR)))
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        freeze(self.inner.get_secret())
+    }
+
+    /// See the API docs.
+    fn with_secret(&self, f: &mut dyn FnMut(&[u8])) -> Result<()> {
+        freeze(CredentialApi::with_secret(&self.inner, f))
+    }
+
+    /// See the API docs.
+    fn has_empty_secret(&self) -> Result<bool> {
+        freeze(self.inner.has_empty_secret())
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        freeze(self.inner.get_attributes())
+    }
+
+    /// See the API docs.
+    fn get_secret_and_attributes(&self) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        freeze(self.inner.get_secret_and_attributes())
+    }
+
+    /// See the API docs.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        freeze(self.inner.attribute_keys())
+    }
+
+    /// See the API docs.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        freeze(self.inner.get_created())
+    }
+
+    /// See the API docs.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        freeze(self.inner.get_modified())
+    }
+
+    /// Frozen credentials can't be changed.
+    fn update_attributes(&self, _: &HashMap<&str, &str>) -> Result<()> {
+        Err(NotSupportedByStore(String::from(VENDOR)))
+    }
+
+    /// Frozen credentials can't be changed.
+    fn compare_and_set(&self, _: Option<&[u8]>, _: Option<&[u8]>) -> Result<bool> {
+        Err(NotSupportedByStore(String::from(VENDOR)))
+    }
+
+    /// Frozen credentials can't be deleted.
+    fn delete_credential(&self) -> Result<()> {
+        Err(NotSupportedByStore(String::from(VENDOR)))
+    }
+
+    /// See the API docs.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let uuid = freeze(self.inner.get_uuid())?;
+        Ok(Some(Arc::new(FrozenCred {
+            inner: CredKey {
+                store: self.inner.store.clone(),
+                id: self.inner.id.clone(),
+                uuid: Some(uuid),
+                immutable: false,
+                idempotency_key: None,
+            },
+        })))
+    }
+
+    /// See the API docs.
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let primary = self.inner.get_primary_credential()?;
+        Ok(primary.and_then(|cred| {
+            cred.as_any()
+                .downcast_ref::<CredKey>()
+                .map(|key| Arc::new(FrozenCred { inner: key.clone() }) as Arc<Credential>)
+        }))
+    }
+
+    /// See the API docs.
+    fn exists(&self) -> Result<bool> {
+        freeze(self.inner.exists())
+    }
+
+    /// See the API docs.
+    fn is_same_credential(&self, other: &Credential) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<FrozenCred>()
+            .is_some_and(|other| self.inner.is_same_credential(&other.inner))
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.inner.get_specifiers()
+    }
+
+    /// See the API docs.
+    fn is_specifier(&self) -> bool {
+        self.inner.is_specifier()
+    }
+
+    /// See the API docs.
+    fn is_wrapper(&self) -> bool {
+        self.inner.is_wrapper()
+    }
+
+    /// See the API docs.
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// See the API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Wrap a sample-store entry so it can't be used to make changes.
+fn freeze_entry(entry: Entry) -> Entry {
+    match entry.as_any().downcast_ref::<CredKey>() {
+        Some(key) => Entry::new_with_credential(Arc::new(FrozenCred { inner: key.clone() })),
+        None => entry,
+    }
+}
+
+/// Make sure the entries in an ambiguity error can't be used to make changes.
+fn freeze<T>(result: Result<T>) -> Result<T> {
+    match result {
+        Err(Error::Ambiguous(entries)) => Err(Error::Ambiguous(
+            entries.into_iter().map(freeze_entry).collect(),
+        )),
+        other => other,
+    }
+}
@@ -32,7 +32,81 @@ The in-memory credentials are only saved to the backing file when
 explicitly requested or when a store is destroyed (that is, the last reference
 to it is released).
 The credential state saved in a backing file (if it exists from a prior run)
-is only loaded when a store using that file is first created.
+is loaded when a store using that file is first created, and again
+whenever `Store::reload` is called, which discards any changes that
+haven't been saved. Entries built before a reload stay usable: see
+`Store::reload` for what they refer to afterwards.
+If you want the backing file kept up to date, specify the `autosave`
+modifier as `true` (or use `Store::new_with_backing_autosave`): the store
+is then saved after every change to a credential. Each save rewrites the
+entire file, so this slows every write in proportion to the size of the store.
+
+Stores take an advisory lock (on a `.lock` file next to the backing file)
+while they read or write it: shared for reading, exclusive for writing.
+So several processes can use the same backing file without reading
+a half-written one. If the lock can't be had within
+`store::BACKING_LOCK_TIMEOUT`, loading or saving fails with a
+[NoStorageAccess](crate::Error::NoStorageAccess) error. While it holds
+the exclusive lock, a saving store first reads the file and adds any
+credentials other stores have saved to it since this store last read
+or wrote it, so stores don't lose each other's new credentials.
+(Changes to a credential that both stores have are not merged:
+the last store to save wins.)
+
+If the directory that should hold the backing file doesn't exist,
+creating the store fails with an [Invalid](crate::Error::Invalid) error
+that names the directory, unless you also specify the `create-dirs`
+modifier as `true`, in which case the directory is created.
+
+# Configuration
+
+In addition to the persistence keys described above, the following
+keys can be given to [Store::new_with_configuration]:
+
+- `constant-time-probe`: When `true`, a wrapper looks up its credential
+  by comparing its UUID against that of every credential with the same
+  service name and username, in constant time, rather than by a hash
+  lookup that stops as soon as it knows the answer. This is a best-effort
+  mitigation against timing attacks that try to learn which credentials
+  exist: it doesn't hide whether there are _any_ credentials for a given
+  service name and username. It defaults to `false`.
+- `backing-format`: The format of the backing file. With `ron` (the
+  default), the file holds the store's credential map in RON. With `json`,
+  it holds a JSON array with one object per credential, in the form written
+  by `Store::export_to`, which is easier to inspect or edit with other
+  tools. If the `persist` key is used, the file is called
+  `keyring-sample-store.json` instead. See also `Store::new_with_backing_format`.
+  The `json` format is only available if the `sample-json` feature is
+  specified; without it, `json` is an [Invalid](crate::Error::Invalid) value.
+- `checksum`: When `true`, the store writes a SHA-256 checksum of its
+  credentials (see [Store::checksum]) as a comment on the first line of
+  its backing file. It defaults to `false`. Whatever this setting, a store
+  that loads a backing file with a checksum verifies it, and fails with a
+  [BadDataFormat](crate::Error::BadDataFormat) error if the file has
+  been changed since the checksum was written. A store with this setting
+  also requires its backing file to have a checksum, so it fails with a
+  [BadDataFormat](crate::Error::BadDataFormat) error if the checksum line
+  has been removed.
+- `empty-spec-policy`: What a search with an empty specification does.
+  With `all`, it returns every credential in the store; with `none`, it
+  returns no credentials; and with `error`, it fails with an
+  [Invalid](crate::Error::Invalid) error. It defaults to `all`.
+- `case-insensitive-specifiers`: When `true`, service names and usernames
+  that differ only in case refer to the same credentials, as they do in
+  some platform stores. The store lowercases them when entries are built
+  (so the specifiers of its entries are lowercase), and searches match
+  them without regard to case. It defaults to `false`.
+
+# Contention statistics
+
+If the `contention-stats` feature is enabled, each store keeps
+statistics on how often, and for how long, operations wait for
+another thread to release a credential. See `Store::contention_stats`.
+Only waits for the credential itself are measured: an operation on a
+specifier that has to wait while the store counts the credentials
+for its service name and username is not counted as contended.
+The statistics cost a little time on every operation, so the feature
+is off by default.
 
 # Ambiguity
 
@@ -54,10 +128,50 @@ the created credential to have two additional attributes:
 * *creation-date*, an HTTP-style date showing when the
   credential was created. This cannot be updated, nor
   can it be added to credentials that don't have it.
+  (The date is taken from the store's clock, which
+  tests can replace using [Store::with_clock].)
 * *comment*, the string value of the `target` modifier.
   This can be updated, and it can be added to credentials
   that don't have it.
 
+Whenever the secret or the attributes of a credential are
+set, it gets a *modified-date* attribute, an HTTP-style date
+showing when that happened. Like the creation date, it is taken
+from the store's clock, and it can't be updated by clients.
+
+Among the credentials for a service name and username, the one
+created first is the _primary_ credential. The store records the
+order in which credentials are created (and saves it in the backing
+file), so [Entry::primary_credential](crate::Entry::primary_credential)
+returns a wrapper for the same credential no matter how many others
+are created later. If the primary credential is deleted, the next
+oldest becomes primary.
+
+# Immutable credentials
+
+If you specify the `immutable` modifier as `true` when creating an entry,
+then setting a secret through that entry makes the credential immutable.
+After that, attempts to set its secret or update its attributes fail with
+an [Immutable](crate::Error::Immutable) error, whichever entry is used.
+Immutable credentials can still be deleted. The immutability of a credential
+is saved along with it in the backing file.
+
+# Idempotency keys
+
+If you specify the `idempotency-key` modifier when creating an entry,
+its value is recorded (as the read-only `idempotency-key` attribute)
+on every credential created or written through the entry. Then:
+
+* If the `force-create` modifier is also given, no credential is created
+  if there's already one for the service name and username that has
+  the same idempotency key.
+* Setting a secret through the entry does nothing (and succeeds)
+  if there's already a credential for the service name and username
+  that has the same idempotency key.
+
+So a client that isn't sure whether a write went through can safely
+retry it with the same idempotency key.
+
 # Attributes
 
 Credentials in this store, in addition to the attributes
@@ -65,24 +179,83 @@ described in the section on Ambiguity above, have
 a single read-only attribute `uuid` which is the
 unique ID of the credential in the store.
 
+Clients can also give credentials attributes of their own, such
+as `label` or `url`, by updating them. Any attribute but `uuid`,
+`creation-date`, `modified-date`, and `idempotency-key` can be updated, and
+client attributes are saved in the backing file along with the
+rest of the credential.
+
+The attributes that can be updated, and their values, can be
+constrained by giving the store an [AttributeSchema] with
+[Store::set_attribute_schema].
+
 # Search
 
 This store implements credential search. Specs can specify
 desired regular expressions for the `service` and `user` a
-credential is attached to, and for the `comment` and `uuid` attributes
-of the credential itself. (All other key/value pairs in the spec
-are ignored.) Credentials are returned only if _all_ the
-specified regular expressions match against its values.
+credential is attached to, and for any of the attributes
+of the credential itself, such as `comment` or `uuid`.
+Credentials are returned only if they have all the specified
+attributes and _all_ the specified regular expressions match
+against its values.
+
+By default, a regular expression matches if it matches any
+substring of a value, so `foo` matches both `foo` and `foobar`.
+If the spec also has a `match-mode` key with the value `exact`,
+each regular expression must match its entire value instead
+(as if it were written `^(?:foo)$`). The value `substring`
+asks for the default behavior.
+
+Matching is case-sensitive unless the spec has an `ignore-case`
+key with the value `true`, in which case all of its regular
+expressions ignore case. (A store that has case-insensitive
+specifiers always matches `service` and `user` without regard
+to case.)
 
 Note: Search is implemented by iterating over every credential
 in the store. This is an in-memory store, so it happens
 pretty quickly.
 
+# Frozen stores
+
+For reproducible test fixtures, a [FrozenStore] can be loaded
+from a document in the same format as a store's backing file.
+Its credentials can be read and searched as usual, but
+every attempt to change them fails with a
+[NotSupportedByStore](crate::Error::NotSupportedByStore) error.
+
+# Encrypted backing files
+
+If the `sample-encryption` feature is enabled,
+`Store::new_with_encrypted_backing` creates a store whose backing
+file is encrypted with a key derived from a passphrase, rather than
+written in plain text. See the `encryption` module for details.
+
+# SQLite stores
+
+If the `sqlite` feature is enabled, a `SqliteStore` keeps its
+credentials in a SQLite database instead of in memory, writing
+each change as it's made. See the `sqlite` module for details.
+
  */
 
 pub mod credential;
 pub use credential::CredKey;
 
+#[cfg(feature = "sample-encryption")]
+pub mod encryption;
+
+pub mod frozen;
+pub use frozen::FrozenStore;
+
+pub mod schema;
+pub use schema::AttributeSchema;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
 pub mod store;
 pub use store::Store;
 
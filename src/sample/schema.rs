@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::{Error::Invalid, Result};
+
+/// Attributes that the store maintains itself, which clients can't update
+/// and a schema can't mention.
+pub const RESERVED_ATTRIBUTES: [&str; 4] =
+    ["uuid", "creation-date", "modified-date", "idempotency-key"];
+
+/// A check on the value of an attribute.
+#[derive(Debug, Clone)]
+pub enum AttributeValidator {
+    /// Any value is allowed.
+    Any,
+    /// The value must match the regular expression.
+    Regex(regex::Regex),
+    /// The value must be one of these.
+    OneOf(Vec<String>),
+}
+
+impl AttributeValidator {
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            AttributeValidator::Any => true,
+            AttributeValidator::Regex(regex) => regex.is_match(value),
+            AttributeValidator::OneOf(values) => values.iter().any(|v| v == value),
+        }
+    }
+}
+
+/// The rule for one attribute in an [AttributeSchema].
+#[derive(Debug, Clone)]
+pub struct AttributeRule {
+    /// Whether every credential must have a value for the attribute
+    /// once its attributes are updated.
+    pub required: bool,
+    pub validator: AttributeValidator,
+}
+
+/// The attributes that can be updated on a store's credentials.
+///
+/// Only the attributes named in the schema can be updated,
+/// and only to values their validators accept.
+/// See [Store::set_attribute_schema](super::Store::set_attribute_schema).
+#[derive(Debug, Clone, Default)]
+pub struct AttributeSchema {
+    pub attributes: HashMap<String, AttributeRule>,
+}
+
+impl AttributeSchema {
+    /// Check that the schema only mentions attributes that can be updated.
+    pub fn check(&self) -> Result<()> {
+        for key in self.attributes.keys() {
+            if RESERVED_ATTRIBUTES.contains(&key.as_str()) {
+                return Err(Invalid(
+                    key.clone(),
+                    "is reserved and can't be in an attribute schema".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check an update of a credential's `current` attributes with `attrs`.
+    pub fn validate(
+        &self,
+        current: &HashMap<String, String>,
+        attrs: &HashMap<&str, &str>,
+    ) -> Result<()> {
+        for (key, value) in attrs {
+            match self.attributes.get(*key) {
+                None => {
+                    return Err(Invalid(
+                        key.to_string(),
+                        "is not allowed by the attribute schema".to_string(),
+                    ));
+                }
+                Some(rule) if !rule.validator.accepts(value) => {
+                    return Err(Invalid(
+                        key.to_string(),
+                        format!("value '{value}' is not allowed by the attribute schema"),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, rule) in self.attributes.iter() {
+            if rule.required && !attrs.contains_key(key.as_str()) && !current.contains_key(key) {
+                return Err(Invalid(
+                    key.clone(),
+                    "is required by the attribute schema".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
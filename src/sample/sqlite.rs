@@ -0,0 +1,650 @@
+/*!
+
+# SQLite sample store
+
+This store keeps its credentials in a SQLite database, so, unlike the
+sample [Store](super::Store), every change is written as soon as it's made
+and lookups use the database's index rather than a scan of the whole store.
+It's only built if the `sqlite` feature is specified.
+
+Credentials are kept in a single `credentials` table, keyed by service name,
+username, and UUID, with columns for the secret, the `comment` and
+`creation-date` attributes, and any other attributes (as a JSON object).
+Ambiguity and the `force-create` modifier work as they do in the sample
+store. Search is simpler: the `service`, `user`, `comment`, and `uuid`
+keys of a spec are regular expressions that must all match, any other keys
+(including other attributes) are ignored, and there are no `match-mode`
+or `ignore-case` spec keys and no `empty-spec-policy`.
+
+Like the sample store, this store is explicitly *not* for use in production apps!
+ */
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use uuid::Uuid;
+
+use crate::{
+    Credential, Entry,
+    Error::{self, Invalid, PlatformFailure},
+    Result,
+    api::{Capabilities, CredentialApi, CredentialPersistence, CredentialStoreApi},
+    attributes::parse_attributes,
+};
+
+const VENDOR: &str = "SQLite sample store, https://crates.io/crates/keyring-core";
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS credentials (
+    service TEXT NOT NULL,
+    user TEXT NOT NULL,
+    uuid TEXT NOT NULL,
+    secret BLOB NOT NULL,
+    comment TEXT,
+    creation_date TEXT,
+    attributes TEXT NOT NULL DEFAULT '{}',
+    PRIMARY KEY (service, user, uuid)
+)";
+
+/// The columns that search specs can match, and the spec keys that name them.
+const SEARCH_KEYS: [&str; 4] = ["service", "user", "comment", "uuid"];
+
+/// A credential store backed by a SQLite database.
+pub struct SqliteStore {
+    pub id: String,
+    /// The database file, or `None` for an in-memory database
+    pub path: Option<String>,
+    pub conn: Arc<Mutex<Connection>>,
+}
+
+impl std::fmt::Debug for SqliteStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl SqliteStore {
+    /// Open (or create) a store in the database file at `path`.
+    ///
+    /// Returns a [PlatformFailure](Error::PlatformFailure) error
+    /// if the database can't be opened or initialized.
+    pub fn new(path: &str) -> Result<Arc<Self>> {
+        Self::new_internal(Connection::open(path), Some(path.to_string()))
+    }
+
+    /// Create a store in a new in-memory database.
+    ///
+    /// The credentials vanish when the store is dropped,
+    /// which makes this useful for tests.
+    pub fn new_in_memory() -> Result<Arc<Self>> {
+        Self::new_internal(Connection::open_in_memory(), None)
+    }
+
+    fn new_internal(conn: rusqlite::Result<Connection>, path: Option<String>) -> Result<Arc<Self>> {
+        let conn = conn.map_err(platform)?;
+        conn.execute(SCHEMA, []).map_err(platform)?;
+        add_regexp_function(&conn).map_err(platform)?;
+        Ok(Arc::new(SqliteStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            path,
+            conn: Arc::new(Mutex::new(conn)),
+        }))
+    }
+}
+
+impl CredentialStoreApi for SqliteStore {
+    /// See the API docs.
+    fn vendor(&self) -> String {
+        String::from(VENDOR)
+    }
+
+    /// See the API docs.
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// See the API docs.
+    ///
+    /// The only allowed modifier is `force-create`, which works
+    /// as it does in the sample store.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        let mods = parse_attributes(&["force-create"], mods)?;
+        let cred = SqliteCred {
+            conn: self.conn.clone(),
+            service: service.to_string(),
+            user: user.to_string(),
+            uuid: None,
+        };
+        if let Some(comment) = mods.get("force-create") {
+            cred.lock()
+                .execute(
+                    "INSERT INTO credentials (service, user, uuid, secret, comment, creation_date)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        service,
+                        user,
+                        Uuid::new_v4().to_string(),
+                        Vec::<u8>::new(),
+                        comment,
+                        chrono::Local::now().to_rfc2822(),
+                    ],
+                )
+                .map_err(platform)?;
+        }
+        Ok(Entry::new_with_credential(Arc::new(cred)))
+    }
+
+    /// See the API docs.
+    ///
+    /// The values of the `service`, `user`, `comment`, and `uuid` keys
+    /// are regular expressions that must all match. Unlike the sample store,
+    /// other keys are ignored (so `{"label": "work"}` matches every
+    /// credential), and the `match-mode` and `ignore-case` keys have no
+    /// effect. An empty spec matches every credential. The matching is
+    /// done by the database.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        let mut clauses = vec![String::from("1")];
+        let mut patterns = Vec::new();
+        for key in SEARCH_KEYS {
+            if let Some(pattern) = spec.get(key) {
+                Regex::new(pattern).map_err(|e| Invalid(format!("{key} regex"), e.to_string()))?;
+                patterns.push(pattern.to_string());
+                clauses.push(format!("{key} REGEXP ?{}", patterns.len()));
+            }
+        }
+        let query = format!(
+            "SELECT service, user, uuid FROM credentials WHERE {} ORDER BY rowid",
+            clauses.join(" AND ")
+        );
+        let conn = self.conn.lock().expect("Mutex bug at search!");
+        let mut statement = conn.prepare(&query).map_err(platform)?;
+        let rows = statement
+            .query_map(params_from_iter(patterns.iter()), |row| {
+                Ok(SqliteCred {
+                    conn: self.conn.clone(),
+                    service: row.get(0)?,
+                    user: row.get(1)?,
+                    uuid: Some(row.get(2)?),
+                })
+            })
+            .map_err(platform)?;
+        rows.map(|cred| {
+            Ok(Entry::new_with_credential(Arc::new(
+                cred.map_err(platform)?,
+            )))
+        })
+        .collect()
+    }
+
+    /// See the API docs.
+    fn all(&self) -> Result<Vec<Entry>> {
+        self.search(&HashMap::new())
+    }
+
+    /// See the API docs.
+    fn count(&self) -> Result<usize> {
+        let conn = self.conn.lock().expect("Mutex bug at count!");
+        conn.query_row("SELECT COUNT(*) FROM credentials", [], |row| row.get(0))
+            .map_err(platform)
+    }
+
+    /// See the API docs.
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// See the API docs.
+    ///
+    /// Credentials are written to the database as soon as they change.
+    /// (An in-memory database doesn't outlive its store, but it reports
+    /// the same persistence, because it's meant for testing.)
+    fn persistence(&self) -> CredentialPersistence {
+        CredentialPersistence::UntilDelete
+    }
+
+    /// See the API docs.
+    ///
+    /// Like [persistence](CredentialStoreApi::persistence), this
+    /// reports an in-memory database as persisting to disk.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_search: true,
+            supports_attributes: true,
+            supports_ambiguity: true,
+            persists_to_disk: true,
+            supports_secret_bytes: true,
+        }
+    }
+
+    /// See the API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential in a [SqliteStore].
+///
+/// As in the sample store, a credential without a UUID is a specifier,
+/// and one with a UUID is a wrapper.
+#[derive(Clone)]
+pub struct SqliteCred {
+    pub conn: Arc<Mutex<Connection>>,
+    pub service: String,
+    pub user: String,
+    pub uuid: Option<String>,
+}
+
+impl std::fmt::Debug for SqliteCred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteCred")
+            .field("service", &self.service)
+            .field("user", &self.user)
+            .field("uuid", &self.uuid)
+            .finish()
+    }
+}
+
+impl SqliteCred {
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("Mutex bug at connection lock!")
+    }
+
+    fn wrapper(&self, uuid: String) -> SqliteCred {
+        SqliteCred {
+            uuid: Some(uuid),
+            ..self.clone()
+        }
+    }
+
+    /// Find the UUID of the sole credential for this cred.
+    ///
+    /// If there is no credential, this returns a NoEntry error.
+    /// If there are several, it returns an Ambiguous error.
+    fn unique_uuid(&self, conn: &Connection) -> Result<String> {
+        let mut statement = conn
+            .prepare(
+                "SELECT uuid FROM credentials
+                 WHERE service = ?1 AND user = ?2 AND (?3 IS NULL OR uuid = ?3)
+                 ORDER BY rowid",
+            )
+            .map_err(platform)?;
+        let mut uuids = statement
+            .query_map(params![self.service, self.user, self.uuid], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(platform)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(platform)?;
+        match uuids.len() {
+            0 => Err(Error::NoEntry),
+            1 => Ok(uuids.remove(0)),
+            _ => Err(Error::Ambiguous(
+                uuids
+                    .into_iter()
+                    .map(|uuid| Entry::new_with_credential(Arc::new(self.wrapper(uuid))))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl CredentialApi for SqliteCred {
+    /// See the API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        let conn = self.lock();
+        match self.unique_uuid(&conn) {
+            Ok(uuid) => conn.execute(
+                "UPDATE credentials SET secret = ?4 WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+                params![self.service, self.user, uuid, secret],
+            ),
+            // a specifier with no credential: create the cred
+            Err(Error::NoEntry) if self.uuid.is_none() => conn.execute(
+                "INSERT INTO credentials (service, user, uuid, secret) VALUES (?1, ?2, ?3, ?4)",
+                params![self.service, self.user, Uuid::new_v4().to_string(), secret],
+            ),
+            Err(e) => return Err(e),
+        }
+        .map_err(platform)?;
+        Ok(())
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        let conn = self.lock();
+        let uuid = self.unique_uuid(&conn)?;
+        conn.query_row(
+            "SELECT secret FROM credentials WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+            params![self.service, self.user, uuid],
+            |row| row.get(0),
+        )
+        .map_err(platform)
+    }
+
+    /// See the API docs.
+    ///
+    /// Every credential has a `uuid` attribute; those made with the
+    /// `force-create` modifier also have `comment` and `creation-date`.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        let conn = self.lock();
+        let uuid = self.unique_uuid(&conn)?;
+        let (comment, creation_date, extra) = conn
+            .query_row(
+                "SELECT comment, creation_date, attributes FROM credentials
+                 WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+                params![self.service, self.user, uuid],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .map_err(platform)?;
+        let mut attrs: HashMap<String, String> = serde_json::from_str(&extra)
+            .map_err(|e| Error::BadDataFormat(extra.into_bytes(), Box::from(e)))?;
+        attrs.insert("uuid".to_string(), uuid);
+        if let Some(comment) = comment {
+            attrs.insert("comment".to_string(), comment);
+        }
+        if let Some(creation_date) = creation_date {
+            attrs.insert("creation-date".to_string(), creation_date);
+        }
+        Ok(attrs)
+    }
+
+    /// See the API docs.
+    ///
+    /// Any attribute but `uuid` and `creation-date` can be updated.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        for key in ["uuid", "creation-date"] {
+            if attrs.contains_key(key) {
+                return Err(Invalid(key.to_string(), "cannot be updated".to_string()));
+            }
+        }
+        let conn = self.lock();
+        let uuid = self.unique_uuid(&conn)?;
+        let extra: Option<String> = conn
+            .query_row(
+                "SELECT attributes FROM credentials WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+                params![self.service, self.user, uuid],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(platform)?;
+        let Some(extra) = extra else {
+            return Err(Error::NoEntry);
+        };
+        let mut extra: HashMap<String, String> = serde_json::from_str(&extra)
+            .map_err(|e| Error::BadDataFormat(extra.into_bytes(), Box::from(e)))?;
+        let mut comment = None;
+        for (key, value) in attrs {
+            match *key {
+                "comment" => comment = Some(*value),
+                _ => _ = extra.insert(key.to_string(), value.to_string()),
+            }
+        }
+        let extra = serde_json::to_string(&extra).map_err(|e| PlatformFailure(Box::from(e)))?;
+        conn.execute(
+            "UPDATE credentials SET comment = COALESCE(?4, comment), attributes = ?5
+             WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+            params![self.service, self.user, uuid, comment, extra],
+        )
+        .map_err(platform)?;
+        Ok(())
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        let conn = self.lock();
+        let uuid = self.unique_uuid(&conn)?;
+        conn.execute(
+            "DELETE FROM credentials WHERE service = ?1 AND user = ?2 AND uuid = ?3",
+            params![self.service, self.user, uuid],
+        )
+        .map_err(platform)?;
+        Ok(())
+    }
+
+    /// See the API docs.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let uuid = self.unique_uuid(&self.lock())?;
+        match self.uuid {
+            Some(_) => Ok(None),
+            None => Ok(Some(Arc::new(self.wrapper(uuid)))),
+        }
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    /// Credentials without a UUID are specifiers.
+    fn is_specifier(&self) -> bool {
+        self.uuid.is_none()
+    }
+
+    /// Credentials with a UUID are wrappers.
+    fn is_wrapper(&self) -> bool {
+        self.uuid.is_some()
+    }
+
+    /// See the API docs.
+    fn get_wrapped_uuid(&self) -> Option<String> {
+        self.uuid.clone()
+    }
+
+    /// See the API docs.
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// See the API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+fn platform(err: rusqlite::Error) -> Error {
+    PlatformFailure(Box::from(err))
+}
+
+/// Give the connection a `regexp` function, so queries can use `REGEXP`.
+///
+/// Each pattern is compiled once per query. A `NULL` value never matches.
+fn add_regexp_function(conn: &Connection) -> rusqlite::Result<()> {
+    type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let regex: Arc<Regex> =
+                ctx.get_or_create_aux(0, |pattern| -> std::result::Result<_, BoxError> {
+                    Ok(Regex::new(pattern.as_str()?)?)
+                })?;
+            match ctx.get_raw(1) {
+                ValueRef::Text(text) => Ok(regex.is_match(&String::from_utf8_lossy(text))),
+                _ => Ok(false),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::SqliteStore;
+    use crate::{CredentialPersistence, CredentialStore, Entry, Error};
+
+    fn new_store() -> Arc<CredentialStore> {
+        SqliteStore::new_in_memory().unwrap()
+    }
+
+    fn test_round_trip(entry: &Entry, secret: &[u8]) {
+        entry.set_secret(secret).unwrap();
+        assert_eq!(entry.get_secret().unwrap(), secret);
+        entry.delete_credential().unwrap();
+        assert!(matches!(entry.get_secret(), Err(Error::NoEntry)));
+        assert!(matches!(entry.delete_credential(), Err(Error::NoEntry)));
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let store = new_store();
+        let entry = store.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        test_round_trip(&entry, b"");
+        test_round_trip(&entry, "password".as_bytes());
+        test_round_trip(&entry, "パスワード".as_bytes());
+        test_round_trip(&entry, &[0, 159, 146, 150, 255]);
+        let empty = store.build("", "", None).unwrap();
+        test_round_trip(&empty, b"empty");
+        entry.set_password("first").unwrap();
+        entry.set_password("second").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "second");
+        let other = store.build("service", "other", None).unwrap();
+        assert!(matches!(other.get_password(), Err(Error::NoEntry)));
+        assert!(matches!(
+            store.persistence(),
+            CredentialPersistence::UntilDelete
+        ));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let store = new_store();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        let attrs = entry.get_attributes().unwrap();
+        assert_eq!(attrs.len(), 1);
+        assert!(attrs.contains_key("uuid"));
+        entry
+            .update_attributes(&HashMap::from([("comment", "note"), ("label", "work")]))
+            .unwrap();
+        let attrs = entry.get_attributes().unwrap();
+        assert_eq!(attrs["comment"], "note");
+        assert_eq!(attrs["label"], "work");
+        assert!(matches!(
+            entry.update_attributes(&HashMap::from([("uuid", "new")])),
+            Err(Error::Invalid(key, _)) if key == "uuid"
+        ));
+        assert!(matches!(
+            store.build("service", "user", Some(&HashMap::from([("bad", "mod")]))),
+            Err(Error::Invalid(..))
+        ));
+    }
+
+    #[test]
+    fn test_ambiguity() {
+        let store = new_store();
+        let entry = store.build("service", "user", None).unwrap();
+        entry.set_password("first").unwrap();
+        let mods = HashMap::from([("force-create", "second")]);
+        store.build("service", "user", Some(&mods)).unwrap();
+        let wrappers = match entry.get_password() {
+            Err(Error::Ambiguous(wrappers)) => wrappers,
+            other => panic!("expected an ambiguous error, got {other:?}"),
+        };
+        assert_eq!(wrappers.len(), 2);
+        assert!(wrappers.iter().all(|w| w.is_wrapper()));
+        assert_eq!(wrappers[0].get_password().unwrap(), "first");
+        assert_eq!(wrappers[1].get_password().unwrap(), "");
+        let attrs = wrappers[1].get_attributes().unwrap();
+        assert_eq!(attrs["comment"], "second");
+        assert!(attrs.contains_key("creation-date"));
+        assert!(matches!(
+            entry.set_password("third"),
+            Err(Error::Ambiguous(_))
+        ));
+        wrappers[1].set_password("third").unwrap();
+        wrappers[0].delete_credential().unwrap();
+        assert!(matches!(wrappers[0].get_password(), Err(Error::NoEntry)));
+        assert!(matches!(
+            wrappers[0].set_password("gone"),
+            Err(Error::NoEntry)
+        ));
+        assert_eq!(entry.get_password().unwrap(), "third");
+        let wrapper = entry.get_credential().unwrap();
+        assert!(wrapper.is_wrapper());
+        assert_eq!(
+            wrapper.get_attributes().unwrap(),
+            wrappers[1].get_attributes().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let store = new_store();
+        for (service, user) in [("alpha", "alice"), ("alpha", "bob"), ("beta", "alice")] {
+            store
+                .build(service, user, None)
+                .unwrap()
+                .set_password(user)
+                .unwrap();
+        }
+        let mods = HashMap::from([("force-create", "a comment")]);
+        store.build("gamma", "carol", Some(&mods)).unwrap();
+        let search = |spec: &[(&str, &str)]| {
+            store
+                .search(&spec.iter().copied().collect::<HashMap<_, _>>())
+                .unwrap()
+        };
+        assert_eq!(search(&[]).len(), 4);
+        assert_eq!(store.count().unwrap(), 4);
+        assert_eq!(store.all().unwrap().len(), 4);
+        assert_eq!(search(&[("service", "^alpha$")]).len(), 2);
+        assert_eq!(search(&[("service", "a"), ("user", "^alice")]).len(), 2);
+        assert_eq!(search(&[("comment", "comment")]).len(), 1);
+        assert_eq!(search(&[("user", "nobody")]).len(), 0);
+        let found = search(&[("service", "beta")]);
+        assert_eq!(found[0].get_password().unwrap(), "alice");
+        let uuid = found[0].get_attributes().unwrap()["uuid"].clone();
+        assert_eq!(search(&[("uuid", &uuid)]).len(), 1);
+        assert!(matches!(
+            store.search(&HashMap::from([("service", "(")])),
+            Err(Error::Invalid(key, _)) if key == "service regex"
+        ));
+    }
+
+    #[test]
+    fn test_persistence() {
+        let path =
+            std::env::temp_dir().join(format!("keyring-sqlite-test-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        {
+            let store: Arc<CredentialStore> = SqliteStore::new(path).unwrap();
+            let entry = store.build("service", "user", None).unwrap();
+            entry.set_password("persisted").unwrap();
+        }
+        {
+            let store: Arc<CredentialStore> = SqliteStore::new(path).unwrap();
+            let entry = store.build("service", "user", None).unwrap();
+            assert_eq!(entry.get_password().unwrap(), "persisted");
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,20 +1,24 @@
 use std::any::Any;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock, Weak};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, Weak};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use base64::prelude::{BASE64_STANDARD, Engine};
 use dashmap::DashMap;
-use log::{debug, error};
+use log::{debug, error, warn};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use super::credential::{CredId, CredKey};
+use super::credential::{CredId, CredKey, get_attrs};
+use super::schema::AttributeSchema;
 use crate::{
-    Entry,
+    Entry, Error,
     Error::{Invalid, PlatformFailure},
     Result,
-    api::{CredentialPersistence, CredentialStoreApi},
-    attributes::parse_attributes,
+    api::{Capabilities, CredentialPersistence, CredentialStoreApi, StoreKind},
+    attributes::{parse_attributes, parse_attributes_with_defaults},
 };
 
 /// The stored data for a credential
@@ -23,6 +27,19 @@ pub struct CredValue {
     pub secret: Vec<u8>,
     pub comment: Option<String>,
     pub creation_date: Option<String>,
+    /// When the secret or attributes were last changed
+    #[serde(default)]
+    pub modified_date: Option<String>,
+    #[serde(default)]
+    pub immutable: bool,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// The order of creation of this credential among those with its ID
+    #[serde(default)]
+    pub serial: u64,
+    /// Attributes set by clients, other than `comment`
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
 }
 
 impl CredValue {
@@ -31,18 +48,173 @@ impl CredValue {
             secret: secret.to_vec(),
             comment: None,
             creation_date: None,
+            modified_date: None,
+            immutable: false,
+            idempotency_key: None,
+            serial: 0,
+            attributes: HashMap::new(),
         }
     }
 
     pub fn new_ambiguous(comment: &str) -> CredValue {
+        Self::new_ambiguous_at(comment, &chrono::Local::now().to_rfc2822())
+    }
+
+    pub fn new_ambiguous_at(comment: &str, creation_date: &str) -> CredValue {
         CredValue {
             secret: vec![],
             comment: Some(comment.to_string()),
-            creation_date: Some(chrono::Local::now().to_rfc2822()),
+            creation_date: Some(creation_date.to_string()),
+            modified_date: None,
+            immutable: false,
+            idempotency_key: None,
+            serial: 0,
+            attributes: HashMap::new(),
         }
     }
 }
 
+/// Wipe the secret of a credential when it's deleted or the store is dropped.
+#[cfg(feature = "zeroize")]
+impl Drop for CredValue {
+    fn drop(&mut self) {
+        crate::secret::scrub(&mut self.secret);
+    }
+}
+
+/// The formats supported by [Store::export_to] and [Store::import_from].
+///
+/// Both formats put one credential on each line.
+/// The JSON format is only built if the `sample-json` feature is specified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExportFormat {
+    /// Each line is a JSON object.
+    #[cfg(feature = "sample-json")]
+    JsonLines,
+    /// Each line is a RON struct.
+    RonLines,
+}
+
+/// The formats a [Store] can use for its backing file.
+///
+/// See the module docs for the `backing-format` configuration key that sets this.
+/// The JSON format is only built if the `sample-json` feature is specified,
+/// which is why matches on this enum need a wildcard arm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BackingFormat {
+    /// The credential map, in RON.
+    #[default]
+    Ron,
+    /// A JSON array with one object per credential, as
+    /// written by [Store::export_to] in the `JsonLines` format.
+    #[cfg(feature = "sample-json")]
+    Json,
+}
+
+impl BackingFormat {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "ron" => Ok(BackingFormat::Ron),
+            #[cfg(feature = "sample-json")]
+            "json" => Ok(BackingFormat::Json),
+            #[cfg(not(feature = "sample-json"))]
+            "json" => Err(Invalid(
+                "backing-format".to_string(),
+                "`json` needs the `sample-json` feature".to_string(),
+            )),
+            _ => Err(Invalid(
+                "backing-format".to_string(),
+                "must be `ron` or `json`".to_string(),
+            )),
+        }
+    }
+}
+
+/// One credential, as written on a line by [Store::export_to].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub service: String,
+    pub user: String,
+    pub uuid: String,
+    /// The secret, in standard base64 encoding
+    pub secret: String,
+    pub comment: Option<String>,
+    pub creation_date: Option<String>,
+    /// When the secret or attributes were last changed
+    #[serde(default)]
+    pub modified_date: Option<String>,
+    #[serde(default)]
+    pub immutable: bool,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    #[serde(default)]
+    pub serial: u64,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl ExportRecord {
+    fn new(id: &CredId, uuid: &str, cred: &CredValue) -> Self {
+        ExportRecord {
+            service: id.service.clone(),
+            user: id.user.clone(),
+            uuid: uuid.to_string(),
+            secret: BASE64_STANDARD.encode(&cred.secret),
+            comment: cred.comment.clone(),
+            creation_date: cred.creation_date.clone(),
+            modified_date: cred.modified_date.clone(),
+            immutable: cred.immutable,
+            idempotency_key: cred.idempotency_key.clone(),
+            serial: cred.serial,
+            attributes: cred.attributes.clone(),
+        }
+    }
+
+    fn into_parts(self, location: String) -> Result<(CredId, String, CredValue)> {
+        let secret = BASE64_STANDARD
+            .decode(&self.secret)
+            .map_err(|e| Invalid(location, e.to_string()))?;
+        let id = CredId {
+            service: self.service,
+            user: self.user,
+        };
+        let value = CredValue {
+            secret,
+            comment: self.comment,
+            creation_date: self.creation_date,
+            modified_date: self.modified_date,
+            immutable: self.immutable,
+            idempotency_key: self.idempotency_key,
+            serial: self.serial,
+            attributes: self.attributes,
+        };
+        Ok((id, self.uuid, value))
+    }
+}
+
+/// A source of the current time.
+///
+/// Stores use a clock whenever they record a date, so tests
+/// can control the dates by supplying their own clock.
+pub trait Clock {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The clock that tells the system time.
+///
+/// This is the clock that stores use unless they are given another one.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
 /// A map from <service, user> pairs to matching credentials
 pub type CredMap = DashMap<CredId, DashMap<String, CredValue>>;
 
@@ -58,6 +230,98 @@ pub struct SelfRef {
     inner_store: Weak<Store>,
 }
 
+/// Optional store behaviors, chosen when the store is created.
+///
+/// See the module docs for the configuration keys that set these.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Look up credentials by UUID without exiting early.
+    pub constant_time_probe: bool,
+    /// Write a checksum of the credentials into the backing file.
+    pub checksum: bool,
+    /// What a search with an empty spec does.
+    pub empty_spec_policy: EmptySpecPolicy,
+    /// Treat service names and usernames that differ only in case as the same.
+    pub case_insensitive_specifiers: bool,
+    /// The format of the backing file.
+    pub backing_format: BackingFormat,
+    /// Save the store to its backing file after every change to a credential.
+    pub autosave: bool,
+    /// The key that encrypts the backing file, if it's encrypted.
+    #[cfg(feature = "sample-encryption")]
+    pub encryption: Option<super::encryption::BackingKey>,
+}
+
+/// What a [search](CredentialStoreApi::search) with an empty spec does.
+///
+/// See the module docs for the `empty-spec-policy` configuration key that sets this.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptySpecPolicy {
+    /// Return every credential in the store.
+    #[default]
+    All,
+    /// Return no credentials.
+    None,
+    /// Fail with an [Invalid](crate::Error::Invalid) error.
+    Error,
+}
+
+impl EmptySpecPolicy {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "all" => Ok(EmptySpecPolicy::All),
+            "none" => Ok(EmptySpecPolicy::None),
+            "error" => Ok(EmptySpecPolicy::Error),
+            _ => Err(Invalid(
+                "empty-spec-policy".to_string(),
+                "must be `all`, `none`, or `error`".to_string(),
+            )),
+        }
+    }
+}
+
+/// What [Store::rename_service] does when a credential would be
+/// moved to a service and user that already have credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave the colliding credentials under the old service.
+    Skip,
+    /// Delete the credentials under the new service and move the old ones over them.
+    Overwrite,
+    /// Fail with an [Invalid](crate::Error::Invalid) error without moving anything.
+    Error,
+}
+
+/// Statistics on waits for credential locks in a [Store].
+///
+/// These are only collected when the `contention-stats` feature is enabled.
+/// An acquisition is contended if the credential was locked by
+/// another thread when it was requested.
+#[cfg(feature = "contention-stats")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentionStats {
+    /// The number of credential locks acquired
+    pub acquisitions: u64,
+    /// The number of those acquisitions that had to wait
+    pub contended: u64,
+    /// The total time spent waiting in contended acquisitions
+    pub total_wait: Duration,
+    /// The longest time spent waiting in one acquisition
+    pub max_wait: Duration,
+}
+
+#[cfg(feature = "contention-stats")]
+impl ContentionStats {
+    /// The average time spent waiting in a contended acquisition.
+    pub fn average_wait(&self) -> Duration {
+        if self.contended == 0 {
+            Duration::ZERO
+        } else {
+            self.total_wait.div_f64(self.contended as f64)
+        }
+    }
+}
+
 /// A credential store.
 ///
 /// The credential data is kept in the CredMap. We keep the index of
@@ -67,7 +331,20 @@ pub struct Store {
     pub id: String,
     pub creds: CredMap,
     pub backing: Option<String>, // the backing file, if any
+    pub options: Options,
+    pub clock: RwLock<Arc<dyn Clock + Send + Sync>>,
+    pub schema: RwLock<Option<AttributeSchema>>,
+    /// Held for writing while credentials move between services,
+    /// and for reading while credentials are looked up or created.
+    pub rename_lock: RwLock<()>,
+    #[cfg(feature = "contention-stats")]
+    pub contention: std::sync::Mutex<ContentionStats>,
     pub self_ref: RwLock<SelfRef>,
+    /// The number of times the backing file has been written
+    pub saves: AtomicU64,
+    /// The credentials, by ID and UUID, that were in the backing file
+    /// when this store last read or wrote it
+    pub synced: Mutex<HashSet<(CredId, String)>>,
 }
 
 impl std::fmt::Debug for Store {
@@ -76,6 +353,7 @@ impl std::fmt::Debug for Store {
             .field("vendor", &self.vendor())
             .field("id", &self.id)
             .field("backing", &self.backing)
+            .field("options", &self.options)
             .field("cred-count", &self.creds.len())
             .finish()
     }
@@ -83,18 +361,22 @@ impl std::fmt::Debug for Store {
 
 impl Drop for Store {
     fn drop(&mut self) {
-        if self.backing.is_none() {
-            debug!("dropping store {self:?}")
-        } else {
-            debug!("Saving store {self:?} on drop...");
-            match self.save() {
-                Ok(_) => debug!("Save of store {self:?} completed"),
-                Err(e) => error!("Save of store {self:?} failed: {e:?}"),
-            }
-        }
+        // a failed save logs its own event
+        _ = self.save();
+        self.log_event("dropped", None);
     }
 }
 
+/// The log target for store lifecycle events.
+///
+/// Each event is logged as a message of the form
+/// `event=<event> vendor=<vendor> id=<id> backing=<path> count=<count>`,
+/// followed by `error=<error>` if the event is a failure.
+/// The events are `created`, `backing-loaded`, `reloaded`, `saved`, and
+/// `dropped`, which are logged at debug level, and `save-failed`,
+/// which is logged at error level.
+pub const LIFECYCLE_TARGET: &str = "keyring_core::sample::lifecycle";
+
 impl Store {
     /// Create a new store with a default configuration.
     ///
@@ -105,22 +387,71 @@ impl Store {
 
     /// Create a new store with a user-specified configuration.
     ///
-    /// There are two allowed configuration keys: `persist` and `backing-file`. See
-    /// the module docs for details of how these affect the store's behavior.
+    /// The allowed configuration keys are `persist`, `backing-file`,
+    /// `backing-format`, `autosave`, `create-dirs`, `constant-time-probe`,
+    /// `checksum`, `empty-spec-policy`, and `case-insensitive-specifiers`.
+    /// See the module docs
+    /// for details of how these affect the store's behavior.
     pub fn new_with_configuration(config: &HashMap<&str, &str>) -> Result<Arc<Self>> {
-        let mods = parse_attributes(&["backing-file", "*persist"], Some(config))?;
-        if let Some(path) = mods.get("backing-file") {
-            Self::new_with_backing(path)
-        } else if let Some(persist) = mods.get("persist") {
-            if persist == "true" {
-                let dir = std::env::temp_dir();
-                let path = dir.join("keyring-sample-store.ron");
-                Self::new_with_backing(path.to_str().expect("Invalid backing path"))
-            } else {
-                Self::new()
-            }
+        let mods = parse_attributes(
+            &[
+                "backing-file",
+                "backing-format",
+                "*autosave",
+                "*persist",
+                "*create-dirs",
+                "*constant-time-probe",
+                "*checksum",
+                "empty-spec-policy",
+                "*case-insensitive-specifiers",
+            ],
+            Some(config),
+        )?;
+        let options = Options {
+            constant_time_probe: mods.get("constant-time-probe").is_some_and(|v| v == "true"),
+            checksum: mods.get("checksum").is_some_and(|v| v == "true"),
+            empty_spec_policy: match mods.get("empty-spec-policy") {
+                Some(value) => EmptySpecPolicy::parse(value)?,
+                None => EmptySpecPolicy::default(),
+            },
+            case_insensitive_specifiers: mods
+                .get("case-insensitive-specifiers")
+                .is_some_and(|v| v == "true"),
+            backing_format: match mods.get("backing-format") {
+                Some(value) => BackingFormat::parse(value)?,
+                None => BackingFormat::default(),
+            },
+            autosave: mods.get("autosave").is_some_and(|v| v == "true"),
+            #[cfg(feature = "sample-encryption")]
+            encryption: None,
+        };
+        let backing = if let Some(path) = mods.get("backing-file") {
+            Some(path.clone())
+        } else if mods.get("persist").is_some_and(|v| v == "true") {
+            let dir = std::env::temp_dir();
+            let path = dir.join(match options.backing_format {
+                BackingFormat::Ron => "keyring-sample-store.ron",
+                #[cfg(feature = "sample-json")]
+                BackingFormat::Json => "keyring-sample-store.json",
+            });
+            Some(path.to_str().expect("Invalid backing path").to_string())
         } else {
-            Self::new()
+            None
+        };
+        if let Some(path) = backing.as_ref() {
+            check_backing_dir(path, mods.get("create-dirs").is_some_and(|v| v == "true"))?;
+        }
+        match backing {
+            Some(path) => Ok(Self::new_internal_with_options(
+                load_backing_file(&path, options.backing_format, options.checksum)?,
+                Some(path),
+                options,
+            )),
+            None => Ok(Self::new_internal_with_options(
+                DashMap::new(),
+                None,
+                options,
+            )),
         }
     }
 
@@ -129,13 +460,106 @@ impl Store {
     /// The backing file must be a valid path, but it need not exist,
     /// in which case the store starts off empty. If the file does
     /// exist, the initial contents of the store are loaded from it.
+    ///
+    /// The directory containing the backing file must exist; if it doesn't,
+    /// this returns an [Invalid](crate::Error::Invalid) error naming it.
+    /// Use the `create-dirs` configuration key with
+    /// [new_with_configuration](Store::new_with_configuration)
+    /// to have the directory created instead.
+    ///
+    /// The backing file is in RON; use
+    /// [new_with_backing_format](Store::new_with_backing_format)
+    /// for a backing file in another format.
+    ///
+    /// If the backing file exists but can't be parsed, this returns a
+    /// [BadDataFormat](crate::Error::BadDataFormat) error holding its content,
+    /// so it can be backed up before it's overwritten. Errors reading the
+    /// file are [PlatformFailure](crate::Error::PlatformFailure) errors.
     pub fn new_with_backing(path: &str) -> Result<Arc<Self>> {
-        Ok(Self::new_internal(
+        Self::new_with_backing_format(path, BackingFormat::Ron)
+    }
+
+    /// Create a new store from a backing file in the given format.
+    ///
+    /// This is like [new_with_backing](Store::new_with_backing),
+    /// except that the backing file is read and written in `format`.
+    pub fn new_with_backing_format(path: &str, format: BackingFormat) -> Result<Arc<Self>> {
+        check_backing_dir(path, false)?;
+        Ok(Self::new_internal_with_options(
+            Self::load_credentials_with_format(path, format)?,
+            Some(String::from(path)),
+            Options {
+                backing_format: format,
+                ..Options::default()
+            },
+        ))
+    }
+
+    /// Create a new store from a backing file that is encrypted with a passphrase.
+    ///
+    /// This is like [new_with_backing](Store::new_with_backing), except that
+    /// the backing file is encrypted with a key derived from `passphrase`.
+    /// (See the [encryption](super::encryption) module for how.) If the file
+    /// exists, it must have been written by a store using the same passphrase.
+    ///
+    /// Returns a [BadDataFormat](crate::Error::BadDataFormat) error holding
+    /// the content of the file if it can't be decrypted with the passphrase.
+    #[cfg(feature = "sample-encryption")]
+    pub fn new_with_encrypted_backing(path: &str, passphrase: &str) -> Result<Arc<Self>> {
+        use super::encryption::BackingKey;
+
+        check_backing_dir(path, false)?;
+        let (key, creds) = match read_backing(path)? {
+            Some(content) => {
+                let (key, plaintext) = BackingKey::open(passphrase, &content)?;
+                let s = String::from_utf8(plaintext)
+                    .map_err(|e| Error::BadDataFormat(content, Box::from(e)))?;
+                (key, parse_backing(&s, BackingFormat::Ron, false)?)
+            }
+            None => (BackingKey::new(passphrase), DashMap::new()),
+        };
+        Ok(Self::new_internal_with_options(
+            creds,
+            Some(String::from(path)),
+            Options {
+                encryption: Some(key),
+                ..Options::default()
+            },
+        ))
+    }
+
+    /// Create a new store from a backing file that is saved after every change.
+    ///
+    /// This is like [new_with_backing](Store::new_with_backing), except
+    /// that the store is saved each time a credential is created, changed,
+    /// or deleted, so the backing file always reflects the store's contents.
+    /// Each save re-serializes the entire store, so every write costs time
+    /// in proportion to the size of the store: this is meant for development
+    /// use with small stores.
+    pub fn new_with_backing_autosave(path: &str) -> Result<Arc<Self>> {
+        check_backing_dir(path, false)?;
+        Ok(Self::new_internal_with_options(
             Self::load_credentials(path)?,
             Some(String::from(path)),
+            Options {
+                autosave: true,
+                ..Options::default()
+            },
         ))
     }
 
+    /// Save this store to its backing file if it was created with autosave on.
+    ///
+    /// This is called after each change to a credential, once
+    /// the locks taken to make the change have been released.
+    pub fn autosave(&self) -> Result<()> {
+        if self.options.autosave {
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
     /// Save this store to its backing file.
     ///
     /// This is a no-op if there is no backing file.
@@ -147,19 +571,497 @@ impl Store {
     /// API default store, because the default store
     /// is kept in a static variable
     /// and thus is *never* dropped.
+    ///
+    /// If other stores share the backing file, the credentials they have
+    /// saved to it since this store last read or wrote it are added to this
+    /// store before it's written, so saves don't lose each other's credentials.
     pub fn save(&self) -> Result<()> {
         if self.backing.is_none() {
             return Ok(());
         };
-        let content = ron::ser::to_string_pretty(&self.creds, ron::ser::PrettyConfig::new())
-            .map_err(|e| PlatformFailure(Box::from(e)))?;
-        std::fs::write(self.backing.as_ref().unwrap(), content)
-            .map_err(|e| PlatformFailure(Box::from(e)))?;
+        match self.write_backing() {
+            Ok(()) => {
+                self.saves.fetch_add(1, Ordering::Relaxed);
+                self.log_event("saved", None);
+                Ok(())
+            }
+            Err(e) => {
+                self.log_event("save-failed", Some(&e));
+                Err(e)
+            }
+        }
+    }
+
+    /// Replace this store's credentials with those in its backing file.
+    ///
+    /// This discards any changes made in memory since the store was last
+    /// saved. (If the backing file doesn't exist, the store is emptied.)
+    /// If the store has no backing file, this does nothing.
+    ///
+    /// The credentials are replaced in place, so entries built earlier
+    /// stay usable. Specifiers find whatever credentials the file has
+    /// for their service name and username. Wrappers whose credential
+    /// is still in the file find it, and the rest fail with a
+    /// [NoEntry](crate::Error::NoEntry) error.
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = self.backing.as_ref() else {
+            return Ok(());
+        };
+        let creds = self.load_backing(path)?;
+        {
+            let _guard = self.rename_lock.write().expect("RwLock bug at reload!");
+            *self.synced.lock().expect("Mutex bug at reload!") = credential_ids(&creds);
+            self.creds.clear();
+            for (id, group) in creds {
+                self.creds.insert(id, group);
+            }
+        }
+        self.log_event("reloaded", None);
         Ok(())
     }
 
+    /// Load the credentials in a backing file in this store's format.
+    fn load_backing(&self, path: &str) -> Result<CredMap> {
+        match read_backing(path)? {
+            Some(content) => self.parse_content(content),
+            None => Ok(DashMap::new()),
+        }
+    }
+
+    /// Parse the content of a backing file in this store's format.
+    fn parse_content(&self, content: Vec<u8>) -> Result<CredMap> {
+        #[cfg(feature = "sample-encryption")]
+        if let Some(key) = self.options.encryption.as_ref() {
+            let plaintext = key.reopen(&content)?;
+            let s = String::from_utf8(plaintext)
+                .map_err(|e| Error::BadDataFormat(content, Box::from(e)))?;
+            return parse_backing(&s, self.options.backing_format, self.options.checksum);
+        }
+        let s = String::from_utf8(content)
+            .map_err(|e| Error::BadDataFormat(e.as_bytes().to_vec(), Box::from(e)))?;
+        parse_backing(&s, self.options.backing_format, self.options.checksum)
+    }
+
+    /// Add the credentials in the backing file that this store hasn't seen.
+    ///
+    /// Another store may have saved to the backing file since this one
+    /// last read or wrote it, and its credentials would be lost if the file
+    /// were simply replaced. Credentials that were in the file then, but
+    /// aren't in memory now, were deleted or renamed by this store, so they
+    /// aren't brought back. A file that can't be parsed has nothing to merge,
+    /// so it is replaced. This must be called with the backing file locked.
+    fn merge_backing(&self, path: &str) -> Result<()> {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(PlatformFailure(Box::from(e))),
+        };
+        let on_disk = match self.parse_content(content) {
+            Ok(creds) => creds,
+            Err(Error::BadDataFormat(_, e)) => {
+                warn!("replacing backing file {path}, which can't be parsed: {e}");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let _guard = self.read_ids();
+        let synced = self.synced.lock().expect("Mutex bug at merge!");
+        for (id, group) in on_disk {
+            for (uuid, value) in group {
+                if synced.contains(&(id.clone(), uuid.clone())) {
+                    continue;
+                }
+                let target = self.creds.entry(id.clone()).or_default();
+                target.entry(uuid).or_insert(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_backing(&self) -> Result<()> {
+        let path = self.backing.as_ref().unwrap();
+        let _lock = BackingLock::acquire(path, true)?;
+        self.merge_backing(path)?;
+        let _guard = self.read_ids();
+        let mut content = match self.options.backing_format {
+            BackingFormat::Ron => {
+                ron::ser::to_string_pretty(&self.creds, ron::ser::PrettyConfig::new())
+                    .map_err(|e| PlatformFailure(Box::from(e)))?
+            }
+            #[cfg(feature = "sample-json")]
+            BackingFormat::Json => {
+                let mut records: Vec<ExportRecord> = Vec::new();
+                for pair in self.creds.iter() {
+                    for cred in pair.value().iter() {
+                        records.push(ExportRecord::new(pair.key(), cred.key(), cred.value()));
+                    }
+                }
+                records.sort_by(|a, b| {
+                    (&a.service, &a.user, a.serial).cmp(&(&b.service, &b.user, b.serial))
+                });
+                serde_json::to_string_pretty(&records).map_err(|e| PlatformFailure(Box::from(e)))?
+            }
+        };
+        if self.options.checksum {
+            let header = format!(
+                "{CHECKSUM_PREFIX}{}\n",
+                to_hex(&checksum_credentials(&self.creds))
+            );
+            content.insert_str(0, &header);
+        }
+        #[cfg(feature = "sample-encryption")]
+        let content = match self.options.encryption.as_ref() {
+            Some(key) => key.seal(content.as_bytes())?,
+            None => content.into_bytes(),
+        };
+        write_atomically(path, content.as_ref())?;
+        *self.synced.lock().expect("Mutex bug at save!") = credential_ids(&self.creds);
+        Ok(())
+    }
+
+    /// The number of credentials in this store.
+    ///
+    /// Each of the credentials for a service and user is counted,
+    /// so this can be more than the number of service and user pairs.
+    pub fn len(&self) -> usize {
+        let _guard = self.read_ids();
+        self.creds.iter().map(|pair| pair.value().len()).sum()
+    }
+
+    /// Whether there are no credentials in this store.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Compute a SHA-256 checksum of the credentials in this store.
+    ///
+    /// The checksum covers the service name, username, UUID, secret,
+    /// and attributes of every credential. It doesn't depend on the
+    /// order in which the credentials were created, so two stores
+    /// with the same credentials have the same checksum.
+    pub fn checksum(&self) -> Result<[u8; 32]> {
+        let _guard = self.read_ids();
+        Ok(checksum_credentials(&self.creds))
+    }
+
+    /// Export a single credential as a document.
+    ///
+    /// The document has the same format as a backing file,
+    /// so it can also be used to create a [FrozenStore](super::FrozenStore).
+    /// If `uuid` is given, it selects one of several credentials with the
+    /// given service and user. Otherwise, there must be only one credential
+    /// for the service and user, and it is the one exported.
+    ///
+    /// Returns a [NoEntry](crate::Error::NoEntry) error if there is no
+    /// matching credential, and an [Ambiguous](crate::Error::Ambiguous)
+    /// error if there is more than one.
+    pub fn export_credential(
+        &self,
+        service: &str,
+        user: &str,
+        uuid: Option<&str>,
+    ) -> Result<String> {
+        let key = CredKey {
+            store: self.get_store(),
+            id: self.cred_id(service, user),
+            uuid: uuid.map(String::from),
+            immutable: false,
+            idempotency_key: None,
+        };
+        key.with_unique_pair(|uuid, cred| {
+            let creds = HashMap::from([(&key.id, HashMap::from([(uuid, &*cred)]))]);
+            ron::ser::to_string_pretty(&creds, ron::ser::PrettyConfig::new())
+        })?
+        .map_err(|e| PlatformFailure(Box::from(e)))
+    }
+
+    /// Import a single credential from a document produced by
+    /// [export_credential](Store::export_credential).
+    ///
+    /// The credential keeps its UUID, replacing any credential
+    /// with the same UUID in this store. Returns a wrapper for it.
+    ///
+    /// Returns an [Invalid](crate::Error::Invalid) error if the document
+    /// can't be parsed or doesn't contain exactly one credential.
+    pub fn import_credential(&self, doc: &str) -> Result<Entry> {
+        let creds: CredMap =
+            ron::de::from_str(doc).map_err(|e| Invalid("document".to_string(), e.to_string()))?;
+        if creds.len() != 1 || creds.iter().any(|pair| pair.value().len() != 1) {
+            return Err(Invalid(
+                "document".to_string(),
+                "must contain exactly one credential".to_string(),
+            ));
+        }
+        let (id, group) = creds.into_iter().next().unwrap();
+        let (uuid, value) = group.into_iter().next().unwrap();
+        {
+            let _guard = self.read_ids();
+            self.creds
+                .entry(id.clone())
+                .or_default()
+                .insert(uuid.clone(), value);
+        }
+        self.autosave()?;
+        Ok(Entry {
+            inner: Arc::new(CredKey {
+                store: self.get_store(),
+                id,
+                uuid: Some(uuid),
+                immutable: false,
+                idempotency_key: None,
+            }),
+        })
+    }
+
+    /// List the credentials that [delete_matching](Store::delete_matching)
+    /// would remove, without removing them.
+    ///
+    /// The `spec` is interpreted as it is by [search](CredentialStoreApi::search).
+    /// Each credential is described by its service, user, and UUID.
+    pub fn delete_matching_preview(
+        &self,
+        spec: &HashMap<&str, &str>,
+    ) -> Result<Vec<(String, String, Option<String>)>> {
+        Ok(self.search(spec)?.iter().map(describe_entry).collect())
+    }
+
+    /// Delete every credential that matches the `spec`.
+    ///
+    /// The `spec` is interpreted as it is by [search](CredentialStoreApi::search).
+    /// Returns the descriptions of the deleted credentials, in the same form as
+    /// [delete_matching_preview](Store::delete_matching_preview). Credentials
+    /// deleted by another thread while this call runs are left out.
+    ///
+    /// Any matching credential can be deleted, so either every one is
+    /// deleted or (if the search fails) none is. The store is autosaved
+    /// once, after they have all been deleted; if that save fails, its
+    /// error is returned, but the credentials stay deleted from the store.
+    pub fn delete_matching(
+        &self,
+        spec: &HashMap<&str, &str>,
+    ) -> Result<Vec<(String, String, Option<String>)>> {
+        let mut result = Vec::new();
+        for entry in self.search(spec)? {
+            let key = entry
+                .as_any()
+                .downcast_ref::<CredKey>()
+                .expect("Sample store produced a foreign entry: please report a bug!");
+            match key.delete_credential_unsaved() {
+                Ok(()) => result.push(describe_entry(&entry)),
+                // deleted by another thread since the search
+                Err(Error::NoEntry) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if !result.is_empty() {
+            self.autosave()?;
+        }
+        Ok(result)
+    }
+
+    /// Find groups of credentials that have the same secret.
+    ///
+    /// Each group holds wrappers for two or more credentials with
+    /// identical secrets, which is useful for detecting password reuse.
+    /// Credentials with an empty secret (such as those just made with
+    /// the `force-create` modifier) are never reported.
+    /// The secrets themselves are compared inside the store and are not
+    /// part of the result.
+    pub fn find_duplicate_secrets(&self) -> Result<Vec<Vec<Entry>>> {
+        let store = self.get_store();
+        let _guard = self.read_ids();
+        let mut groups: HashMap<Vec<u8>, Vec<Entry>> = HashMap::new();
+        for pair in self.creds.iter() {
+            for cred in pair.value().iter() {
+                if cred.value().secret.is_empty() {
+                    continue;
+                }
+                let entry = Entry {
+                    inner: Arc::new(CredKey {
+                        store: store.clone(),
+                        id: pair.key().clone(),
+                        uuid: Some(cred.key().clone()),
+                        immutable: false,
+                        idempotency_key: None,
+                    }),
+                };
+                groups
+                    .entry(cred.value().secret.clone())
+                    .or_default()
+                    .push(entry);
+            }
+        }
+        Ok(groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect())
+    }
+
+    /// Write all the credentials in this store to `w`, one per line.
+    ///
+    /// Each line is a self-contained document in the given `format`,
+    /// holding the service name, username, UUID, and attributes of
+    /// one credential, plus its secret in base64. Because credentials
+    /// are written as they are read, memory use doesn't grow with
+    /// the size of the store.
+    ///
+    /// Returns a [PlatformFailure](crate::Error::PlatformFailure) error
+    /// if writing fails.
+    pub fn export_to(&self, mut w: impl std::io::Write, format: ExportFormat) -> Result<()> {
+        let _guard = self.read_ids();
+        for pair in self.creds.iter() {
+            for cred in pair.value().iter() {
+                let record = ExportRecord::new(pair.key(), cred.key(), cred.value());
+                let line =
+                    match format {
+                        #[cfg(feature = "sample-json")]
+                        ExportFormat::JsonLines => serde_json::to_string(&record)
+                            .map_err(|e| PlatformFailure(Box::from(e)))?,
+                        ExportFormat::RonLines => ron::ser::to_string(&record)
+                            .map_err(|e| PlatformFailure(Box::from(e)))?,
+                    };
+                writeln!(w, "{line}").map_err(|e| PlatformFailure(Box::from(e)))?;
+            }
+        }
+        w.flush().map_err(|e| PlatformFailure(Box::from(e)))
+    }
+
+    /// Read credentials written by [export_to](Store::export_to) from `r`.
+    ///
+    /// Lines are read and imported one at a time, so memory use doesn't
+    /// grow with the size of the export. Blank lines are skipped.
+    /// Credentials keep their UUIDs, replacing any credentials
+    /// with the same UUIDs in this store. Returns the number of
+    /// credentials imported.
+    ///
+    /// Returns an [Invalid](crate::Error::Invalid) error naming the line
+    /// if a line can't be parsed; the credentials on earlier lines
+    /// will have been imported. Returns a
+    /// [PlatformFailure](crate::Error::PlatformFailure) error if reading fails.
+    pub fn import_from(&self, r: impl std::io::Read, format: ExportFormat) -> Result<usize> {
+        let result = self.import_from_unsaved(r, format);
+        // the credentials imported before a failure are saved too
+        self.autosave()?;
+        result
+    }
+
+    fn import_from_unsaved(&self, r: impl std::io::Read, format: ExportFormat) -> Result<usize> {
+        let _guard = self.read_ids();
+        let mut count = 0;
+        for (index, line) in std::io::BufRead::lines(std::io::BufReader::new(r)).enumerate() {
+            let line = line.map_err(|e| PlatformFailure(Box::from(e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ExportRecord = match format {
+                #[cfg(feature = "sample-json")]
+                ExportFormat::JsonLines => serde_json::from_str(&line).map_err(|e| e.to_string()),
+                ExportFormat::RonLines => ron::de::from_str(&line).map_err(|e| e.to_string()),
+            }
+            .map_err(|e| Invalid(format!("line {}", index + 1), e))?;
+            let (id, uuid, value) = record.into_parts(format!("line {}", index + 1))?;
+            self.creds.entry(id).or_default().insert(uuid, value);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Move all the credentials for service `old` to service `new`.
+    ///
+    /// Each credential keeps its UUID and attributes. If there are already
+    /// credentials for `new` and one of the users being moved, the `policy`
+    /// says what to do. Returns the number of credentials moved.
+    ///
+    /// The move is atomic: no lookup, search, or save sees some of the
+    /// credentials moved and others not. Entries built for the old service,
+    /// and wrappers for the moved credentials, no longer find them.
+    ///
+    /// Returns an [Invalid](crate::Error::Invalid) error naming the
+    /// first colliding user if the policy is [ConflictPolicy::Error].
+    pub fn rename_service(&self, old: &str, new: &str, policy: ConflictPolicy) -> Result<usize> {
+        let count = self.rename_service_unsaved(old, new, policy)?;
+        if count > 0 {
+            self.autosave()?;
+        }
+        Ok(count)
+    }
+
+    fn rename_service_unsaved(
+        &self,
+        old: &str,
+        new: &str,
+        policy: ConflictPolicy,
+    ) -> Result<usize> {
+        let old = self.cred_id(old, "").service;
+        let new = self.cred_id(new, "").service;
+        let _guard = self.rename_lock.write().expect("RwLock bug at rename!");
+        if old == new {
+            return Ok(0);
+        }
+        let ids: Vec<CredId> = self
+            .creds
+            .iter()
+            .filter(|pair| pair.key().service == old && !pair.value().is_empty())
+            .map(|pair| pair.key().clone())
+            .collect();
+        let mut moves = Vec::new();
+        for id in ids {
+            let target = CredId {
+                service: new.clone(),
+                user: id.user.clone(),
+            };
+            if self
+                .creds
+                .get(&target)
+                .is_some_and(|group| !group.is_empty())
+            {
+                match policy {
+                    ConflictPolicy::Skip => continue,
+                    ConflictPolicy::Overwrite => {}
+                    ConflictPolicy::Error => {
+                        return Err(Invalid(
+                            format!("user {}", id.user),
+                            format!("already has credentials for service {new}"),
+                        ));
+                    }
+                }
+            }
+            moves.push((id, target));
+        }
+        let mut count = 0;
+        for (id, target) in moves {
+            if let Some((_, group)) = self.creds.remove(&id) {
+                count += group.len();
+                self.creds.insert(target, group);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Hold off [rename_service](Store::rename_service) while the
+    /// returned guard is alive.
+    ///
+    /// Code holding the guard must not call back into the store,
+    /// because a waiting rename may block it from getting the guard again.
+    pub fn read_ids(&self) -> RwLockReadGuard<'_, ()> {
+        self.rename_lock.read().expect("RwLock bug at rename read!")
+    }
+
     /// Create a store with the given credentials and backing file.
     pub fn new_internal(creds: CredMap, backing: Option<String>) -> Arc<Self> {
+        Self::new_internal_with_options(creds, backing, Options::default())
+    }
+
+    /// Create a store with the given credentials, backing file, and options.
+    pub fn new_internal_with_options(
+        creds: CredMap,
+        backing: Option<String>,
+        options: Options,
+    ) -> Arc<Self> {
+        let synced = match backing {
+            Some(_) => credential_ids(&creds),
+            None => HashSet::new(),
+        };
         let store = Store {
             id: format!(
                 "Crate version {}, Instantiated at {}",
@@ -171,11 +1073,22 @@ impl Store {
             ),
             creds,
             backing,
+            options,
+            clock: RwLock::new(Arc::new(SystemClock)),
+            schema: RwLock::new(None),
+            rename_lock: RwLock::new(()),
+            #[cfg(feature = "contention-stats")]
+            contention: std::sync::Mutex::new(ContentionStats::default()),
             self_ref: RwLock::new(SelfRef {
                 inner_store: Weak::new(),
             }),
+            saves: AtomicU64::new(0),
+            synced: Mutex::new(synced),
         };
-        debug!("Created new store: {store:?}");
+        store.log_event("created", None);
+        if store.backing.is_some() {
+            store.log_event("backing-loaded", None);
+        }
         let result = Arc::new(store);
         result.set_store(result.clone());
         result
@@ -185,17 +1098,116 @@ impl Store {
     ///
     /// If the backing file does not exist, the returned store is empty.
     pub fn load_credentials(path: &str) -> Result<CredMap> {
-        match std::fs::exists(path) {
-            Ok(true) => match std::fs::read_to_string(path) {
-                Ok(s) => Ok(ron::de::from_str(&s).map_err(|e| PlatformFailure(Box::from(e)))?),
-                Err(e) => Err(PlatformFailure(Box::from(e))),
-            },
-            Ok(false) => Ok(DashMap::new()),
-            Err(e) => Err(Invalid("Invalid path".to_string(), e.to_string())),
+        Self::load_credentials_with_format(path, BackingFormat::Ron)
+    }
+
+    /// Loads store content from a backing file in the given format.
+    ///
+    /// If the backing file does not exist, the returned store is empty.
+    /// If it exists but can't be parsed, this returns a
+    /// [BadDataFormat](crate::Error::BadDataFormat) error holding its content.
+    pub fn load_credentials_with_format(path: &str, format: BackingFormat) -> Result<CredMap> {
+        load_backing_file(path, format, false)
+    }
+
+    /// Get the statistics on credential lock waits in this store so far.
+    #[cfg(feature = "contention-stats")]
+    pub fn contention_stats(&self) -> ContentionStats {
+        self.contention
+            .lock()
+            .expect("Mutex bug at contention stats get!")
+            .clone()
+    }
+
+    /// Record the acquisition of a credential lock, with the wait if it was contended.
+    #[cfg(feature = "contention-stats")]
+    pub fn record_lock_wait(&self, wait: Option<Duration>) {
+        let mut stats = self
+            .contention
+            .lock()
+            .expect("Mutex bug at contention stats update!");
+        stats.acquisitions += 1;
+        if let Some(wait) = wait {
+            stats.contended += 1;
+            stats.total_wait += wait;
+            stats.max_wait = stats.max_wait.max(wait);
         }
     }
 
-    fn get_store(&self) -> Arc<Store> {
+    /// Use the given clock for the dates recorded by this store.
+    ///
+    /// This returns the store, so it can be chained with store creation:
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use keyring_core::sample::{Store, store::SystemClock};
+    /// let store = Store::new().unwrap().with_clock(Arc::new(SystemClock));
+    /// ```
+    pub fn with_clock(self: Arc<Self>, clock: Arc<dyn Clock + Send + Sync>) -> Arc<Self> {
+        *self.clock.write().expect("RwLock bug at clock set!") = clock;
+        self
+    }
+
+    /// Constrain attribute updates on this store's credentials by a schema.
+    ///
+    /// Once a schema is set, [update_attributes](crate::Entry::update_attributes)
+    /// fails with an [Invalid](crate::Error::Invalid) error if it names an
+    /// attribute that isn't in the schema, gives an attribute a value the
+    /// schema doesn't allow, or leaves the credential without a value for
+    /// an attribute the schema requires. The schema replaces any earlier one.
+    ///
+    /// Returns an [Invalid](crate::Error::Invalid) error, and leaves the
+    /// current schema in place, if the schema names a reserved attribute
+    /// (such as `uuid` or `creation-date`), which can't be updated.
+    pub fn set_attribute_schema(&self, schema: AttributeSchema) -> Result<()> {
+        schema.check()?;
+        *self.schema.write().expect("RwLock bug at schema set!") = Some(schema);
+        Ok(())
+    }
+
+    /// Remove the schema set by [set_attribute_schema](Store::set_attribute_schema).
+    pub fn clear_attribute_schema(&self) {
+        *self.schema.write().expect("RwLock bug at schema set!") = None;
+    }
+
+    /// The current time according to this store's clock, as an RFC 2822 date.
+    pub fn now(&self) -> String {
+        let now = self.clock.read().expect("RwLock bug at clock get!").now();
+        chrono::DateTime::<chrono::Local>::from(now).to_rfc2822()
+    }
+
+    /// Log a lifecycle event for this store. (See [LIFECYCLE_TARGET].)
+    fn log_event(&self, event: &str, err: Option<&Error>) {
+        let fields = format!(
+            "event={event} vendor={:?} id={:?} backing={:?} count={}",
+            self.vendor(),
+            self.id,
+            self.backing,
+            self.creds.len()
+        );
+        match err {
+            None => debug!(target: LIFECYCLE_TARGET, "{fields}"),
+            Some(e) => error!(target: LIFECYCLE_TARGET, "{fields} error={e}"),
+        }
+    }
+
+    /// The ID of the credentials for a service name and username.
+    ///
+    /// If the store treats specifiers case-insensitively, they are lowercased.
+    pub(super) fn cred_id(&self, service: &str, user: &str) -> CredId {
+        if self.options.case_insensitive_specifiers {
+            CredId {
+                service: service.to_lowercase(),
+                user: user.to_lowercase(),
+            }
+        } else {
+            CredId {
+                service: service.to_owned(),
+                user: user.to_owned(),
+            }
+        }
+    }
+
+    pub(crate) fn get_store(&self) -> Arc<Store> {
         self.self_ref
             .read()
             .expect("RwLock bug at get!")
@@ -224,10 +1236,19 @@ impl CredentialStoreApi for Store {
         self.id.clone()
     }
 
+    /// See the API docs.
+    fn kind(&self) -> StoreKind {
+        StoreKind::Sample
+    }
+
     /// See the API docs.
     ///
-    /// The only modifier you can specify is `force-create`, which forces
-    /// immediate credential creation and can be used to create ambiguity.
+    /// The modifiers you can specify are `force-create`, which forces
+    /// immediate credential creation and can be used to create ambiguity;
+    /// `immutable`, which makes a credential immutable once its
+    /// secret is set through the returned entry; and `idempotency-key`,
+    /// which makes retried creations and writes harmless.
+    /// (See the module docs.)
     ///
     /// When the force-create modifier is specified, the created credential gets
     /// an empty password/secret, a `comment` attribute with the value of the modifier,
@@ -238,28 +1259,27 @@ impl CredentialStoreApi for Store {
         user: &str,
         mods: Option<&HashMap<&str, &str>>,
     ) -> Result<Entry> {
-        let id = CredId {
-            service: service.to_owned(),
-            user: user.to_owned(),
-        };
+        let id = self.cred_id(service, user);
+        let mods = parse_attributes(&["force-create", "*immutable", "idempotency-key"], mods)?;
         let key = CredKey {
             store: self.get_store(),
             id: id.clone(),
             uuid: None,
+            immutable: mods.get("immutable").is_some_and(|v| v == "true"),
+            idempotency_key: mods.get("idempotency-key").cloned(),
         };
-        if let Some(force_create) = parse_attributes(&["force-create"], mods)?.get("force-create") {
-            let uuid = Uuid::new_v4().to_string();
-            let value = CredValue::new_ambiguous(force_create);
-            match self.creds.get(&id) {
-                None => {
-                    let creds = DashMap::new();
-                    creds.insert(uuid, value);
-                    self.creds.insert(id, creds);
+        if let Some(force_create) = mods.get("force-create") {
+            {
+                // hold the lock on the credentials so a retry can't race this creation
+                let creds = self.creds.entry(id).or_default();
+                if !has_idempotency_key(&creds, key.idempotency_key.as_deref()) {
+                    let mut value = CredValue::new_ambiguous_at(force_create, &self.now());
+                    value.idempotency_key = key.idempotency_key.clone();
+                    value.serial = next_serial(&creds);
+                    creds.insert(Uuid::new_v4().to_string(), value);
                 }
-                Some(creds) => {
-                    creds.value().insert(uuid, value);
-                }
-            };
+            }
+            self.autosave()?;
         }
         Ok(Entry {
             inner: Arc::new(key),
@@ -268,53 +1288,125 @@ impl CredentialStoreApi for Store {
 
     /// See the API docs.
     ///
-    /// The specification must contain exactly two keys - `service` and `user` -
-    /// and their values must be valid regular expressions.
-    /// Every credential whose service name matches the service regex
-    /// _and_ whose username matches the user regex will be returned.
+    /// The values in the specification must be valid regular expressions.
+    /// The `service` and `user` keys match a credential's specifiers, and any
+    /// other key matches the credential's attribute of that name, so
+    /// credentials without that attribute don't match.
+    /// Every credential whose values match all the given regexes will be returned.
     /// (The match is a substring match, so the empty string will match every value.)
+    /// If the spec's `match-mode` key is `exact`, rather than the default
+    /// `substring`, each regex must match the entire value.
+    /// If its `ignore-case` key is `true`, the regexes ignore case.
+    ///
+    /// What an empty specification matches is set by the store's
+    /// `empty-spec-policy` configuration key; by default, it matches everything.
     fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
-        let mut result: Vec<Entry> = Vec::new();
-        let svc = regex::Regex::new(spec.get("service").unwrap_or(&""))
-            .map_err(|e| Invalid("service regex".to_string(), e.to_string()))?;
-        let usr = regex::Regex::new(spec.get("user").unwrap_or(&""))
-            .map_err(|e| Invalid("user regex".to_string(), e.to_string()))?;
-        let comment = regex::Regex::new(spec.get("uuid").unwrap_or(&""))
-            .map_err(|e| Invalid("comment regex".to_string(), e.to_string()))?;
-        let uuid = regex::Regex::new(spec.get("uuid").unwrap_or(&""))
-            .map_err(|e| Invalid("uuid regex".to_string(), e.to_string()))?;
+        let mut results = self.search_multi(std::slice::from_ref(spec))?;
+        Ok(results.pop().unwrap_or_default())
+    }
+
+    /// See the API docs.
+    ///
+    /// Each spec is interpreted as it is by [search](CredentialStoreApi::search),
+    /// but all the searches are done in a single pass over the store's credentials.
+    fn search_multi(&self, specs: &[HashMap<&str, &str>]) -> Result<Vec<Vec<Entry>>> {
+        let specs = specs
+            .iter()
+            .map(|spec| SearchSpec::compile(spec, &self.options))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results: Vec<Vec<Entry>> = specs.iter().map(|_| Vec::new()).collect();
         let store = self.get_store();
+        let _guard = self.read_ids();
         for pair in self.creds.iter() {
-            if !svc.is_match(pair.key().service.as_str()) {
-                continue;
-            }
-            if !usr.is_match(pair.key().user.as_str()) {
+            let matching: Vec<usize> = (0..specs.len())
+                .filter(|i| specs[*i].matches_id(pair.key()))
+                .collect();
+            if matching.is_empty() {
                 continue;
             }
             for cred in pair.value().iter() {
-                if !uuid.is_match(cred.key()) {
-                    continue;
-                }
-                if spec.get("comment").is_some() {
-                    if cred.value().comment.is_none() {
-                        continue;
-                    }
-                    if !comment.is_match(cred.value().comment.as_ref().unwrap()) {
+                for i in matching.iter() {
+                    if !specs[*i].matches_cred(cred.key(), cred.value()) {
                         continue;
                     }
+                    results[*i].push(Entry {
+                        inner: Arc::new(CredKey {
+                            store: store.clone(),
+                            id: pair.key().clone(),
+                            uuid: Some(cred.key().clone()),
+                            immutable: false,
+                            idempotency_key: None,
+                        }),
+                    })
                 }
+            }
+        }
+        Ok(results)
+    }
+
+    /// See the API docs.
+    fn all(&self) -> Result<Vec<Entry>> {
+        let store = self.get_store();
+        let _guard = self.read_ids();
+        let mut result = Vec::new();
+        for pair in self.creds.iter() {
+            for cred in pair.value().iter() {
                 result.push(Entry {
                     inner: Arc::new(CredKey {
                         store: store.clone(),
                         id: pair.key().clone(),
                         uuid: Some(cred.key().clone()),
+                        immutable: false,
+                        idempotency_key: None,
                     }),
-                })
+                });
             }
         }
         Ok(result)
     }
 
+    /// See the API docs.
+    ///
+    /// The secrets are all set before the store is autosaved,
+    /// so a store with autosave on writes its backing file once.
+    fn set_many(&self, items: &[(String, String, Vec<u8>)]) -> Result<Vec<Result<()>>> {
+        let results = items
+            .iter()
+            .map(|(service, user, secret)| {
+                let entry = self.build(service, user, None)?;
+                let key: &CredKey = entry
+                    .as_any()
+                    .downcast_ref()
+                    .expect("Sample store produced a foreign entry: please report a bug!");
+                key.set_secret_reporting_unsaved(secret).map(|_| ())
+            })
+            .collect();
+        self.autosave()?;
+        Ok(results)
+    }
+
+    /// See the API docs.
+    ///
+    /// This is the same as [Store::len].
+    fn count(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    /// See the API docs.
+    ///
+    /// The credentials are removed together, and then the store
+    /// is saved, if it has a backing file.
+    fn clear(&self) -> Result<usize> {
+        let count = {
+            let _guard = self.rename_lock.write().expect("RwLock bug at clear!");
+            let count = self.creds.iter().map(|pair| pair.value().len()).sum();
+            self.creds.clear();
+            count
+        };
+        self.save()?;
+        Ok(count)
+    }
+
     //// See the API docs.
     fn as_any(&self) -> &dyn Any {
         self
@@ -332,8 +1424,438 @@ impl CredentialStoreApi for Store {
         }
     }
 
+    /// See the API docs.
+    ///
+    /// The store persists to disk only if it has a backing file.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_search: true,
+            supports_attributes: true,
+            supports_ambiguity: true,
+            persists_to_disk: self.backing.is_some(),
+            supports_secret_bytes: true,
+        }
+    }
+
     /// See the API docs.
     fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         std::fmt::Debug::fmt(self, f)
     }
 }
+
+/// The serial number for a credential about to be added to a group.
+///
+/// Serial numbers increase in order of creation, so the credential
+/// created first in a group has the lowest one.
+pub fn next_serial(creds: &DashMap<String, CredValue>) -> u64 {
+    creds
+        .iter()
+        .map(|pair| pair.value().serial + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// The UUID of the primary credential in a group, if the group isn't empty.
+///
+/// The primary credential is the one with the lowest serial number.
+/// Credentials read from files written before serial numbers were
+/// recorded all have serial number 0, so ties are broken by UUID.
+pub fn primary_uuid(creds: &DashMap<String, CredValue>) -> Option<String> {
+    creds
+        .iter()
+        .min_by(|a, b| (a.value().serial, a.key()).cmp(&(b.value().serial, b.key())))
+        .map(|pair| pair.key().clone())
+}
+
+/// A search spec with its regular expressions compiled.
+struct SearchSpec {
+    service: regex::Regex,
+    user: regex::Regex,
+    /// The regexes for attributes, which a credential must have to match.
+    attrs: Vec<(String, regex::Regex)>,
+    /// The spec matches nothing, because it's empty and the policy says so.
+    nothing: bool,
+}
+
+/// The search spec keys that modify the search rather than match values.
+const SEARCH_MODIFIERS: [&str; 2] = ["match-mode", "ignore-case"];
+
+impl SearchSpec {
+    fn compile(spec: &HashMap<&str, &str>, options: &Options) -> Result<Self> {
+        let given: HashMap<&str, &str> = spec
+            .iter()
+            .filter(|(k, _)| SEARCH_MODIFIERS.contains(k))
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        let mods = parse_attributes_with_defaults(
+            &["match-mode", "*ignore-case"],
+            &[("match-mode", "substring"), ("ignore-case", "false")],
+            Some(&given),
+        )?;
+        let exact = match mods["match-mode"].as_str() {
+            "substring" => false,
+            "exact" => true,
+            _ => {
+                return Err(Invalid(
+                    "match-mode".to_string(),
+                    "must be `substring` or `exact`".to_string(),
+                ));
+            }
+        };
+        let ignore_case = mods["ignore-case"] == "true";
+        // in exact mode, a given pattern must match the whole value
+        let regex = |key: &str, case_insensitive: bool| {
+            let pattern = match spec.get(key) {
+                Some(p) if exact => format!("^(?:{p})$"),
+                Some(p) => p.to_string(),
+                None => String::new(),
+            };
+            regex::RegexBuilder::new(&pattern)
+                .case_insensitive(case_insensitive)
+                .build()
+                .map_err(|e| Invalid(format!("{key} regex"), e.to_string()))
+        };
+        let mut nothing = false;
+        if spec.len() == given.len() {
+            match options.empty_spec_policy {
+                EmptySpecPolicy::All => {}
+                EmptySpecPolicy::None => nothing = true,
+                EmptySpecPolicy::Error => {
+                    return Err(Invalid(
+                        "spec".to_string(),
+                        "can't be empty in this store".to_string(),
+                    ));
+                }
+            }
+        }
+        let specifier_case = ignore_case || options.case_insensitive_specifiers;
+        let service = regex("service", specifier_case)?;
+        let user = regex("user", specifier_case)?;
+        let mut attrs = Vec::new();
+        for key in spec.keys() {
+            if *key == "service" || *key == "user" || SEARCH_MODIFIERS.contains(key) {
+                continue;
+            }
+            attrs.push((key.to_string(), regex(key, ignore_case)?));
+        }
+        Ok(SearchSpec {
+            service,
+            user,
+            attrs,
+            nothing,
+        })
+    }
+
+    fn matches_id(&self, id: &CredId) -> bool {
+        !self.nothing && self.service.is_match(&id.service) && self.user.is_match(&id.user)
+    }
+
+    fn matches_cred(&self, uuid: &str, cred: &CredValue) -> bool {
+        if self.attrs.is_empty() {
+            return true;
+        }
+        let values = get_attrs(uuid, cred);
+        self.attrs
+            .iter()
+            .all(|(key, regex)| values.get(key).is_some_and(|value| regex.is_match(value)))
+    }
+}
+
+/// Describe a sample-store entry by its service, user, and UUID.
+fn describe_entry(entry: &Entry) -> (String, String, Option<String>) {
+    let key = entry
+        .as_any()
+        .downcast_ref::<CredKey>()
+        .expect("Sample store produced a foreign entry: please report a bug!");
+    (
+        key.id.service.clone(),
+        key.id.user.clone(),
+        key.uuid.clone(),
+    )
+}
+
+/// Tell whether any of a group of credentials was written with the given idempotency key.
+///
+/// This is always false if there is no key.
+pub fn has_idempotency_key(creds: &DashMap<String, CredValue>, key: Option<&str>) -> bool {
+    key.is_some_and(|key| {
+        creds
+            .iter()
+            .any(|cred| cred.value().idempotency_key.as_deref() == Some(key))
+    })
+}
+
+/// Make sure the directory that will hold a backing file exists.
+///
+/// If it doesn't, either create it or return an error naming it.
+fn check_backing_dir(path: &str, create: bool) -> Result<()> {
+    let Some(dir) = std::path::Path::new(path).parent() else {
+        return Ok(());
+    };
+    if dir.as_os_str().is_empty() || dir.is_dir() {
+        return Ok(());
+    }
+    if create {
+        std::fs::create_dir_all(dir).map_err(|e| PlatformFailure(Box::from(e)))
+    } else {
+        Err(Invalid(
+            "backing-file".to_string(),
+            format!("directory {} doesn't exist", dir.display()),
+        ))
+    }
+}
+
+/// How long a store waits for the lock on its backing file
+/// before failing with a [NoStorageAccess](Error::NoStorageAccess) error.
+pub const BACKING_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An advisory lock on a backing file, released when dropped.
+///
+/// The lock is taken on a `.lock` file next to the backing file,
+/// rather than on the backing file itself, because saving replaces
+/// the backing file with a new one. Readers share the lock, and
+/// a writer holds it exclusively.
+struct BackingLock(std::fs::File);
+
+impl BackingLock {
+    fn acquire(path: &str, exclusive: bool) -> Result<Self> {
+        let lock_path = format!("{path}.lock");
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(|e| PlatformFailure(Box::from(e)))?;
+        let deadline = std::time::Instant::now() + BACKING_LOCK_TIMEOUT;
+        loop {
+            let result = if exclusive {
+                fs2::FileExt::try_lock_exclusive(&file)
+            } else {
+                fs2::FileExt::try_lock_shared(&file)
+            };
+            match result {
+                Ok(()) => return Ok(BackingLock(file)),
+                Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                    if std::time::Instant::now() >= deadline {
+                        return Err(Error::NoStorageAccess(Box::from(format!(
+                            "timed out waiting for the lock on {path}"
+                        ))));
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                Err(e) => return Err(PlatformFailure(Box::from(e))),
+            }
+        }
+    }
+}
+
+impl Drop for BackingLock {
+    fn drop(&mut self) {
+        _ = fs2::FileExt::unlock(&self.0);
+    }
+}
+
+/// Replace the content of the file at `path`, so that a crash
+/// can't leave it half-written.
+///
+/// The content is written to a temporary file next to `path`, which is
+/// then renamed over it. If the two can't be on the same filesystem,
+/// so the rename fails, the file is written in place instead.
+fn write_atomically(path: &str, content: &[u8]) -> Result<()> {
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    let temp = format!(
+        "{path}.tmp-{}-{}",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    );
+    std::fs::write(&temp, content).map_err(|e| PlatformFailure(Box::from(e)))?;
+    match std::fs::rename(&temp, path) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            _ = std::fs::remove_file(&temp);
+            if e.kind() != std::io::ErrorKind::CrossesDevices {
+                return Err(PlatformFailure(Box::from(e)));
+            }
+            warn!("can't rename a temporary file over {path} ({e}), so writing it in place");
+            std::fs::write(path, content).map_err(|e| PlatformFailure(Box::from(e)))
+        }
+    }
+}
+
+/// The start of the first line of a backing file that has a checksum.
+///
+/// The line is a RON comment, so files with a checksum can still
+/// be read by stores that don't check it. JSON has no comments,
+/// so the line is skipped before a JSON backing file is parsed.
+const CHECKSUM_PREFIX: &str = "// sha256: ";
+
+/// Read the content of a backing file, under a shared lock.
+///
+/// Returns `None` if the file doesn't exist.
+fn read_backing(path: &str) -> Result<Option<Vec<u8>>> {
+    match std::fs::exists(path) {
+        Ok(true) => {
+            let _lock = BackingLock::acquire(path, false)?;
+            std::fs::read(path)
+                .map(Some)
+                .map_err(|e| PlatformFailure(Box::from(e)))
+        }
+        Ok(false) => Ok(None),
+        Err(e) => Err(Invalid("Invalid path".to_string(), e.to_string())),
+    }
+}
+
+/// Load the credentials in an unencrypted backing file.
+///
+/// If `require_checksum` is true, the file must have a checksum.
+fn load_backing_file(path: &str, format: BackingFormat, require_checksum: bool) -> Result<CredMap> {
+    match read_backing(path)? {
+        Some(content) => {
+            let s = String::from_utf8(content)
+                .map_err(|e| Error::BadDataFormat(e.as_bytes().to_vec(), Box::from(e)))?;
+            parse_backing(&s, format, require_checksum)
+        }
+        None => Ok(DashMap::new()),
+    }
+}
+
+/// Parse the content of a backing file and verify its checksum, if it has one.
+///
+/// If `require_checksum` is true, content without a checksum is rejected.
+fn parse_backing(content: &str, format: BackingFormat, require_checksum: bool) -> Result<CredMap> {
+    let creds = match format {
+        BackingFormat::Ron => {
+            ron::de::from_str(content).map_err(|e| bad_backing(content, Box::from(e)))?
+        }
+        #[cfg(feature = "sample-json")]
+        BackingFormat::Json => parse_json_backing(content)?,
+    };
+    verify_checksum(content, &creds, require_checksum)?;
+    Ok(creds)
+}
+
+/// The error for backing file content that can't be parsed.
+fn bad_backing(content: &str, e: crate::error::PlatformError) -> Error {
+    Error::BadDataFormat(content.as_bytes().to_vec(), e)
+}
+
+/// Parse the content of a JSON backing file.
+#[cfg(feature = "sample-json")]
+fn parse_json_backing(content: &str) -> Result<CredMap> {
+    let body = match content.strip_prefix(CHECKSUM_PREFIX) {
+        Some(rest) => rest.split_once('\n').map_or("", |(_, body)| body),
+        None => content,
+    };
+    let records: Vec<ExportRecord> =
+        serde_json::from_str(body).map_err(|e| bad_backing(content, Box::from(e)))?;
+    let creds: CredMap = DashMap::new();
+    for (index, record) in records.into_iter().enumerate() {
+        let (id, uuid, value) = record.into_parts(format!("record {}", index + 1))?;
+        creds.entry(id).or_default().insert(uuid, value);
+    }
+    Ok(creds)
+}
+
+/// The ID and UUID of every credential in a credential map.
+fn credential_ids(creds: &CredMap) -> HashSet<(CredId, String)> {
+    let mut ids = HashSet::new();
+    for pair in creds.iter() {
+        for cred in pair.value().iter() {
+            ids.insert((pair.key().clone(), cred.key().clone()));
+        }
+    }
+    ids
+}
+
+/// Compute the checksum of a credential map.
+///
+/// The credentials are hashed in sorted order, and each field
+/// is length-prefixed, so the result is canonical.
+pub fn checksum_credentials(creds: &CredMap) -> [u8; 32] {
+    fn field(hasher: &mut Sha256, value: Option<&[u8]>) {
+        match value {
+            None => hasher.update([0u8]),
+            Some(bytes) => {
+                hasher.update([1u8]);
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+        }
+    }
+    let mut ids: Vec<CredId> = creds.iter().map(|pair| pair.key().clone()).collect();
+    ids.sort_by(|a, b| (&a.service, &a.user).cmp(&(&b.service, &b.user)));
+    let mut hasher = Sha256::new();
+    for id in ids {
+        let Some(group) = creds.get(&id) else {
+            continue;
+        };
+        let mut uuids: Vec<String> = group.iter().map(|pair| pair.key().clone()).collect();
+        uuids.sort();
+        for uuid in uuids {
+            let Some(cred) = group.get(&uuid) else {
+                continue;
+            };
+            field(&mut hasher, Some(id.service.as_bytes()));
+            field(&mut hasher, Some(id.user.as_bytes()));
+            field(&mut hasher, Some(uuid.as_bytes()));
+            field(&mut hasher, Some(&cred.secret));
+            field(&mut hasher, cred.comment.as_ref().map(|s| s.as_bytes()));
+            field(
+                &mut hasher,
+                cred.creation_date.as_ref().map(|s| s.as_bytes()),
+            );
+            field(&mut hasher, Some(&[cred.immutable as u8]));
+            field(
+                &mut hasher,
+                cred.idempotency_key.as_ref().map(|s| s.as_bytes()),
+            );
+            field(&mut hasher, Some(&cred.serial.to_be_bytes()));
+            // credentials without attributes hash as they did before attributes existed
+            if !cred.attributes.is_empty() {
+                let mut attrs: Vec<_> = cred.attributes.iter().collect();
+                attrs.sort();
+                field(&mut hasher, Some(&(attrs.len() as u64).to_be_bytes()));
+                for (key, value) in attrs {
+                    field(&mut hasher, Some(key.as_bytes()));
+                    field(&mut hasher, Some(value.as_bytes()));
+                }
+            }
+            // as are credentials without a modification date
+            if let Some(date) = &cred.modified_date {
+                field(&mut hasher, Some(b"modified-date"));
+                field(&mut hasher, Some(date.as_bytes()));
+            }
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// If the content of a backing file has a checksum, check that it matches the credentials.
+///
+/// If `required` is true, content without a checksum is a
+/// [BadDataFormat](Error::BadDataFormat) error.
+fn verify_checksum(content: &str, creds: &CredMap, required: bool) -> Result<()> {
+    let Some(rest) = content.strip_prefix(CHECKSUM_PREFIX) else {
+        if required {
+            return Err(Error::BadDataFormat(
+                content.as_bytes().to_vec(),
+                Box::from("the backing file has no checksum"),
+            ));
+        }
+        return Ok(());
+    };
+    let expected = rest.lines().next().unwrap_or_default().trim();
+    if expected == to_hex(&checksum_credentials(creds)) {
+        Ok(())
+    } else {
+        Err(Error::BadDataFormat(
+            content.as_bytes().to_vec(),
+            Box::from("the checksum doesn't match the credentials"),
+        ))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
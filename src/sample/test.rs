@@ -5,8 +5,14 @@ use dashmap::DashMap;
 use uuid::Uuid;
 
 use super::credential::{CredId, CredKey};
-use super::store::{CredValue, Store};
-use crate::{CredentialStore, Entry, Error, api::CredentialPersistence, get_default_store};
+use super::frozen::FrozenStore;
+#[cfg(feature = "sample-json")]
+use super::store::BackingFormat;
+use super::store::{Clock, ConflictPolicy, CredValue, ExportFormat, Store};
+use crate::{
+    Capabilities, CredentialStore, Entry, Error, SetOutcome, StoreKind, api::CredentialPersistence,
+    get_default_store,
+};
 
 static SET_STORE: Once = Once::new();
 
@@ -168,6 +174,29 @@ fn test_round_trip_random_secret() {
     test_round_trip_secret("non-ascii password", &entry, secret.as_slice());
 }
 
+#[test]
+fn test_with_secret() {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.with_secret(hash), Err(Error::NoEntry)));
+    let secret: Vec<u8> = (0..1024 * 1024).map(|_| fastrand::u8(..)).collect();
+    entry.set_secret(&secret).unwrap();
+    assert_eq!(entry.with_secret(hash).unwrap(), hash(&secret));
+    assert_eq!(entry.with_secret(|s| s.len()).unwrap(), secret.len());
+    let cred: &CredKey = entry.as_any().downcast_ref().unwrap();
+    assert_eq!(cred.with_secret(hash).unwrap(), hash(&secret));
+    entry.delete_credential().unwrap();
+}
+
 #[test]
 fn test_update() {
     let name = generate_random_string();
@@ -180,6 +209,143 @@ fn test_update() {
     );
 }
 
+#[test]
+fn test_set_if_changed() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(entry.set_password_if_changed("first").unwrap());
+    assert_eq!(entry.get_password().unwrap(), "first");
+    assert!(!entry.set_password_if_changed("first").unwrap());
+    assert!(entry.set_password_if_changed("second").unwrap());
+    assert_eq!(entry.get_password().unwrap(), "second");
+    let wrapper = entry.get_credential().unwrap();
+    assert!(!wrapper.set_secret_if_changed(b"second").unwrap());
+    assert!(wrapper.set_secret_if_changed(b"third").unwrap());
+    assert_eq!(entry.get_password().unwrap(), "third");
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    assert!(matches!(
+        entry.set_password_if_changed("third"),
+        Err(Error::Ambiguous(_))
+    ));
+    wrapper.delete_credential().unwrap();
+    assert!(matches!(
+        wrapper.set_password_if_changed("fourth"),
+        Err(Error::NoEntry)
+    ));
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_increment_secret() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert_eq!(entry.increment_secret_u64(5).unwrap(), 5);
+    assert_eq!(entry.increment_secret_u64(3).unwrap(), 8);
+    assert_eq!(entry.get_secret().unwrap(), 8u64.to_be_bytes());
+    entry.set_secret(&(u64::MAX - 1).to_be_bytes()).unwrap();
+    assert!(matches!(
+        entry.increment_secret_u64(2),
+        Err(Error::Invalid(_, _))
+    ));
+    entry.set_password("not a counter").unwrap();
+    assert!(matches!(
+        entry.increment_secret_u64(1),
+        Err(Error::BadDataFormat(_, _))
+    ));
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_simultaneous_increment_secret() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let mut handles = vec![];
+    for delta in 1..=10u64 {
+        let entry = store.build(&name, &name, None).unwrap();
+        let test = move || {
+            for _ in 0..100 {
+                entry.increment_secret_u64(delta).unwrap();
+            }
+        };
+        handles.push(std::thread::spawn(test))
+    }
+    for handle in handles {
+        handle.join().unwrap()
+    }
+    let entry = store.build(&name, &name, None).unwrap();
+    assert_eq!(entry.increment_secret_u64(0).unwrap(), 100 * 55);
+    assert_eq!(store.search(&HashMap::new()).unwrap().len(), 1);
+}
+
+#[test]
+fn test_delete_matching() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    let other = generate_random_string();
+    let e1 = store.build(&name, "user1", None).unwrap();
+    e1.set_password("1").unwrap();
+    let e2 = store.build(&name, "user2", None).unwrap();
+    e2.set_password("2").unwrap();
+    let e3 = store.build(&other, "user1", None).unwrap();
+    e3.set_password("3").unwrap();
+    let spec = HashMap::from([("service", name.as_str())]);
+    let mut preview = sample.delete_matching_preview(&spec).unwrap();
+    preview.sort();
+    assert_eq!(preview.len(), 2);
+    assert_eq!(preview[0].0, name);
+    assert_eq!(preview[0].1, "user1");
+    assert_eq!(preview[1].1, "user2");
+    assert!(preview.iter().all(|(_, _, uuid)| uuid.is_some()));
+    assert_eq!(e1.get_password().unwrap(), "1");
+    assert_eq!(e2.get_password().unwrap(), "2");
+    let mut deleted = sample.delete_matching(&spec).unwrap();
+    deleted.sort();
+    assert_eq!(deleted, preview);
+    assert!(matches!(e1.get_password(), Err(Error::NoEntry)));
+    assert!(matches!(e2.get_password(), Err(Error::NoEntry)));
+    assert_eq!(e3.get_password().unwrap(), "3");
+    assert!(sample.delete_matching(&spec).unwrap().is_empty());
+}
+
+#[test]
+fn test_delete_matching_saves_once() {
+    let path = std::env::temp_dir()
+        .join(format!(
+            "store-delete-test-{}.ron",
+            generate_random_string()
+        ))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let sample = Store::new_with_backing_autosave(&path).unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    for user in ["user1", "user2", "user3"] {
+        store
+            .build("service", user, None)
+            .unwrap()
+            .set_password(user)
+            .unwrap();
+    }
+    let saves = sample.saves.load(std::sync::atomic::Ordering::Relaxed);
+    let spec = HashMap::from([("service", "service")]);
+    assert_eq!(sample.delete_matching(&spec).unwrap().len(), 3);
+    assert_eq!(
+        sample.saves.load(std::sync::atomic::Ordering::Relaxed),
+        saves + 1
+    );
+    let saved = Store::load_credentials(&path).unwrap();
+    assert!(saved.iter().all(|group| group.value().is_empty()));
+    drop(store);
+    drop(sample);
+    // saving left both the backing file and its lock file
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
 #[test]
 fn test_duplicate_entries() {
     let name = generate_random_string();
@@ -202,15 +368,10 @@ fn test_get_update_attributes() {
     assert!(matches!(entry1.get_attributes(), Err(Error::NoEntry)));
     entry1.set_password("password for entry1").unwrap();
     let attrs = entry1.get_attributes().unwrap();
-    assert_eq!(attrs.len(), 1); // uuid
-    let unknown_map = HashMap::from([("foo", "bar")]);
+    assert_eq!(attrs.len(), 2); // uuid and modified-date
     let forbidden_map1 = HashMap::from([("creation-date", "doesn't matter")]);
     let forbidden_map2 = HashMap::from([("uuid", "doesn't matter")]);
     let comment_map = HashMap::from([("comment", "some comment")]);
-    assert!(matches!(
-        entry1.update_attributes(&unknown_map),
-        Err(Error::Invalid(_, _))
-    ));
     assert!(matches!(
         entry1.update_attributes(&forbidden_map1),
         Err(Error::Invalid(_, _))
@@ -240,6 +401,275 @@ fn test_get_update_attributes() {
     entry2.delete_credential().unwrap();
 }
 
+#[test]
+fn test_custom_attributes() {
+    let path = std::env::temp_dir().join(format!("{}.ron", generate_random_string()));
+    let path = path.to_str().unwrap();
+    let store = Store::new_with_backing(path).unwrap();
+    let cred_store: Arc<CredentialStore> = store.clone();
+    let entry = cred_store
+        .build(
+            "service",
+            "user",
+            Some(&HashMap::from([("force-create", "original")])),
+        )
+        .unwrap();
+    entry
+        .update_attributes(&HashMap::from([
+            ("label", "Work"),
+            ("url", "https://example.com"),
+        ]))
+        .unwrap();
+    let attrs = entry.get_attributes().unwrap();
+    assert_eq!(attrs.len(), 6);
+    assert_eq!(attrs["label"], "Work");
+    assert_eq!(attrs["url"], "https://example.com");
+    assert_eq!(attrs["comment"], "original");
+    let mut keys = entry.attribute_keys().unwrap();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "comment",
+            "creation-date",
+            "label",
+            "modified-date",
+            "url",
+            "uuid"
+        ]
+    );
+    entry
+        .update_attributes(&HashMap::from([("label", "Home")]))
+        .unwrap();
+    assert_eq!(entry.get_attributes().unwrap()["label"], "Home");
+    let date = attrs["creation-date"].clone();
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("creation-date", "yesterday"), ("label", "x")])),
+        Err(Error::Invalid(key, _)) if key == "creation-date"
+    ));
+    let attrs = entry.get_attributes().unwrap();
+    assert_eq!(attrs["creation-date"], date);
+    assert_eq!(attrs["label"], "Home");
+    store.save().unwrap();
+    let reloaded = Store::new_with_backing(path).unwrap();
+    let reloaded: Arc<CredentialStore> = reloaded;
+    let entry = reloaded.build("service", "user", None).unwrap();
+    assert_eq!(entry.get_attributes().unwrap(), attrs);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_uuid_attribute() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let entry = store.build("service", "user", None).unwrap();
+    entry.set_password("password").unwrap();
+    let key: &CredKey = entry.as_any().downcast_ref().unwrap();
+    let uuid = key.get_uuid().unwrap();
+    assert_eq!(entry.get_attributes().unwrap()["uuid"], uuid);
+    let wrapper = entry.get_credential().unwrap();
+    assert_eq!(wrapper.get_attributes().unwrap()["uuid"], uuid);
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("uuid", "not-a-uuid")])),
+        Err(Error::Invalid(key, _)) if key == "uuid"
+    ));
+    assert_eq!(entry.get_attributes().unwrap()["uuid"], uuid);
+}
+
+#[test]
+fn test_get_comment_and_creation_date() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let plain = store.build("service", "plain", None).unwrap();
+    let key: &CredKey = plain.as_any().downcast_ref().unwrap();
+    assert!(matches!(key.get_comment(), Err(Error::NoEntry)));
+    plain.set_password("password").unwrap();
+    assert_eq!(key.get_comment().unwrap(), None);
+    assert_eq!(key.get_creation_date().unwrap(), None);
+    let forced = store
+        .build(
+            "service",
+            "forced",
+            Some(&HashMap::from([("force-create", "my comment")])),
+        )
+        .unwrap();
+    let key: &CredKey = forced.as_any().downcast_ref().unwrap();
+    let attrs = forced.get_attributes().unwrap();
+    assert_eq!(key.get_comment().unwrap().unwrap(), "my comment");
+    assert_eq!(
+        key.get_creation_date().unwrap().as_ref(),
+        attrs.get("creation-date")
+    );
+}
+
+#[test]
+fn test_modified_date() {
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    struct SteppingClock(Mutex<SystemTime>);
+
+    impl Clock for SteppingClock {
+        fn now(&self) -> SystemTime {
+            let mut now = self.0.lock().unwrap();
+            *now += Duration::from_secs(60);
+            *now
+        }
+    }
+
+    let clock = Arc::new(SteppingClock(Mutex::new(SystemTime::now())));
+    let store: Arc<CredentialStore> = Store::new().unwrap().with_clock(clock);
+    let mods = HashMap::from([("force-create", "dated")]);
+    let entry = store.build("service", "user", Some(&mods)).unwrap();
+    let key: &CredKey = entry.as_any().downcast_ref().unwrap();
+    let created = key.get_creation_date().unwrap().unwrap();
+    assert_eq!(key.get_modified_date().unwrap(), None);
+    assert_eq!(entry.inner.get_modified().unwrap(), None);
+    entry.set_password("first").unwrap();
+    let first = key.get_modified_date().unwrap().unwrap();
+    let first_time = entry.inner.get_modified().unwrap().unwrap();
+    entry.set_password("second").unwrap();
+    let second = key.get_modified_date().unwrap().unwrap();
+    assert_ne!(first, second);
+    assert!(entry.inner.get_modified().unwrap().unwrap() > first_time);
+    assert_eq!(key.get_creation_date().unwrap().unwrap(), created);
+    let attrs = entry.get_attributes().unwrap();
+    assert_eq!(attrs["modified-date"], second);
+    assert_eq!(attrs["creation-date"], created);
+    entry
+        .update_attributes(&HashMap::from([("comment", "changed")]))
+        .unwrap();
+    assert_ne!(key.get_modified_date().unwrap().unwrap(), second);
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("modified-date", "never")])),
+        Err(Error::Invalid(_, _))
+    ));
+}
+
+#[test]
+fn test_rename() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let old = store
+        .build(
+            "service",
+            "old",
+            Some(&HashMap::from([("force-create", "my comment")])),
+        )
+        .unwrap();
+    old.set_password("password").unwrap();
+    let before = old.get_attributes().unwrap();
+    old.rename("service", "new").unwrap();
+    assert!(matches!(old.get_password(), Err(Error::NoEntry)));
+    let new = store.build("service", "new", None).unwrap();
+    assert_eq!(new.get_password().unwrap(), "password");
+    assert_eq!(new.get_attributes().unwrap(), before);
+    // a wrapper can move the credential back, and then no longer finds it
+    let wrapper = new.get_credential().unwrap();
+    wrapper.rename("service", "old").unwrap();
+    assert!(matches!(wrapper.get_password(), Err(Error::NoEntry)));
+    assert_eq!(old.get_attributes().unwrap(), before);
+    assert!(matches!(new.rename("service", "old"), Err(Error::NoEntry)));
+}
+
+#[test]
+fn test_rename_collision() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let first = store.build("service", "first", None).unwrap();
+    first.set_password("first").unwrap();
+    let second = store.build("service", "second", None).unwrap();
+    second.set_password("second").unwrap();
+    assert!(matches!(
+        first.rename("service", "second"),
+        Err(Error::Invalid(_, _))
+    ));
+    assert_eq!(first.get_password().unwrap(), "first");
+    assert_eq!(second.get_password().unwrap(), "second");
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    for _ in 0..2 {
+        store.build("service", "ambiguous", Some(&mods)).unwrap();
+    }
+    let ambiguous = store.build("service", "ambiguous", None).unwrap();
+    assert!(matches!(
+        ambiguous.rename("service", "third"),
+        Err(Error::Ambiguous(_))
+    ));
+    // the mock store doesn't support renaming
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    let entry = mock.build("service", "user", None).unwrap();
+    entry.set_password("password").unwrap();
+    assert!(matches!(
+        entry.rename("service", "other"),
+        Err(Error::NotSupportedByStore(_))
+    ));
+}
+
+#[test]
+fn test_has_empty_secret() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.has_empty_secret(), Err(Error::NoEntry)));
+    entry.set_secret(&[]).unwrap();
+    assert!(entry.has_empty_secret().unwrap());
+    assert_eq!(entry.get_secret().unwrap(), Vec::<u8>::new());
+    entry.set_password("not empty").unwrap();
+    assert!(!entry.has_empty_secret().unwrap());
+    entry.delete_credential().unwrap();
+    assert!(matches!(entry.has_empty_secret(), Err(Error::NoEntry)));
+    let mods = HashMap::from([("force-create", "empty")]);
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    assert!(entry.has_empty_secret().unwrap());
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_merged_attributes() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.merged_attributes(), Err(Error::NoEntry)));
+    for comment in ["first", "second", "second"] {
+        let mods = HashMap::from([("force-create", comment)]);
+        store.build(&name, &name, Some(&mods)).unwrap();
+    }
+    let merged = entry.merged_attributes().unwrap();
+    let comments: Vec<&str> = merged["comment"].iter().map(String::as_str).collect();
+    assert_eq!(comments, vec!["first", "second"]);
+    assert_eq!(merged["uuid"].len(), 3);
+    let wrapper = entry.resolve_by_attribute("comment", "first").unwrap();
+    let merged = wrapper.merged_attributes().unwrap();
+    assert_eq!(merged["comment"].len(), 1);
+    assert!(merged["comment"].contains("first"));
+    assert_eq!(merged.len(), wrapper.get_attributes().unwrap().len());
+    for wrapper in store
+        .search(&HashMap::from([("service", name.as_str())]))
+        .unwrap()
+    {
+        wrapper.delete_credential().unwrap();
+    }
+}
+
+#[test]
+fn test_attribute_keys() {
+    fn sorted(mut keys: Vec<String>) -> Vec<String> {
+        keys.sort();
+        keys
+    }
+
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry1 = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry1.attribute_keys(), Err(Error::NoEntry)));
+    entry1.set_password("password for entry1").unwrap();
+    let expected = sorted(entry1.get_attributes().unwrap().into_keys().collect());
+    assert_eq!(sorted(entry1.attribute_keys().unwrap()), expected);
+    assert_eq!(expected, vec!["modified-date", "uuid"]);
+    let name = generate_random_string();
+    let mods = HashMap::from([("force-create", "entry2")]);
+    let entry2 = store.build(&name, &name, Some(&mods)).unwrap();
+    let expected = sorted(entry2.get_attributes().unwrap().into_keys().collect());
+    assert_eq!(sorted(entry2.attribute_keys().unwrap()), expected);
+    assert_eq!(expected, vec!["comment", "creation-date", "uuid"]);
+}
+
 #[test]
 fn test_get_credential_and_specifiers() {
     let name = generate_random_string();
@@ -271,12 +701,16 @@ fn test_with_unique_credential_helper() {
             store: store.clone(),
             id: id.clone(),
             uuid: None,
+            immutable: false,
+            idempotency_key: None,
         };
         assert!(matches!(spec.with_unique_cred(|_| ()), Err(Error::NoEntry),));
         let wrapper = CredKey {
             store: store.clone(),
             id: id.clone(),
             uuid: Some(Uuid::new_v4().to_string()),
+            immutable: false,
+            idempotency_key: None,
         };
         assert!(matches!(
             wrapper.with_unique_cred(|_| ()),
@@ -294,6 +728,8 @@ fn test_with_unique_credential_helper() {
             store: store.clone(),
             id: id.clone(),
             uuid: None,
+            immutable: false,
+            idempotency_key: None,
         };
         let uuid1 = Uuid::new_v4().to_string();
         let uuid2 = Uuid::new_v4().to_string();
@@ -301,11 +737,15 @@ fn test_with_unique_credential_helper() {
             store: store.clone(),
             id: id.clone(),
             uuid: Some(uuid1.clone()),
+            immutable: false,
+            idempotency_key: None,
         };
         let wrapper2 = CredKey {
             store: store.clone(),
             id: id.clone(),
             uuid: Some(uuid2.clone()),
+            immutable: false,
+            idempotency_key: None,
         };
         let creds = DashMap::new();
         creds.insert(uuid1.clone(), CredValue::new(&[1u8, 2u8]));
@@ -366,6 +806,48 @@ fn test_credential_and_ambiguous_credential() {
     entry1.delete_credential().unwrap();
 }
 
+#[test]
+fn test_resolve_by_attribute() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(
+        entry.resolve_by_attribute("comment", "e1"),
+        Err(Error::NoEntry)
+    ));
+    for comment in ["e1", "e2", "e3", "e3"] {
+        let mods = HashMap::from([("force-create", comment)]);
+        store.build(&name, &name, Some(&mods)).unwrap();
+    }
+    assert!(matches!(entry.get_password(), Err(Error::Ambiguous(_))));
+    let e2 = entry.resolve_by_attribute("comment", "e2").unwrap();
+    let cred: &CredKey = e2.as_any().downcast_ref().unwrap();
+    assert!(cred.uuid.is_some());
+    assert_eq!(cred.get_comment().unwrap().unwrap(), "e2");
+    e2.set_password("password for e2").unwrap();
+    assert!(matches!(
+        entry.resolve_by_attribute("comment", "e4"),
+        Err(Error::NoEntry)
+    ));
+    let Err(Error::Ambiguous(e3s)) = entry.resolve_by_attribute("comment", "e3") else {
+        panic!("Expected two ambiguous e3 entries")
+    };
+    assert_eq!(e3s.len(), 2);
+    // once ambiguity is gone, the sole credential is checked
+    for e3 in e3s {
+        e3.delete_credential().unwrap();
+    }
+    let e1 = entry.resolve_by_attribute("comment", "e1").unwrap();
+    e1.delete_credential().unwrap();
+    let e2 = entry.resolve_by_attribute("comment", "e2").unwrap();
+    assert_eq!(e2.get_password().unwrap(), "password for e2");
+    assert!(matches!(
+        entry.resolve_by_attribute("comment", "e1"),
+        Err(Error::NoEntry)
+    ));
+    entry.delete_credential().unwrap();
+}
+
 #[test]
 fn test_create_then_move() {
     let name = generate_random_string();
@@ -548,8 +1030,210 @@ fn test_search() {
     assert_eq!(two.len(), 2);
     let three = store.search(&HashMap::from([("service", "foo")])).unwrap();
     assert_eq!(three.len(), 3);
-    let all = store.search(&HashMap::from([("foo", "bar")])).unwrap();
-    assert_eq!(all.len(), 3);
+    assert!(
+        store
+            .search(&HashMap::from([("foo", "bar")]))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_search_by_comment() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let first = store.build("service", "first", None).unwrap();
+    first.set_password("first").unwrap();
+    first
+        .update_attributes(&HashMap::from([("comment", "work laptop")]))
+        .unwrap();
+    let second = store.build("service", "second", None).unwrap();
+    second.set_password("second").unwrap();
+    second
+        .update_attributes(&HashMap::from([("comment", "home desktop")]))
+        .unwrap();
+    let found = store
+        .search(&HashMap::from([("comment", "^work")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "first");
+    let found = store
+        .search(&HashMap::from([("comment", "desk"), ("service", "serv")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "second");
+    assert!(
+        store
+            .search(&HashMap::from([("comment", "phone")]))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_search_match_mode() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    for user in ["foo", "foobar"] {
+        let entry = store.build("service", user, None).unwrap();
+        entry.set_password(user).unwrap();
+    }
+    let substring = store.search(&HashMap::from([("user", "foo")])).unwrap();
+    assert_eq!(substring.len(), 2);
+    let substring = store
+        .search(&HashMap::from([
+            ("user", "foo"),
+            ("match-mode", "substring"),
+        ]))
+        .unwrap();
+    assert_eq!(substring.len(), 2);
+    let exact = store
+        .search(&HashMap::from([("user", "foo"), ("match-mode", "exact")]))
+        .unwrap();
+    assert_eq!(exact.len(), 1);
+    assert_eq!(exact[0].get_password().unwrap(), "foo");
+    let exact = store
+        .search(&HashMap::from([
+            ("user", "foo|foobar"),
+            ("match-mode", "exact"),
+        ]))
+        .unwrap();
+    assert_eq!(exact.len(), 2);
+    let exact = store
+        .search(&HashMap::from([
+            ("service", "serv"),
+            ("match-mode", "exact"),
+        ]))
+        .unwrap();
+    assert!(exact.is_empty());
+    assert!(matches!(
+        store.search(&HashMap::from([("user", "foo"), ("match-mode", "prefix")])),
+        Err(Error::Invalid(key, _)) if key == "match-mode"
+    ));
+}
+
+#[test]
+fn test_search_ignore_case() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let entry = store.build("github", "user", None).unwrap();
+    entry.set_password("password").unwrap();
+    entry
+        .update_attributes(&HashMap::from([("comment", "Work Account")]))
+        .unwrap();
+    assert!(
+        store
+            .search(&HashMap::from([("service", "Github")]))
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        store
+            .search(&HashMap::from([
+                ("service", "Github"),
+                ("ignore-case", "false")
+            ]))
+            .unwrap()
+            .is_empty()
+    );
+    let found = store
+        .search(&HashMap::from([
+            ("service", "Github"),
+            ("ignore-case", "true"),
+        ]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "password");
+    let found = store
+        .search(&HashMap::from([
+            ("comment", "work account"),
+            ("ignore-case", "true"),
+        ]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(matches!(
+        store.search(&HashMap::from([("service", "Github"), ("ignore-case", "yes")])),
+        Err(Error::Invalid(key, _)) if key == "ignore-case"
+    ));
+}
+
+#[test]
+fn test_search_by_attribute() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let keyed = store
+        .build(
+            "service",
+            "keyed",
+            Some(&HashMap::from([("idempotency-key", "request-17")])),
+        )
+        .unwrap();
+    keyed.set_password("keyed").unwrap();
+    assert_eq!(
+        keyed
+            .get_attributes()
+            .unwrap()
+            .get("idempotency-key")
+            .unwrap(),
+        "request-17"
+    );
+    let plain = store.build("service", "plain", None).unwrap();
+    plain.set_password("plain").unwrap();
+    let found = store
+        .search(&HashMap::from([("idempotency-key", "request-1")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "keyed");
+    let found = store
+        .search(&HashMap::from([
+            ("idempotency-key", "request-1"),
+            ("match-mode", "exact"),
+        ]))
+        .unwrap();
+    assert!(found.is_empty());
+    // a credential without the attribute doesn't match even an empty regex
+    let found = store
+        .search(&HashMap::from([("idempotency-key", "")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    let found = store
+        .search(&HashMap::from([("uuid", ""), ("service", "service")]))
+        .unwrap();
+    assert_eq!(found.len(), 2);
+}
+
+#[test]
+fn test_kind() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    assert_eq!(store.kind(), StoreKind::Sample);
+}
+
+#[test]
+fn test_capabilities() {
+    let expected = Capabilities {
+        supports_search: true,
+        supports_attributes: true,
+        supports_ambiguity: true,
+        persists_to_disk: false,
+        supports_secret_bytes: true,
+    };
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    assert_eq!(store.capabilities(), expected);
+    let path = std::env::temp_dir()
+        .join(format!(
+            "store-capabilities-{}.ron",
+            generate_random_string()
+        ))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let store: Arc<CredentialStore> = Store::new_with_backing(&path).unwrap();
+    assert_eq!(
+        store.capabilities(),
+        Capabilities {
+            persists_to_disk: true,
+            ..expected
+        }
+    );
+    drop(store);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
 }
 
 #[test]
@@ -589,42 +1273,1964 @@ fn test_persistence_with_backing_and_save() {
     assert_eq!(s2.as_ref().creds.len(), 2);
 }
 
+#[cfg(feature = "sample-json")]
 #[test]
-fn test_persistence_with_backing_and_drop() {
+fn test_persistence_with_json_backing_and_save() {
     let path = std::env::temp_dir()
-        .join("keyring-sample-store.ron")
+        .join("store-save-test.json")
         .to_str()
         .unwrap()
         .to_string();
     _ = std::fs::remove_file(&path);
-    println!("Persistence test path is {path}");
-    {
-        let s1 = Store::new_with_backing(&path).unwrap();
-        let cred_store: Arc<CredentialStore> = s1.clone();
-        assert_eq!(s1.as_ref().creds.len(), 0);
-        let e1 = cred_store.build("s1", "u1", None).unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 0);
-        e1.set_password("pw1").unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 1);
-        let e2 = cred_store.build("s2", "u2", None).unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 1);
-        e2.set_password("pw2").unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 2);
-    }
-    {
-        let s1 = Store::new_with_backing(&path).unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 2);
-    }
-    {
-        let s1 = Store::new_with_configuration(&HashMap::from([("persist", "true")])).unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 2);
-        let store: Arc<CredentialStore> = s1.clone();
-        let e3 = store.build("s3", "u3", None).unwrap();
-        e3.set_password("pw3").unwrap();
-        assert_eq!(s1.as_ref().creds.len(), 3);
-    }
+    let s1 = Store::new_with_backing_format(&path, BackingFormat::Json).unwrap();
+    let cred_store: Arc<CredentialStore> = s1.clone();
+    assert!(matches!(
+        cred_store.persistence(),
+        CredentialPersistence::UntilDelete
+    ));
+    assert_eq!(s1.as_ref().creds.len(), 0);
+    let e1 = cred_store.build("s1", "u1", None).unwrap();
+    e1.set_password("pw1").unwrap();
+    e1.update_attributes(&HashMap::from([("comment", "first")]))
+        .unwrap();
+    let e2 = cred_store.build("s2", "u2", None).unwrap();
+    e2.set_secret(&[0, 159, 146, 150]).unwrap();
+    assert_eq!(s1.as_ref().creds.len(), 2);
+    s1.save().unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    let records: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(records.as_array().unwrap().len(), 2);
+    assert_eq!(records[0]["service"], "s1");
+    let s2 = Store::new_with_backing_format(&path, BackingFormat::Json).unwrap();
+    assert_eq!(s2.as_ref().creds.len(), 2);
+    assert_eq!(s2.checksum().unwrap(), s1.checksum().unwrap());
+    let cred_store: Arc<CredentialStore> = s2.clone();
+    let e1 = cred_store.build("s1", "u1", None).unwrap();
+    assert_eq!(e1.get_password().unwrap(), "pw1");
+    assert_eq!(e1.get_attributes().unwrap()["comment"], "first");
+    let e2 = cred_store.build("s2", "u2", None).unwrap();
+    assert_eq!(e2.get_secret().unwrap(), vec![0, 159, 146, 150]);
+    assert!(matches!(
+        Store::new_with_backing(&path),
+        Err(Error::BadDataFormat(_, _))
+    ));
+    drop(s2);
+    _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_persistence_with_backing_autosave() {
+    let path = std::env::temp_dir()
+        .join("store-autosave-test.ron")
+        .to_str()
+        .unwrap()
+        .to_string();
+    _ = std::fs::remove_file(&path);
+    let s1 = Store::new_with_backing_autosave(&path).unwrap();
+    let cred_store: Arc<CredentialStore> = s1.clone();
+    let e1 = cred_store.build("s1", "u1", None).unwrap();
+    e1.set_password("pw1").unwrap();
+    let e2 = cred_store.build("s2", "u2", None).unwrap();
+    e2.set_password("pw2").unwrap();
+    e1.update_attributes(&HashMap::from([("comment", "saved")]))
+        .unwrap();
+    e2.delete_credential().unwrap();
+    let s2 = Store::new_with_backing(&path).unwrap();
+    let reader: Arc<CredentialStore> = s2.clone();
+    let e1 = reader.build("s1", "u1", None).unwrap();
+    assert_eq!(e1.get_password().unwrap(), "pw1");
+    assert_eq!(e1.get_attributes().unwrap()["comment"], "saved");
+    assert!(matches!(
+        reader.build("s2", "u2", None).unwrap().get_password(),
+        Err(Error::NoEntry)
+    ));
+    // changes made through the store, rather than an entry, are saved too
+    assert_eq!(
+        s1.rename_service("s1", "renamed", ConflictPolicy::Error)
+            .unwrap(),
+        1
+    );
+    let doc = s2.export_credential("s1", "u1", None).unwrap();
+    s1.import_credential(&doc).unwrap();
+    let saved = Store::new_with_backing(&path).unwrap();
+    let saved_store: Arc<CredentialStore> = saved.clone();
+    let renamed = saved_store.build("renamed", "u1", None).unwrap();
+    assert_eq!(renamed.get_password().unwrap(), "pw1");
+    let imported = saved_store.build("s1", "u1", None).unwrap();
+    assert_eq!(imported.get_password().unwrap(), "pw1");
+    drop(saved);
+    cred_store
+        .build("renamed", "u1", None)
+        .unwrap()
+        .delete_credential()
+        .unwrap();
+    // without autosave, changes aren't saved until the store is
+    let s3 = Store::new_with_backing(&path).unwrap();
+    let writer: Arc<CredentialStore> = s3.clone();
+    writer
+        .build("s3", "u3", None)
+        .unwrap()
+        .set_password("pw3")
+        .unwrap();
+    let s4 = Store::new_with_backing(&path).unwrap();
+    assert_eq!(s4.len(), 1);
+    drop(s1);
+    drop(s2);
+    drop(s3);
+    drop(s4);
+    _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_set_many() {
+    let path = std::env::temp_dir()
+        .join(format!("store-set-many-{}.ron", generate_random_string()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let sample = Store::new_with_backing_autosave(&path).unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    for _ in 0..2 {
+        store.build("service", "ambiguous", Some(&mods)).unwrap();
+    }
+    let saves = sample.saves.load(std::sync::atomic::Ordering::Relaxed);
+    let mut items: Vec<(String, String, Vec<u8>)> = (0..100)
+        .map(|i| {
+            (
+                "service".to_string(),
+                format!("user{i}"),
+                format!("secret{i}").into_bytes(),
+            )
+        })
+        .collect();
+    items.insert(50, ("service".to_string(), "ambiguous".to_string(), vec![]));
+    let results = store.set_many(&items).unwrap();
+    assert_eq!(results.len(), 101);
+    assert!(matches!(results[50], Err(Error::Ambiguous(_))));
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 100);
+    assert_eq!(
+        sample.saves.load(std::sync::atomic::Ordering::Relaxed),
+        saves + 1
+    );
+    let saved = Store::load_credentials(&path).unwrap();
+    assert_eq!(saved.len(), 101);
+    let entry = store.build("service", "user99", None).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "secret99");
+    // the default implementation sets the secrets one at a time
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    let results = mock.set_many(&items[..3]).unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+    let entry = mock.build("service", "user2", None).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "secret2");
+    drop(sample);
+    drop(store);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[test]
+fn test_save_replaces_corrupt_backing_file() {
+    let dir = std::env::temp_dir().join(format!("keyring-atomic-{}", generate_random_string()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("store.ron").to_str().unwrap().to_string();
+    let s1 = Store::new_with_backing(&path).unwrap();
+    let cred_store: Arc<CredentialStore> = s1.clone();
+    cred_store
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("pw1")
+        .unwrap();
+    // what a crash in the middle of a non-atomic write would leave
+    std::fs::write(&path, "{\n    (service: \"s1\", user: ").unwrap();
+    assert!(Store::load_credentials(&path).is_err());
+    s1.save().unwrap();
+    let creds = Store::load_credentials(&path).unwrap();
+    assert_eq!(creds.len(), 1);
+    // the temporary file is gone
+    let names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert!(names.contains(&std::ffi::OsString::from("store.ron")));
+    assert!(
+        names
+            .iter()
+            .all(|name| !name.to_string_lossy().contains(".tmp-"))
+    );
+    drop(s1);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_unparseable_backing_file() {
+    let path = std::env::temp_dir().join(format!("{}.ron", generate_random_string()));
+    let path = path.to_str().unwrap();
+    let garbage = b"this is not RON {{{".to_vec();
+    std::fs::write(path, &garbage).unwrap();
+    match Store::new_with_backing(path) {
+        Err(Error::BadDataFormat(bytes, _)) => assert_eq!(bytes, garbage),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    #[cfg(feature = "sample-json")]
+    match Store::new_with_backing_format(path, BackingFormat::Json) {
+        Err(Error::BadDataFormat(bytes, _)) => assert_eq!(bytes, garbage),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    let not_utf8 = vec![0xff, 0xfe, 0x00];
+    std::fs::write(path, &not_utf8).unwrap();
+    match Store::load_credentials(path) {
+        Err(Error::BadDataFormat(bytes, _)) => assert_eq!(bytes, not_utf8),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_reload() {
+    let path = std::env::temp_dir()
+        .join(format!("store-reload-{}.ron", generate_random_string()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let s1 = Store::new_with_backing(&path).unwrap();
+    let s2 = Store::new_with_backing(&path).unwrap();
+    let writer: Arc<CredentialStore> = s1.clone();
+    let reader: Arc<CredentialStore> = s2.clone();
+    let written = writer.build("service", "user", None).unwrap();
+    written.set_password("from s1").unwrap();
+    let read = reader.build("service", "user", None).unwrap();
+    assert!(matches!(read.get_password(), Err(Error::NoEntry)));
+    s1.save().unwrap();
+    s2.reload().unwrap();
+    assert_eq!(read.get_password().unwrap(), "from s1");
+    // unsaved changes are discarded
+    let unsaved = reader.build("service", "unsaved", None).unwrap();
+    unsaved.set_password("never saved").unwrap();
+    s2.reload().unwrap();
+    assert!(matches!(unsaved.get_password(), Err(Error::NoEntry)));
+    assert_eq!(read.get_password().unwrap(), "from s1");
+    // a store without a backing file has nothing to reload
+    let memory = Store::new().unwrap();
+    let memory_store: Arc<CredentialStore> = memory.clone();
+    let kept = memory_store.build("service", "user", None).unwrap();
+    kept.set_password("kept").unwrap();
+    memory.reload().unwrap();
+    assert_eq!(kept.get_password().unwrap(), "kept");
+    drop(s1);
+    drop(s2);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[test]
+fn test_reload_wrappers() {
+    let path = std::env::temp_dir()
+        .join(format!("store-reload-{}.ron", generate_random_string()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let s1 = Store::new_with_backing(&path).unwrap();
+    let store: Arc<CredentialStore> = s1.clone();
+    for comment in ["survivor", "vanisher"] {
+        store
+            .build(
+                "service",
+                "user",
+                Some(&HashMap::from([("force-create", comment)])),
+            )
+            .unwrap();
+    }
+    let specifier = store.build("service", "user", None).unwrap();
+    let Err(Error::Ambiguous(wrappers)) = specifier.get_password() else {
+        panic!("expected an ambiguous entry");
+    };
+    let comment = |e: &Entry| e.get_attributes().unwrap()["comment"].clone();
+    let (survivor, vanisher) = if comment(&wrappers[0]) == "survivor" {
+        (wrappers[0].clone(), wrappers[1].clone())
+    } else {
+        (wrappers[1].clone(), wrappers[0].clone())
+    };
+    s1.save().unwrap();
+    // another store removes one of the credentials from the file
+    let s2 = Store::new_with_backing(&path).unwrap();
+    let other: Arc<CredentialStore> = s2.clone();
+    let found = other
+        .search(&HashMap::from([("comment", "vanisher")]))
+        .unwrap();
+    found[0].delete_credential().unwrap();
+    s2.save().unwrap();
+    s1.reload().unwrap();
+    survivor.set_password("still here").unwrap();
+    assert_eq!(survivor.get_password().unwrap(), "still here");
+    assert!(matches!(vanisher.get_password(), Err(Error::NoEntry)));
+    assert_eq!(specifier.get_password().unwrap(), "still here");
+    drop(s1);
+    drop(s2);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[test]
+fn test_concurrent_saves_to_one_backing_file() {
+    let dir = std::env::temp_dir().join(format!("keyring-locked-{}", generate_random_string()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("store.ron").to_str().unwrap().to_string();
+    let writers: Vec<_> = ["first", "second"]
+        .into_iter()
+        .map(|name| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let store = Store::new_with_backing(&path).unwrap();
+                let cred_store: Arc<CredentialStore> = store.clone();
+                for i in 0..20 {
+                    let entry = cred_store.build(name, &i.to_string(), None).unwrap();
+                    entry.set_password(name).unwrap();
+                    store.save().unwrap();
+                    // every save is whole when read back
+                    Store::load_credentials(&path).unwrap();
+                }
+                drop(store);
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    // every save merges in the other writer's credentials, so none are lost
+    let saved: Arc<CredentialStore> = Store::new_with_backing(&path).unwrap();
+    for name in ["first", "second"] {
+        for i in 0..20 {
+            let entry = saved.build(name, &i.to_string(), None).unwrap();
+            assert_eq!(entry.get_password().unwrap(), name);
+        }
+    }
+    assert_eq!(saved.count().unwrap(), 40);
+    drop(saved);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_backing_lock_timeout() {
+    let dir = std::env::temp_dir().join(format!("keyring-timeout-{}", generate_random_string()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("store.ron").to_str().unwrap().to_string();
+    let store = Store::new_with_backing(&path).unwrap();
+    store.save().unwrap();
+    let holder = std::fs::File::open(format!("{path}.lock")).unwrap();
+    fs2::FileExt::lock_exclusive(&holder).unwrap();
+    assert!(matches!(store.save(), Err(Error::NoStorageAccess(_))));
+    assert!(matches!(
+        Store::load_credentials(&path),
+        Err(Error::NoStorageAccess(_))
+    ));
+    fs2::FileExt::unlock(&holder).unwrap();
+    store.save().unwrap();
+    drop(store);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(feature = "sample-encryption")]
+fn test_encrypted_backing() {
+    let path = std::env::temp_dir()
+        .join(format!("store-encrypted-{}.bin", generate_random_string()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let s1 = Store::new_with_encrypted_backing(&path, "right passphrase").unwrap();
+    let cred_store: Arc<CredentialStore> = s1.clone();
+    cred_store
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("plaintext password")
+        .unwrap();
+    s1.save().unwrap();
+    let content = std::fs::read(&path).unwrap();
+    assert!(content.starts_with(super::encryption::MAGIC));
+    assert!(
+        !content
+            .windows("plaintext password".len())
+            .any(|w| w == b"plaintext password")
+    );
+    assert!(Store::load_credentials(&path).is_err());
+    let s2 = Store::new_with_encrypted_backing(&path, "right passphrase").unwrap();
+    let reader: Arc<CredentialStore> = s2.clone();
+    assert_eq!(
+        reader
+            .build("s1", "u1", None)
+            .unwrap()
+            .get_password()
+            .unwrap(),
+        "plaintext password"
+    );
+    cred_store
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("changed password")
+        .unwrap();
+    s1.save().unwrap();
+    s2.reload().unwrap();
+    assert_eq!(
+        reader
+            .build("s1", "u1", None)
+            .unwrap()
+            .get_password()
+            .unwrap(),
+        "changed password"
+    );
+    let content = std::fs::read(&path).unwrap();
+    match Store::new_with_encrypted_backing(&path, "wrong passphrase") {
+        Err(Error::BadDataFormat(data, _)) => assert_eq!(data, content),
+        other => panic!("expected a bad data format error, got {other:?}"),
+    }
+    drop(s1);
+    drop(s2);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[cfg(not(feature = "sample-json"))]
+#[test]
+fn test_json_backing_needs_feature() {
+    let config = HashMap::from([("backing-format", "json")]);
+    assert!(matches!(
+        Store::new_with_configuration(&config),
+        Err(Error::Invalid(key, _)) if key == "backing-format"
+    ));
+}
+
+#[cfg(feature = "sample-json")]
+#[test]
+fn test_json_backing_with_checksum() {
+    let path = std::env::temp_dir()
+        .join("store-checksum-test.json")
+        .to_str()
+        .unwrap()
+        .to_string();
+    _ = std::fs::remove_file(&path);
+    let config = HashMap::from([
+        ("backing-file", path.as_str()),
+        ("backing-format", "json"),
+        ("checksum", "true"),
+    ]);
+    let s1 = Store::new_with_configuration(&config).unwrap();
+    let cred_store: Arc<CredentialStore> = s1.clone();
+    cred_store
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("pw1")
+        .unwrap();
+    s1.save().unwrap();
+    assert!(
+        std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("// sha256: ")
+    );
+    let s2 = Store::new_with_configuration(&config).unwrap();
+    assert_eq!(s2.checksum().unwrap(), s1.checksum().unwrap());
+    assert!(matches!(
+        Store::new_with_configuration(&HashMap::from([("backing-format", "yaml")])),
+        Err(Error::Invalid(_, _))
+    ));
+    drop(s1);
+    drop(s2);
+    _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_persistence_with_backing_and_drop() {
+    let path = std::env::temp_dir()
+        .join("keyring-sample-store.ron")
+        .to_str()
+        .unwrap()
+        .to_string();
+    _ = std::fs::remove_file(&path);
+    println!("Persistence test path is {path}");
+    {
+        let s1 = Store::new_with_backing(&path).unwrap();
+        let cred_store: Arc<CredentialStore> = s1.clone();
+        assert_eq!(s1.as_ref().creds.len(), 0);
+        let e1 = cred_store.build("s1", "u1", None).unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 0);
+        e1.set_password("pw1").unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 1);
+        let e2 = cred_store.build("s2", "u2", None).unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 1);
+        e2.set_password("pw2").unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 2);
+    }
+    {
+        let s1 = Store::new_with_backing(&path).unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 2);
+    }
+    {
+        let s1 = Store::new_with_configuration(&HashMap::from([("persist", "true")])).unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 2);
+        let store: Arc<CredentialStore> = s1.clone();
+        let e3 = store.build("s3", "u3", None).unwrap();
+        e3.set_password("pw3").unwrap();
+        assert_eq!(s1.as_ref().creds.len(), 3);
+    }
     {
         let s1 = Store::new_with_configuration(&HashMap::from([("persist", "true")])).unwrap();
         assert_eq!(s1.as_ref().creds.len(), 3);
     }
 }
+
+#[test]
+fn test_frozen_store() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    store
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("pw1")
+        .unwrap();
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    store.build("s2", "u2", Some(&mods)).unwrap();
+    store.build("s2", "u2", Some(&mods)).unwrap();
+    let sample: &Store = store.as_any().downcast_ref().unwrap();
+    let doc = ron::ser::to_string(&sample.creds).unwrap();
+    let frozen: Arc<CredentialStore> = FrozenStore::from_document(&doc).unwrap();
+    assert!(matches!(
+        frozen.persistence(),
+        CredentialPersistence::ProcessOnly
+    ));
+    // reads work
+    let e1 = frozen.build("s1", "u1", None).unwrap();
+    assert_eq!(e1.get_password().unwrap(), "pw1");
+    assert!(e1.get_attributes().unwrap().contains_key("uuid"));
+    let wrapper = e1.get_credential().unwrap();
+    assert_eq!(wrapper.get_password().unwrap(), "pw1");
+    assert_eq!(frozen.search(&HashMap::new()).unwrap().len(), 3);
+    // writes fail, even on wrappers
+    assert!(matches!(
+        e1.set_password("new"),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    assert!(matches!(
+        wrapper.update_attributes(&HashMap::from([("comment", "new")])),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    assert!(matches!(
+        wrapper.delete_credential(),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    assert!(matches!(
+        frozen.build("s3", "u3", Some(&mods)),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    assert_eq!(e1.get_password().unwrap(), "pw1");
+    // the entries in an ambiguity error are frozen, too
+    let e2 = frozen.build("s2", "u2", None).unwrap();
+    let Err(Error::Ambiguous(entries)) = e2.get_password() else {
+        panic!("Expected an ambiguous entry")
+    };
+    assert_eq!(entries.len(), 2);
+    for entry in entries {
+        assert_eq!(entry.get_password().unwrap(), "");
+        assert!(matches!(
+            entry.set_password("new"),
+            Err(Error::NotSupportedByStore(_))
+        ));
+    }
+    assert!(matches!(
+        FrozenStore::from_document("not a document"),
+        Err(Error::Invalid(_, _))
+    ));
+}
+
+#[test]
+fn test_constant_time_probe() {
+    let config = HashMap::from([("constant-time-probe", "true")]);
+    let sample = Store::new_with_configuration(&config).unwrap();
+    assert!(sample.options.constant_time_probe);
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    test_round_trip_no_delete("constant-time probe", &entry, "test password");
+    let wrapper = entry.get_credential().unwrap();
+    assert_eq!(wrapper.get_password().unwrap(), "test password");
+    let mods = HashMap::from([("force-create", "second")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    let Err(Error::Ambiguous(entries)) = entry.get_password() else {
+        panic!("Expected an ambiguous entry")
+    };
+    assert_eq!(entries.len(), 2);
+    for entry in entries.iter() {
+        assert!(entry.get_password().is_ok());
+    }
+    wrapper.delete_credential().unwrap();
+    assert!(matches!(wrapper.get_password(), Err(Error::NoEntry)));
+    let missing = CredKey {
+        store: sample.clone(),
+        id: CredId {
+            service: name.clone(),
+            user: name.clone(),
+        },
+        uuid: Some(Uuid::new_v4().to_string()),
+        immutable: false,
+        idempotency_key: None,
+    };
+    assert!(matches!(
+        missing.with_unique_cred(|_| ()),
+        Err(Error::NoEntry)
+    ));
+    assert_eq!(entry.get_password().unwrap(), "");
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_export_import_credential() {
+    let s1 = Store::new().unwrap();
+    let store1: Arc<CredentialStore> = s1.clone();
+    assert!(matches!(
+        s1.export_credential("s1", "u1", None),
+        Err(Error::NoEntry)
+    ));
+    store1
+        .build("s1", "u1", None)
+        .unwrap()
+        .set_password("pw1")
+        .unwrap();
+    let mods1 = HashMap::from([("force-create", "first")]);
+    let mods2 = HashMap::from([("force-create", "second")]);
+    store1.build("s2", "u2", Some(&mods1)).unwrap();
+    store1.build("s2", "u2", Some(&mods2)).unwrap();
+    assert!(matches!(
+        s1.export_credential("s2", "u2", None),
+        Err(Error::Ambiguous(_))
+    ));
+    let second = store1
+        .build("s2", "u2", None)
+        .unwrap()
+        .resolve_by_attribute("comment", "second")
+        .unwrap();
+    second.set_password("pw2").unwrap();
+    let uuid = second.get_attributes().unwrap()["uuid"].clone();
+    let doc = s1.export_credential("s2", "u2", Some(&uuid)).unwrap();
+    let s2 = Store::new().unwrap();
+    let imported = s2.import_credential(&doc).unwrap();
+    assert_eq!(imported.get_password().unwrap(), "pw2");
+    let attrs = imported.get_attributes().unwrap();
+    assert_eq!(attrs["uuid"], uuid);
+    assert_eq!(attrs["comment"], "second");
+    let store2: Arc<CredentialStore> = s2.clone();
+    let specifier = store2.build("s2", "u2", None).unwrap();
+    assert_eq!(specifier.get_password().unwrap(), "pw2");
+    assert_eq!(store2.search(&HashMap::new()).unwrap().len(), 1);
+    let doc = s1.export_credential("s1", "u1", None).unwrap();
+    s2.import_credential(&doc).unwrap();
+    assert_eq!(store2.search(&HashMap::new()).unwrap().len(), 2);
+    let two = ron::ser::to_string(&s2.creds).unwrap();
+    assert!(matches!(
+        s1.import_credential(&two),
+        Err(Error::Invalid(_, _))
+    ));
+    assert!(matches!(
+        s1.import_credential("garbage"),
+        Err(Error::Invalid(_, _))
+    ));
+}
+
+#[test]
+fn test_injected_clock() {
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    struct TestClock(Mutex<SystemTime>);
+
+    impl Clock for TestClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn creation_date(entry: &Entry) -> SystemTime {
+        let date = entry.get_attributes().unwrap()["creation-date"].clone();
+        chrono::DateTime::parse_from_rfc2822(&date).unwrap().into()
+    }
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = Arc::new(TestClock(Mutex::new(start)));
+    let store: Arc<CredentialStore> = Store::new().unwrap().with_clock(clock.clone());
+    let mods = HashMap::from([("force-create", "first")]);
+    let e1 = store.build("s1", "u1", Some(&mods)).unwrap();
+    assert_eq!(creation_date(&e1), start);
+    let later = start + Duration::from_secs(30 * 24 * 60 * 60);
+    *clock.0.lock().unwrap() = later;
+    let e2 = store.build("s2", "u2", Some(&mods)).unwrap();
+    assert_eq!(creation_date(&e2), later);
+    assert_eq!(creation_date(&e1), start);
+}
+
+#[test]
+fn test_secret_age() {
+    use std::time::{Duration, SystemTime};
+
+    struct FixedClock(SystemTime);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> SystemTime {
+            self.0
+        }
+    }
+
+    let thirty_days = Duration::from_secs(30 * 24 * 60 * 60);
+    let clock = Arc::new(FixedClock(SystemTime::now() - thirty_days));
+    let store: Arc<CredentialStore> = Store::new().unwrap().with_clock(clock);
+    let name = generate_random_string();
+    let mods = HashMap::from([("force-create", "old")]);
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    let age = entry.secret_age().unwrap().unwrap();
+    assert!(age >= thirty_days, "age is too small: {age:?}");
+    assert!(
+        age < thirty_days + Duration::from_secs(60),
+        "age is too big: {age:?}"
+    );
+    let other = store.build(&name, "other", None).unwrap();
+    assert!(matches!(other.secret_age(), Err(Error::NoEntry)));
+    other.set_password("modified").unwrap();
+    let age = other.secret_age().unwrap().unwrap();
+    assert!(age >= thirty_days, "age is too small: {age:?}");
+}
+
+#[test]
+fn test_update_attributes_many() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let mut entries = vec![];
+    for user in ["user1", "user2", "user3"] {
+        let entry = store.build(&name, user, None).unwrap();
+        entry.set_password(user).unwrap();
+        entries.push(entry);
+    }
+    let deleted = entries[1].get_credential().unwrap();
+    deleted.delete_credential().unwrap();
+    entries.push(deleted);
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    let entry = mock.build(&name, "user4", None).unwrap();
+    entry.set_password("user4").unwrap();
+    entries.push(entry);
+    let tag = HashMap::from([("comment", "tagged")]);
+    let results = crate::update_attributes_many(&entries, &tag);
+    assert_eq!(results.len(), entries.len());
+    for (i, (entry, result)) in results.iter().enumerate() {
+        assert_eq!(entry.get_specifiers(), entries[i].get_specifiers());
+        match i {
+            0 | 2 => {
+                result.as_ref().unwrap();
+                assert_eq!(entry.get_attributes().unwrap()["comment"], "tagged");
+            }
+            1 => assert!(matches!(result, Err(Error::NoEntry))),
+            3 => assert!(matches!(result, Err(Error::NoEntry))),
+            4 => assert!(matches!(result, Err(Error::NotSupportedByStore(_)))),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_find_duplicate_secrets() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    for (user, password) in [
+        ("user1", "reused"),
+        ("user2", "unique"),
+        ("user3", "reused"),
+    ] {
+        store
+            .build(&name, user, None)
+            .unwrap()
+            .set_password(password)
+            .unwrap();
+    }
+    let mods = HashMap::from([("force-create", "empty")]);
+    store.build(&name, "user4", Some(&mods)).unwrap();
+    store.build(&name, "user5", Some(&mods)).unwrap();
+    let groups = sample.find_duplicate_secrets().unwrap();
+    assert_eq!(groups.len(), 1);
+    let mut users: Vec<String> = groups[0]
+        .iter()
+        .map(|entry| entry.get_specifiers().unwrap().1)
+        .collect();
+    users.sort();
+    assert_eq!(users, vec!["user1", "user3"]);
+    assert!(groups[0].iter().all(|e| e.get_credential().is_ok()));
+}
+
+#[test]
+fn test_checksum_is_canonical() {
+    use super::store::CredMap;
+
+    let values = [
+        ("s1", "u1", "uuid-1", "pw1"),
+        ("s1", "u1", "uuid-2", "pw2"),
+        ("s2", "u2", "uuid-3", "pw3"),
+    ];
+    let forward = CredMap::new();
+    for (service, user, uuid, password) in values {
+        let id = CredId {
+            service: service.to_string(),
+            user: user.to_string(),
+        };
+        let value = CredValue::new(password.as_bytes());
+        forward
+            .entry(id)
+            .or_default()
+            .insert(uuid.to_string(), value);
+    }
+    let backward = CredMap::new();
+    for (service, user, uuid, password) in values.into_iter().rev() {
+        let id = CredId {
+            service: service.to_string(),
+            user: user.to_string(),
+        };
+        let value = CredValue::new(password.as_bytes());
+        backward
+            .entry(id)
+            .or_default()
+            .insert(uuid.to_string(), value);
+    }
+    let s1 = Store::new_internal(forward, None);
+    let s2 = Store::new_internal(backward, None);
+    assert_eq!(s1.checksum().unwrap(), s2.checksum().unwrap());
+    assert_ne!(
+        s1.checksum().unwrap(),
+        Store::new().unwrap().checksum().unwrap()
+    );
+    let store: Arc<CredentialStore> = s2.clone();
+    store
+        .build("s2", "u2", None)
+        .unwrap()
+        .set_password("changed")
+        .unwrap();
+    assert_ne!(s1.checksum().unwrap(), s2.checksum().unwrap());
+}
+
+#[test]
+fn test_checksum_detects_tampering() {
+    let path = std::env::temp_dir()
+        .join(format!(
+            "store-checksum-test-{}.ron",
+            generate_random_string()
+        ))
+        .to_str()
+        .unwrap()
+        .to_string();
+    _ = std::fs::remove_file(&path);
+    let config = HashMap::from([("backing-file", path.as_str()), ("checksum", "true")]);
+    {
+        let s1 = Store::new_with_configuration(&config).unwrap();
+        let store: Arc<CredentialStore> = s1.clone();
+        let entry = store.build("checksum-service", "u1", None).unwrap();
+        entry.set_password("pw1").unwrap();
+        s1.save().unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("// sha256: "));
+        let s2 = Store::new_internal(Store::load_credentials(&path).unwrap(), None);
+        assert_eq!(s2.checksum().unwrap(), s1.checksum().unwrap());
+    }
+    let s3 = Store::new_with_configuration(&config).unwrap();
+    assert_eq!(s3.creds.len(), 1);
+    drop(s3);
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(
+        &path,
+        content.replace("checksum-service", "tampered-service"),
+    )
+    .unwrap();
+    assert!(matches!(
+        Store::load_credentials(&path),
+        Err(Error::BadDataFormat(_, _))
+    ));
+    let content = std::fs::read_to_string(&path).unwrap();
+    let unchecked = content.split_once('\n').unwrap().1;
+    std::fs::write(&path, unchecked).unwrap();
+    // a store that doesn't ask for a checksum can read the file,
+    // but one that does won't accept it without one
+    assert_eq!(Store::load_credentials(&path).unwrap().len(), 1);
+    assert!(matches!(
+        Store::new_with_configuration(&config),
+        Err(Error::BadDataFormat(_, _))
+    ));
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[test]
+fn test_missing_backing_dir() {
+    let dir = std::env::temp_dir().join(format!("store-dir-test-{}", generate_random_string()));
+    let path = dir.join("nested").join("store.ron");
+    let path = path.to_str().unwrap();
+    match Store::new_with_backing(path) {
+        Err(Error::Invalid(key, reason)) => {
+            assert_eq!(key, "backing-file");
+            assert!(
+                reason.contains("nested"),
+                "reason doesn't name dir: {reason}"
+            );
+        }
+        other => panic!("Missing dir gave wrong result: {other:?}"),
+    }
+    let config = HashMap::from([("backing-file", path)]);
+    assert!(matches!(
+        Store::new_with_configuration(&config),
+        Err(Error::Invalid(_, _))
+    ));
+    assert!(!dir.exists());
+    let config = HashMap::from([("backing-file", path), ("create-dirs", "true")]);
+    let store = Store::new_with_configuration(&config).unwrap();
+    assert!(dir.join("nested").is_dir());
+    store.save().unwrap();
+    assert!(std::path::Path::new(path).is_file());
+    drop(store);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_export_import_stream() {
+    let source = Store::new().unwrap();
+    let store: Arc<CredentialStore> = source.clone();
+    for i in 0..500 {
+        let entry = store.build(&format!("service-{i}"), "user", None).unwrap();
+        let secret: Vec<u8> = (0..32).map(|_| fastrand::u8(..)).collect();
+        entry.set_secret(&secret).unwrap();
+    }
+    let mods = HashMap::from([("force-create", "with a comment")]);
+    store.build("service-0", "user", Some(&mods)).unwrap();
+    let formats = [
+        #[cfg(feature = "sample-json")]
+        ExportFormat::JsonLines,
+        ExportFormat::RonLines,
+    ];
+    for format in formats {
+        let mut buffer: Vec<u8> = Vec::new();
+        source.export_to(&mut buffer, format).unwrap();
+        assert_eq!(buffer.iter().filter(|&&b| b == b'\n').count(), 501);
+        let target = Store::new().unwrap();
+        assert_eq!(target.import_from(buffer.as_slice(), format).unwrap(), 501);
+        assert_eq!(target.checksum().unwrap(), source.checksum().unwrap());
+    }
+}
+
+#[cfg(feature = "sample-json")]
+#[test]
+fn test_import_stream_bad_line() {
+    let source = Store::new().unwrap();
+    let store: Arc<CredentialStore> = source.clone();
+    store
+        .build("service", "user", None)
+        .unwrap()
+        .set_password("pw")
+        .unwrap();
+    let mut buffer: Vec<u8> = Vec::new();
+    source
+        .export_to(&mut buffer, ExportFormat::JsonLines)
+        .unwrap();
+    buffer.extend_from_slice(b"\nnot a credential\n");
+    let target = Store::new().unwrap();
+    match target.import_from(buffer.as_slice(), ExportFormat::JsonLines) {
+        Err(Error::Invalid(line, _)) => assert_eq!(line, "line 3"),
+        other => panic!("Bad line gave wrong result: {other:?}"),
+    }
+    assert_eq!(target.creds.len(), 1);
+}
+
+#[cfg(feature = "contention-stats")]
+#[test]
+fn test_contention_stats() {
+    use std::time::Duration;
+
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    entry.set_password("initial").unwrap();
+    let stats = sample.contention_stats();
+    assert_eq!(stats.contended, 0);
+    assert_eq!(stats.average_wait(), Duration::ZERO);
+    // one thread holds the credential's lock while the others write
+    let wrappers: Vec<Entry> = (0..4).map(|_| entry.get_credential().unwrap()).collect();
+    let holder = store.build(&name, &name, None).unwrap();
+    let hold = std::thread::spawn(move || {
+        holder
+            .with_secret(|_| std::thread::sleep(Duration::from_millis(200)))
+            .unwrap()
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    let mut writers = vec![];
+    for (i, entry) in wrappers.into_iter().enumerate() {
+        writers.push(std::thread::spawn(move || {
+            entry.set_password(&format!("writer {i}")).unwrap()
+        }));
+    }
+    hold.join().unwrap();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let stats = sample.contention_stats();
+    assert!(stats.contended >= 1, "no contention recorded: {stats:?}");
+    assert!(stats.acquisitions >= stats.contended + 2);
+    assert!(stats.max_wait > Duration::ZERO);
+    assert!(stats.average_wait() > Duration::ZERO);
+    assert!(stats.average_wait() <= stats.max_wait);
+}
+
+#[test]
+fn test_immutable_credential() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let mods = HashMap::from([("immutable", "true")]);
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    entry.set_password("write once").unwrap();
+    assert!(matches!(
+        entry.set_password("write twice"),
+        Err(Error::Immutable)
+    ));
+    let other = store.build(&name, &name, None).unwrap();
+    assert!(matches!(
+        other.set_password("another"),
+        Err(Error::Immutable)
+    ));
+    assert!(matches!(
+        other.update_attributes(&HashMap::from([("comment", "changed")])),
+        Err(Error::Immutable)
+    ));
+    assert!(matches!(
+        other.increment_secret_u64(1),
+        Err(Error::Immutable)
+    ));
+    assert!(!other.set_password_if_changed("write once").unwrap());
+    assert_eq!(other.get_password().unwrap(), "write once");
+    other.delete_credential().unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    entry.set_password("a new credential").unwrap();
+    assert!(matches!(entry.set_password("again"), Err(Error::Immutable)));
+    entry.delete_credential().unwrap();
+    let mods = HashMap::from([("immutable", "maybe")]);
+    assert!(matches!(
+        store.build(&name, &name, Some(&mods)),
+        Err(Error::Invalid(_, _))
+    ));
+}
+
+#[test]
+fn test_immutable_force_created_credential() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let mods = HashMap::from([("immutable", "true"), ("force-create", "signing key")]);
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    assert!(entry.has_empty_secret().unwrap());
+    entry
+        .update_attributes(&HashMap::from([("comment", "still mutable")]))
+        .unwrap();
+    entry.set_secret(&[1, 2, 3]).unwrap();
+    assert!(matches!(entry.set_secret(&[4]), Err(Error::Immutable)));
+    let wrapper = entry.get_credential().unwrap();
+    assert!(matches!(wrapper.set_secret(&[4]), Err(Error::Immutable)));
+    wrapper.delete_credential().unwrap();
+}
+
+#[test]
+fn test_immutable_persists() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let mods = HashMap::from([("immutable", "true")]);
+    let entry = store.build("immutable", "user", Some(&mods)).unwrap();
+    entry.set_password("fixed").unwrap();
+    let doc = sample.export_credential("immutable", "user", None).unwrap();
+    let copy = Store::new().unwrap();
+    copy.import_credential(&doc).unwrap();
+    let copy: Arc<CredentialStore> = copy;
+    let entry = copy.build("immutable", "user", None).unwrap();
+    assert!(matches!(
+        entry.set_password("changed"),
+        Err(Error::Immutable)
+    ));
+}
+
+#[test]
+fn test_idempotency_key() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    let spec = HashMap::from([("service", name.as_str())]);
+    let mods = HashMap::from([("idempotency-key", "request-1")]);
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    entry.set_password("first try").unwrap();
+    let retry = store.build(&name, &name, Some(&mods)).unwrap();
+    retry.set_password("second try").unwrap();
+    assert_eq!(store.search(&spec).unwrap().len(), 1);
+    assert_eq!(entry.get_password().unwrap(), "first try");
+    let attrs = entry.get_attributes().unwrap();
+    assert_eq!(attrs["idempotency-key"], "request-1");
+    assert!(
+        entry
+            .attribute_keys()
+            .unwrap()
+            .contains(&"idempotency-key".to_string())
+    );
+    let other = HashMap::from([("idempotency-key", "request-2")]);
+    let entry = store.build(&name, &name, Some(&other)).unwrap();
+    entry.set_password("new request").unwrap();
+    assert_eq!(store.search(&spec).unwrap().len(), 1);
+    assert_eq!(entry.get_password().unwrap(), "new request");
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_idempotency_key_with_force_create() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let spec = HashMap::from([("service", name.as_str())]);
+    store
+        .build(&name, &name, None)
+        .unwrap()
+        .set_password("existing")
+        .unwrap();
+    let mods = HashMap::from([("force-create", "new"), ("idempotency-key", "create-1")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    store.build(&name, &name, Some(&mods)).unwrap();
+    assert_eq!(store.search(&spec).unwrap().len(), 2);
+    let created = store
+        .build(&name, &name, None)
+        .unwrap()
+        .resolve_by_attribute("idempotency-key", "create-1")
+        .unwrap();
+    assert!(created.has_empty_secret().unwrap());
+    // writes through the ambiguous specifier are no-ops, not errors
+    let entry = store.build(&name, &name, Some(&mods)).unwrap();
+    entry.set_password("ignored").unwrap();
+    assert!(created.has_empty_secret().unwrap());
+    for entry in store.search(&spec).unwrap() {
+        entry.delete_credential().unwrap();
+    }
+}
+
+#[test]
+fn test_primary_credential() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.primary_credential(), Err(Error::NoEntry)));
+    entry.set_password("first").unwrap();
+    let first = entry.get_credential().unwrap();
+    for comment in ["second", "third", "fourth"] {
+        let mods = HashMap::from([("force-create", comment)]);
+        store.build(&name, &name, Some(&mods)).unwrap();
+    }
+    assert!(matches!(entry.get_password(), Err(Error::Ambiguous(_))));
+    let wrappers = store
+        .search(&HashMap::from([("service", name.as_str())]))
+        .unwrap();
+    assert_eq!(wrappers.len(), 4);
+    for wrapper in wrappers.iter().chain([&entry]) {
+        let primary = wrapper.primary_credential().unwrap();
+        assert_eq!(primary.get_password().unwrap(), "first");
+        assert_eq!(
+            primary.get_attributes().unwrap()["uuid"],
+            first.get_attributes().unwrap()["uuid"]
+        );
+    }
+    first.delete_credential().unwrap();
+    let primary = entry.primary_credential().unwrap();
+    assert_eq!(primary.get_attributes().unwrap()["comment"], "second");
+    for wrapper in wrappers.iter().skip(1) {
+        _ = wrapper.delete_credential();
+    }
+}
+
+#[test]
+fn test_primary_credential_survives_save() {
+    let path = std::env::temp_dir()
+        .join("keyring-sample-store-primary.ron")
+        .to_str()
+        .unwrap()
+        .to_string();
+    _ = std::fs::remove_file(&path);
+    let name = generate_random_string();
+    {
+        let store: Arc<CredentialStore> = Store::new_with_backing(&path).unwrap();
+        store
+            .build(&name, &name, None)
+            .unwrap()
+            .set_password("first")
+            .unwrap();
+        for comment in ["second", "third", "fourth"] {
+            let mods = HashMap::from([("force-create", comment)]);
+            store.build(&name, &name, Some(&mods)).unwrap();
+        }
+    }
+    {
+        let store: Arc<CredentialStore> = Store::new_with_backing(&path).unwrap();
+        let entry = store.build(&name, &name, None).unwrap();
+        let primary = entry.primary_credential().unwrap();
+        assert_eq!(primary.get_password().unwrap(), "first");
+    }
+    _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_verify_many() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let right = store.build(&name, "right", None).unwrap();
+    right.set_password("secret").unwrap();
+    let wrong = store.build(&name, "wrong", None).unwrap();
+    wrong.set_password("secret").unwrap();
+    let missing = store.build(&name, "missing", None).unwrap();
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    let other = mock.build(&name, "other", None).unwrap();
+    other.set_secret(b"other secret").unwrap();
+    let pairs: [(&Entry, &[u8]); 5] = [
+        (&right, b"secret"),
+        (&wrong, b"secreT"),
+        (&missing, b"secret"),
+        (&other, b"other secret"),
+        (&right, b"secret but longer"),
+    ];
+    let results = crate::verify_many(&pairs);
+    assert_eq!(results.len(), pairs.len());
+    for (i, (entry, result)) in results.iter().enumerate() {
+        assert_eq!(entry.get_specifiers(), pairs[i].0.get_specifiers());
+        match i {
+            0 | 3 => assert!(result.as_ref().unwrap()),
+            1 | 4 => assert!(!result.as_ref().unwrap()),
+            2 => assert!(matches!(result, Err(Error::NoEntry))),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_compare_and_set() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(!entry.compare_and_set(Some(b"old"), None).unwrap());
+    assert!(entry.compare_and_set(None, Some(b"old")).unwrap());
+    assert!(!entry.compare_and_set(None, Some(b"other")).unwrap());
+    let wrapper = entry.get_credential().unwrap();
+    assert!(
+        !wrapper
+            .compare_and_set(Some(b"other"), Some(b"new"))
+            .unwrap()
+    );
+    assert!(wrapper.compare_and_set(Some(b"old"), Some(b"new")).unwrap());
+    assert!(!entry.compare_and_set(Some(b"old"), None).unwrap());
+    assert!(entry.compare_and_set(Some(b"new"), None).unwrap());
+    assert!(matches!(entry.get_secret(), Err(Error::NoEntry)));
+    assert!(wrapper.compare_and_set(None, None).unwrap());
+    assert!(matches!(
+        wrapper.compare_and_set(None, Some(b"recreated")),
+        Err(Error::NoEntry)
+    ));
+    entry.set_password("first").unwrap();
+    let mods = HashMap::from([("force-create", "second")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    assert!(matches!(
+        entry.compare_and_set(Some(b"first"), Some(b"new")),
+        Err(Error::Ambiguous(_))
+    ));
+    assert!(!entry.compare_and_set(None, Some(b"new")).unwrap());
+    let immutable = HashMap::from([("immutable", "true")]);
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, Some(&immutable)).unwrap();
+    entry.set_password("fixed").unwrap();
+    assert!(matches!(
+        entry.compare_and_set(Some(b"fixed"), Some(b"changed")),
+        Err(Error::Immutable)
+    ));
+    // like delete_credential, a compare-and-set can delete it
+    assert!(!entry.compare_and_set(Some(b"changed"), None).unwrap());
+    assert!(entry.compare_and_set(Some(b"fixed"), None).unwrap());
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+}
+
+#[test]
+fn test_update_with() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    let append = |current: Option<Vec<u8>>| {
+        let mut value = current.unwrap_or_default();
+        value.push(b'x');
+        Some(value)
+    };
+    entry.update_with(append, 1).unwrap();
+    entry.update_with(append, 1).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "xx");
+    entry.update_with(|_| None, 1).unwrap();
+    assert!(matches!(entry.get_secret(), Err(Error::NoEntry)));
+    // a writer that always intervenes makes every attempt fail
+    let other = store.build(&name, &name, None).unwrap();
+    let result = entry.update_with(
+        |current| {
+            other.set_password(&generate_random_string()).unwrap();
+            current
+        },
+        3,
+    );
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(
+        entry.update_with(append, 0),
+        Err(Error::Timeout(_))
+    ));
+}
+
+#[test]
+fn test_update_with_contended() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    entry.set_secret(&0u64.to_be_bytes()).unwrap();
+    let mut handles = vec![];
+    for _ in 0..2 {
+        let entry = store.build(&name, &name, None).unwrap();
+        handles.push(std::thread::spawn(move || {
+            for _ in 0..100 {
+                let increment = |current: Option<Vec<u8>>| {
+                    let count = crate::error::decode_counter(&current.unwrap()).unwrap();
+                    // give the other thread a chance to intervene
+                    std::thread::yield_now();
+                    Some((count + 1).to_be_bytes().to_vec())
+                };
+                entry.update_with(increment, usize::MAX).unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let count = crate::error::decode_counter(&entry.get_secret().unwrap()).unwrap();
+    assert_eq!(count, 200);
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_empty_spec_policy() {
+    fn populated(policy: Option<&str>) -> Arc<CredentialStore> {
+        let config = match policy {
+            Some(policy) => HashMap::from([("empty-spec-policy", policy)]),
+            None => HashMap::new(),
+        };
+        let store: Arc<CredentialStore> = Store::new_with_configuration(&config).unwrap();
+        for user in ["user1", "user2", "user3"] {
+            let entry = store.build("service", user, None).unwrap();
+            entry.set_password(user).unwrap();
+        }
+        store
+    }
+    let everything = HashMap::new();
+    let some = HashMap::from([("user", "user[12]")]);
+    for policy in [None, Some("all")] {
+        let store = populated(policy);
+        assert_eq!(store.search(&everything).unwrap().len(), 3);
+        assert_eq!(store.search(&some).unwrap().len(), 2);
+    }
+    let store = populated(Some("none"));
+    assert!(store.search(&everything).unwrap().is_empty());
+    assert_eq!(store.search(&some).unwrap().len(), 2);
+    let store = populated(Some("error"));
+    assert!(matches!(
+        store.search(&everything),
+        Err(Error::Invalid(key, _)) if key == "spec"
+    ));
+    assert_eq!(store.search(&some).unwrap().len(), 2);
+    assert!(matches!(
+        Store::new_with_configuration(&HashMap::from([("empty-spec-policy", "some")])),
+        Err(Error::Invalid(key, _)) if key == "empty-spec-policy"
+    ));
+}
+
+#[test]
+fn test_search_multi() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    for user in ["alice", "bob", "carol"] {
+        let entry = store.build(&name, user, None).unwrap();
+        entry.set_password(user).unwrap();
+    }
+    let mods = HashMap::from([("force-create", "second bob")]);
+    store.build(&name, "bob", Some(&mods)).unwrap();
+    let specs = vec![
+        HashMap::from([("service", name.as_str())]),
+        HashMap::from([("service", name.as_str()), ("user", "^bob$")]),
+        HashMap::from([("service", name.as_str()), ("user", "o")]),
+        HashMap::from([("service", name.as_str()), ("user", "nobody")]),
+    ];
+    let multi = store.search_multi(&specs).unwrap();
+    assert_eq!(multi.len(), specs.len());
+    fn uuids(entries: &[Entry]) -> Vec<String> {
+        let mut uuids: Vec<String> = entries
+            .iter()
+            .map(|e| e.get_attributes().unwrap()["uuid"].clone())
+            .collect();
+        uuids.sort();
+        uuids
+    }
+    for (spec, found) in specs.iter().zip(multi.iter()) {
+        assert_eq!(uuids(found), uuids(&store.search(spec).unwrap()));
+    }
+    assert_eq!(
+        multi.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![4, 2, 3, 0]
+    );
+    let bad = vec![specs[0].clone(), HashMap::from([("user", "(")])];
+    assert!(matches!(
+        store.search_multi(&bad),
+        Err(Error::Invalid(_, _))
+    ));
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    mock.build(&name, "alice", None)
+        .unwrap()
+        .set_password("alice")
+        .unwrap();
+    assert_eq!(mock.search_multi(&specs[..2]).unwrap().len(), 2);
+}
+
+#[test]
+fn test_record_and_replay() {
+    use crate::recording::{RecordingStore, ReplayStore};
+
+    fn session(store: &Arc<CredentialStore>, name: &str) -> Vec<String> {
+        let mut results = vec![];
+        let entry = store.build(name, "user", None).unwrap();
+        results.push(format!("{:?}", entry.get_password()));
+        entry.set_password("first").unwrap();
+        results.push(format!("{:?}", entry.get_password()));
+        entry
+            .update_attributes(&HashMap::from([("comment", "recorded")]))
+            .unwrap();
+        let attrs = entry.get_attributes().unwrap();
+        results.push(attrs["comment"].clone());
+        results.push(format!(
+            "{:?}",
+            entry.update_attributes(&HashMap::from([("uuid", "x")]))
+        ));
+        results.push(format!("{:?}", entry.set_password_if_changed("first")));
+        entry.delete_credential().unwrap();
+        results.push(format!("{:?}", entry.delete_credential()));
+        results
+    }
+    let sample: Arc<CredentialStore> = Store::new().unwrap();
+    let recorder: Arc<CredentialStore> = RecordingStore::new(sample.clone()).unwrap();
+    let name = generate_random_string();
+    let recorded = session(&recorder, &name);
+    let recording: &RecordingStore = recorder.as_any().downcast_ref().unwrap();
+    let transcript = recording.transcript();
+    #[cfg(feature = "serde")]
+    let transcript = serde_json::from_str(&serde_json::to_string(&transcript).unwrap()).unwrap();
+    sample
+        .build(&name, "user", None)
+        .unwrap()
+        .set_password("not from the replay")
+        .unwrap();
+    let replay: Arc<CredentialStore> = ReplayStore::from_transcript(transcript).unwrap();
+    assert_eq!(session(&replay, &name), recorded);
+    let entry = sample.build(&name, "user", None).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "not from the replay");
+}
+
+#[test]
+fn test_entry_descriptor() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let specifier = store.build("service", "user", None).unwrap();
+    specifier.set_password("first").unwrap();
+    let d = specifier.descriptor().unwrap();
+    assert_eq!(d.vendor, store.vendor());
+    assert_eq!(d.store_id, store.id());
+    assert_eq!(d.uuid, None);
+    let mods = HashMap::from([("force-create", "second")]);
+    store.build("service", "user", Some(&mods)).unwrap();
+    let Err(Error::Ambiguous(wrappers)) = specifier.get_password() else {
+        panic!("expected an ambiguous entry");
+    };
+    let d = wrappers[1].descriptor().unwrap();
+    assert!(d.uuid.is_some());
+    #[cfg(feature = "serde")]
+    let d: crate::EntryDescriptor =
+        serde_json::from_str(&serde_json::to_string(&d).unwrap()).unwrap();
+    let found = Entry::from_descriptor(&store, &d).unwrap();
+    assert!(found == wrappers[1]);
+    assert!(found.is_wrapper());
+    let found = Entry::from_descriptor(&store, &specifier.descriptor().unwrap()).unwrap();
+    assert!(found.is_specifier());
+    wrappers[1].delete_credential().unwrap();
+    assert!(matches!(
+        Entry::from_descriptor(&store, &d),
+        Err(Error::NoEntry)
+    ));
+    let mock: Arc<CredentialStore> = crate::mock::Store::new().unwrap();
+    assert!(matches!(
+        Entry::from_descriptor(&mock, &d),
+        Err(Error::Invalid(key, _)) if key == "vendor"
+    ));
+}
+
+#[test]
+fn test_attribute_schema() {
+    use super::schema::{AttributeRule, AttributeSchema, AttributeValidator};
+
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    entry.set_password("password").unwrap();
+    let rule = |required, validator| AttributeRule {
+        required,
+        validator,
+    };
+    let schema = AttributeSchema {
+        attributes: HashMap::from([(
+            "comment".to_string(),
+            rule(
+                false,
+                AttributeValidator::Regex(regex::Regex::new("^ticket-[0-9]+$").unwrap()),
+            ),
+        )]),
+    };
+    sample.set_attribute_schema(schema).unwrap();
+    entry
+        .update_attributes(&HashMap::from([("comment", "ticket-42")]))
+        .unwrap();
+    assert_eq!(entry.get_attributes().unwrap()["comment"], "ticket-42");
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("comment", "no ticket")])),
+        Err(Error::Invalid(key, _)) if key == "comment"
+    ));
+    assert_eq!(entry.get_attributes().unwrap()["comment"], "ticket-42");
+    // a schema that allows no attributes disallows every key
+    sample
+        .set_attribute_schema(AttributeSchema::default())
+        .unwrap();
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("comment", "ticket-43")])),
+        Err(Error::Invalid(key, _)) if key == "comment"
+    ));
+    let one_of = AttributeValidator::OneOf(vec!["red".to_string(), "green".to_string()]);
+    let schema = AttributeSchema {
+        attributes: HashMap::from([("comment".to_string(), rule(true, one_of))]),
+    };
+    sample.set_attribute_schema(schema).unwrap();
+    entry
+        .update_attributes(&HashMap::from([("comment", "green")]))
+        .unwrap();
+    // the credential already has a comment, so an empty update is fine
+    entry.update_attributes(&HashMap::new()).unwrap();
+    let other = store.build(&name, "other", None).unwrap();
+    other.set_password("password").unwrap();
+    assert!(matches!(
+        other.update_attributes(&HashMap::new()),
+        Err(Error::Invalid(key, _)) if key == "comment"
+    ));
+    for reserved in ["uuid", "creation-date", "modified-date", "idempotency-key"] {
+        let schema = AttributeSchema {
+            attributes: HashMap::from([(
+                reserved.to_string(),
+                rule(false, AttributeValidator::Any),
+            )]),
+        };
+        assert!(matches!(
+            sample.set_attribute_schema(schema),
+            Err(Error::Invalid(key, _)) if key == reserved
+        ));
+    }
+    sample.clear_attribute_schema();
+    other
+        .update_attributes(&HashMap::from([("comment", "anything")]))
+        .unwrap();
+}
+
+#[test]
+fn test_case_insensitive_specifiers() {
+    let config = HashMap::from([("case-insensitive-specifiers", "true")]);
+    let store: Arc<CredentialStore> = Store::new_with_configuration(&config).unwrap();
+    let upper = store.build("Foo", "Bar", None).unwrap();
+    upper.set_password("shared").unwrap();
+    let lower = store.build("foo", "bar", None).unwrap();
+    assert_eq!(lower.get_password().unwrap(), "shared");
+    assert_eq!(
+        upper.get_specifiers(),
+        Some(("foo".to_string(), "bar".to_string()))
+    );
+    let found = store
+        .search(&HashMap::from([("service", "^FOO$"), ("user", "Bar")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    lower.delete_credential().unwrap();
+    assert!(matches!(upper.get_password(), Err(Error::NoEntry)));
+
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let upper = store.build("Foo", "Bar", None).unwrap();
+    upper.set_password("upper").unwrap();
+    let lower = store.build("foo", "bar", None).unwrap();
+    assert!(matches!(lower.get_password(), Err(Error::NoEntry)));
+    lower.set_password("lower").unwrap();
+    assert_eq!(upper.get_password().unwrap(), "upper");
+    let found = store
+        .search(&HashMap::from([("service", "^foo$")]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+}
+
+#[test]
+fn test_specifier_and_wrapper() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let specifier = store.build(&name, &name, None).unwrap();
+    assert!(specifier.is_specifier());
+    assert!(!specifier.is_wrapper());
+    specifier.set_password("first").unwrap();
+    let wrapper = specifier.get_credential().unwrap();
+    assert!(!wrapper.is_specifier());
+    assert!(wrapper.is_wrapper());
+    let mods = HashMap::from([("force-create", "second")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    match specifier.get_password() {
+        Err(Error::Ambiguous(entries)) => {
+            assert_eq!(entries.len(), 2);
+            assert!(entries.iter().all(|e| e.is_wrapper() && !e.is_specifier()));
+        }
+        other => panic!("expected an ambiguous error, got {other:?}"),
+    }
+    let frozen = FrozenStore::from_document("{}").unwrap();
+    let frozen: Arc<CredentialStore> = frozen;
+    let entry = frozen.build(&name, &name, None).unwrap();
+    assert!(entry.is_specifier());
+    assert!(!entry.is_wrapper());
+}
+
+#[test]
+fn test_rename_service() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let alice = store.build("old", "alice", None).unwrap();
+    alice.set_password("alice").unwrap();
+    alice
+        .update_attributes(&HashMap::from([("comment", "kept")]))
+        .unwrap();
+    let uuid = alice.get_attributes().unwrap()["uuid"].clone();
+    let mods = HashMap::from([("force-create", "second")]);
+    store
+        .build("old", "bob", None)
+        .unwrap()
+        .set_password("bob")
+        .unwrap();
+    store.build("old", "bob", Some(&mods)).unwrap();
+    store
+        .build("other", "alice", None)
+        .unwrap()
+        .set_password("other")
+        .unwrap();
+    assert_eq!(
+        sample
+            .rename_service("old", "new", ConflictPolicy::Error)
+            .unwrap(),
+        3
+    );
+    assert!(matches!(alice.get_password(), Err(Error::NoEntry)));
+    let moved = store.build("new", "alice", None).unwrap();
+    assert_eq!(moved.get_password().unwrap(), "alice");
+    let attrs = moved.get_attributes().unwrap();
+    assert_eq!(attrs["uuid"], uuid);
+    assert_eq!(attrs["comment"], "kept");
+    let bob = store.build("new", "bob", None).unwrap();
+    assert!(matches!(bob.get_password(), Err(Error::Ambiguous(_))));
+    let found = store
+        .search(&HashMap::from([("service", "^old$")]))
+        .unwrap();
+    assert!(found.is_empty());
+    let other = store.build("other", "alice", None).unwrap();
+    assert_eq!(other.get_password().unwrap(), "other");
+    assert_eq!(
+        sample
+            .rename_service("new", "new", ConflictPolicy::Overwrite)
+            .unwrap(),
+        0
+    );
+}
+
+#[test]
+fn test_rename_service_conflicts() {
+    let setup = || {
+        let sample = Store::new().unwrap();
+        let store: Arc<CredentialStore> = sample.clone();
+        for (service, user) in [("old", "alice"), ("old", "bob"), ("new", "alice")] {
+            let entry = store.build(service, user, None).unwrap();
+            entry.set_password(&format!("{service} {user}")).unwrap();
+        }
+        (sample, store)
+    };
+    let password = |store: &Arc<CredentialStore>, service: &str, user: &str| {
+        store.build(service, user, None).unwrap().get_password()
+    };
+
+    let (sample, store) = setup();
+    assert_eq!(
+        sample
+            .rename_service("old", "new", ConflictPolicy::Skip)
+            .unwrap(),
+        1
+    );
+    assert_eq!(password(&store, "new", "alice").unwrap(), "new alice");
+    assert_eq!(password(&store, "old", "alice").unwrap(), "old alice");
+    assert_eq!(password(&store, "new", "bob").unwrap(), "old bob");
+
+    let (sample, store) = setup();
+    assert_eq!(
+        sample
+            .rename_service("old", "new", ConflictPolicy::Overwrite)
+            .unwrap(),
+        2
+    );
+    assert_eq!(password(&store, "new", "alice").unwrap(), "old alice");
+    assert!(matches!(
+        password(&store, "old", "alice"),
+        Err(Error::NoEntry)
+    ));
+    assert_eq!(password(&store, "new", "bob").unwrap(), "old bob");
+
+    let (sample, store) = setup();
+    match sample.rename_service("old", "new", ConflictPolicy::Error) {
+        Err(Error::Invalid(attr, _)) => assert_eq!(attr, "user alice"),
+        other => panic!("expected an invalid error, got {other:?}"),
+    }
+    assert_eq!(password(&store, "old", "alice").unwrap(), "old alice");
+    assert_eq!(password(&store, "old", "bob").unwrap(), "old bob");
+    assert_eq!(password(&store, "new", "alice").unwrap(), "new alice");
+}
+
+#[test]
+fn test_exists() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let specifier = store.build(&name, &name, None).unwrap();
+    assert!(!specifier.exists().unwrap());
+    specifier.set_password("first").unwrap();
+    assert!(specifier.exists().unwrap());
+    let wrapper = specifier.get_credential().unwrap();
+    assert!(wrapper.exists().unwrap());
+    let mods = HashMap::from([("force-create", "second")]);
+    let second = store.build(&name, &name, Some(&mods)).unwrap();
+    match specifier.exists() {
+        Err(Error::Ambiguous(entries)) => assert_eq!(entries.len(), 2),
+        other => panic!("expected an ambiguous error, got {other:?}"),
+    }
+    assert!(wrapper.exists().unwrap());
+    wrapper.delete_credential().unwrap();
+    assert!(!wrapper.exists().unwrap());
+    assert!(second.exists().unwrap());
+    assert!(specifier.exists().unwrap());
+    second.delete_credential().unwrap();
+    assert!(!specifier.exists().unwrap());
+}
+
+#[test]
+fn test_entry_vendor_and_store_id() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let entry = store.build("service", "user", None).unwrap();
+    assert_eq!(entry.vendor().unwrap(), store.vendor());
+    assert_eq!(entry.store_id().unwrap(), sample.id);
+    entry.set_password("password").unwrap();
+    let wrapper = entry.get_credential().unwrap();
+    assert_eq!(wrapper.vendor().unwrap(), store.vendor());
+    let frozen: Arc<CredentialStore> = FrozenStore::from_document("{}").unwrap();
+    let entry = frozen.build("service", "user", None).unwrap();
+    assert!(entry.vendor().is_none());
+    assert!(entry.store_id().is_none());
+}
+
+#[test]
+fn test_same_credential() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let specifier = store.build(&name, &name, None).unwrap();
+    specifier.set_password("first").unwrap();
+    assert!(specifier == store.build(&name, &name, None).unwrap());
+    let wrapper = specifier.get_credential().unwrap();
+    assert!(wrapper == specifier.get_credential().unwrap());
+    assert!(wrapper != specifier);
+    let mods = HashMap::from([("force-create", "second")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    let entries = match specifier.get_password() {
+        Err(Error::Ambiguous(entries)) => entries,
+        other => panic!("expected an ambiguous error, got {other:?}"),
+    };
+    assert!(entries[0] != entries[1]);
+    assert_eq!(entries.iter().filter(|e| **e == wrapper).count(), 1);
+    let other: Arc<CredentialStore> = Store::new().unwrap();
+    let elsewhere = other.build(&name, &name, None).unwrap();
+    elsewhere.set_password("first").unwrap();
+    assert!(elsewhere != specifier);
+}
+
+#[test]
+fn test_count() {
+    let sample = Store::new().unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    assert_eq!(store.count().unwrap(), 0);
+    assert!(sample.is_empty());
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    for _ in 0..3 {
+        store.build("service", "user", Some(&mods)).unwrap();
+    }
+    store
+        .build("service", "other", None)
+        .unwrap()
+        .set_password("other")
+        .unwrap();
+    assert_eq!(store.count().unwrap(), 4);
+    assert_eq!(sample.len(), 4);
+    assert_eq!(sample.creds.len(), 2);
+    let entry = store.build("service", "user", None).unwrap();
+    let Err(Error::Ambiguous(entries)) = entry.get_password() else {
+        panic!("expected an ambiguous entry");
+    };
+    entries[0].delete_credential().unwrap();
+    assert_eq!(store.count().unwrap(), 3);
+    let frozen: Arc<CredentialStore> = FrozenStore::from_document("{}").unwrap();
+    assert_eq!(frozen.count().unwrap(), 0);
+}
+
+#[test]
+fn test_all() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    assert!(store.all().unwrap().is_empty());
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    for _ in 0..3 {
+        store.build("service", "ambiguous", Some(&mods)).unwrap();
+    }
+    for user in ["first", "second"] {
+        let entry = store.build("service", user, None).unwrap();
+        entry.set_password(user).unwrap();
+    }
+    let all = store.all().unwrap();
+    assert_eq!(all.len(), 5);
+    assert!(all.iter().all(|entry| entry.is_wrapper()));
+    for (i, entry) in all.iter().enumerate() {
+        assert!(all[i + 1..].iter().all(|other| other != entry));
+    }
+    let first = store.build("service", "first", None).unwrap();
+    let first = first.get_credential().unwrap();
+    assert_eq!(all.iter().filter(|entry| **entry == first).count(), 1);
+}
+
+#[test]
+fn test_clear() {
+    let path = std::env::temp_dir()
+        .join(format!("store-clear-{}.ron", generate_random_string()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    let sample = Store::new_with_backing(&path).unwrap();
+    let store: Arc<CredentialStore> = sample.clone();
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    for _ in 0..2 {
+        store.build("service", "ambiguous", Some(&mods)).unwrap();
+    }
+    let entry = store.build("service", "user", None).unwrap();
+    entry.set_password("password").unwrap();
+    sample.save().unwrap();
+    assert_eq!(Store::load_credentials(&path).unwrap().len(), 2);
+    assert_eq!(store.clear().unwrap(), 3);
+    assert_eq!(store.count().unwrap(), 0);
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    assert!(Store::load_credentials(&path).unwrap().is_empty());
+    assert_eq!(store.clear().unwrap(), 0);
+    let frozen: Arc<CredentialStore> = FrozenStore::from_document("{}").unwrap();
+    assert!(matches!(frozen.clear(), Err(Error::NotSupportedByStore(_))));
+    drop(sample);
+    drop(store);
+    _ = std::fs::remove_file(&path);
+    _ = std::fs::remove_file(format!("{path}.lock"));
+}
+
+#[test]
+fn test_password_and_attributes() {
+    let store = Store::new().unwrap();
+    let entry = Entry::new_in(&(store.clone() as Arc<CredentialStore>), "service", "user").unwrap();
+    assert!(matches!(
+        entry.get_password_and_attributes(),
+        Err(Error::NoEntry)
+    ));
+    entry.set_password("0").unwrap();
+    entry
+        .update_attributes(&HashMap::from([("comment", "0")]))
+        .unwrap();
+    let (password, attrs) = entry.get_password_and_attributes().unwrap();
+    assert_eq!(password, "0");
+    assert_eq!(attrs["comment"], "0");
+    let key = CredKey {
+        store: store.clone(),
+        id: CredId {
+            service: "service".to_string(),
+            user: "user".to_string(),
+        },
+        uuid: None,
+        immutable: false,
+        idempotency_key: None,
+    };
+    let writer = std::thread::spawn(move || {
+        // change the secret and the comment together, under one lock
+        for i in 1..=1000 {
+            key.with_unique_cred(|cred| {
+                cred.secret = i.to_string().into_bytes();
+                cred.comment = Some(i.to_string());
+            })
+            .unwrap();
+        }
+    });
+    while !writer.is_finished() {
+        let (password, attrs) = entry.get_password_and_attributes().unwrap();
+        assert_eq!(password, attrs["comment"]);
+    }
+    writer.join().unwrap();
+    assert_eq!(entry.get_password().unwrap(), "1000");
+}
+
+#[test]
+fn test_set_password_reporting() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    let outcome = entry.set_password_reporting("first").unwrap();
+    assert_eq!(outcome, SetOutcome::Created);
+    let outcome = entry.set_password_reporting("second").unwrap();
+    assert_eq!(outcome, SetOutcome::Updated);
+    let wrapper = entry.get_credential().unwrap();
+    let outcome = wrapper.set_password_reporting("third").unwrap();
+    assert_eq!(outcome, SetOutcome::Updated);
+    assert_eq!(entry.get_password().unwrap(), "third");
+    entry.delete_credential().unwrap();
+    assert!(matches!(
+        wrapper.set_password_reporting("gone"),
+        Err(Error::NoEntry)
+    ));
+    let mods = HashMap::from([("force-create", "ambiguous")]);
+    store.build(&name, &name, Some(&mods)).unwrap();
+    store.build(&name, &name, Some(&mods)).unwrap();
+    assert!(matches!(
+        entry.set_password_reporting("ambiguous"),
+        Err(Error::Ambiguous(_))
+    ));
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_secret_zeroizing() {
+    let store: Arc<CredentialStore> = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    entry.set_secret(b"first").unwrap();
+    entry.set_secret(b"second").unwrap();
+    let secret = entry.get_secret_zeroizing().unwrap();
+    assert_eq!(&*secret, b"second");
+    drop(secret);
+    entry.delete_credential().unwrap();
+    assert!(matches!(entry.get_secret_zeroizing(), Err(Error::NoEntry)));
+}
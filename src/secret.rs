@@ -0,0 +1,100 @@
+/*!
+
+# Secrets that wipe themselves
+
+If the `zeroize` feature is enabled, [Entry::get_secret_zeroizing](crate::Entry::get_secret_zeroizing)
+returns a [Secret], whose bytes are overwritten with zeros when it is dropped.
+The mock and sample stores also wipe the secrets they hold when those
+secrets are replaced or deleted.
+
+Wiping is best-effort: it can't reach copies that were made
+before the secret was handed over, such as those made by
+a platform store's own APIs.
+ */
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// A secret that is wiped from memory when it's dropped.
+///
+/// The bytes can be read through [Deref](std::ops::Deref) or
+/// [as_bytes](Secret::as_bytes). The `Debug` output never shows them.
+#[cfg(feature = "zeroize")]
+pub struct Secret(Vec<u8>);
+
+#[cfg(feature = "zeroize")]
+impl Secret {
+    /// Take ownership of `bytes`, which will be wiped when the secret is dropped.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Secret(bytes)
+    }
+
+    /// The bytes of the secret.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl std::ops::Deref for Secret {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret([REDACTED; {}])", self.0.len())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Secret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Wipe secret bytes that are about to be replaced or dropped.
+///
+/// This does nothing unless the `zeroize` feature is enabled.
+#[cfg(feature = "zeroize")]
+pub(crate) fn scrub(bytes: &mut Vec<u8>) {
+    bytes.zeroize();
+}
+
+#[cfg(not(feature = "zeroize"))]
+pub(crate) fn scrub(_: &mut Vec<u8>) {}
+
+#[cfg(all(test, feature = "zeroize"))]
+mod tests {
+    use zeroize::Zeroize;
+
+    use super::{Secret, scrub};
+
+    #[test]
+    fn test_secret() {
+        let mut secret = Secret::new(b"hunter2".to_vec());
+        assert_eq!(secret.as_bytes(), b"hunter2");
+        assert_eq!(&*secret, b"hunter2");
+        assert_eq!(format!("{secret:?}"), "Secret([REDACTED; 7])");
+        secret.zeroize();
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn test_scrub() {
+        let mut bytes = b"hunter2".to_vec();
+        scrub(&mut bytes);
+        assert!(bytes.is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+/*!
+
+# Test utilities
+
+This module provides helpers for writing tests of credential stores
+and of clients that use them, such as conformance tests that copy
+credentials from one store to another.
+ */
+use std::collections::HashMap;
+
+use crate::{Entry, Result};
+
+/// Attributes that stores assign to credentials and that clients can't set.
+///
+/// These are left out when comparing credentials, because two credentials
+/// with the same content will have different values for them.
+pub const READ_ONLY_ATTRIBUTES: [&str; 3] = ["uuid", "creation-date", "modified-date"];
+
+/// Compare the content of the credentials of two entries.
+///
+/// The entries are equal if their secrets are the same and their attributes
+/// are the same, apart from the [read-only attributes](READ_ONLY_ATTRIBUTES).
+/// The entries can come from different stores.
+///
+/// # Errors
+///
+/// Any error in reading the secret or attributes of either entry is returned.
+pub fn credentials_equal(a: &Entry, b: &Entry) -> Result<bool> {
+    if a.get_secret()? != b.get_secret()? {
+        return Ok(false);
+    }
+    Ok(writable_attributes(a)? == writable_attributes(b)?)
+}
+
+fn writable_attributes(entry: &Entry) -> Result<HashMap<String, String>> {
+    let mut attrs = entry.get_attributes()?;
+    attrs.retain(|key, _| !READ_ONLY_ATTRIBUTES.contains(&key.as_str()));
+    Ok(attrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::credentials_equal;
+    use crate::{CredentialStore, Error, mock};
+
+    #[test]
+    fn test_mock_credentials() {
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let a = store.build("service", "user1", None).unwrap();
+        let b = store.build("service", "user2", None).unwrap();
+        assert!(matches!(credentials_equal(&a, &b), Err(Error::NoEntry)));
+        a.set_password("same").unwrap();
+        b.set_password("same").unwrap();
+        assert!(credentials_equal(&a, &b).unwrap());
+        b.set_password("different").unwrap();
+        assert!(!credentials_equal(&a, &b).unwrap());
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_sample_and_mock_credentials() {
+        use std::collections::HashMap;
+
+        use crate::sample;
+
+        let mock: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let sample: Arc<CredentialStore> = sample::Store::new().unwrap();
+        let a = mock.build("service", "user", None).unwrap();
+        a.set_password("same").unwrap();
+        let b = sample.build("service", "user", None).unwrap();
+        b.set_password("same").unwrap();
+        assert!(credentials_equal(&a, &b).unwrap());
+        b.update_attributes(&HashMap::from([("comment", "extra")]))
+            .unwrap();
+        assert!(!credentials_equal(&a, &b).unwrap());
+        let c = sample.build("other-service", "user", None).unwrap();
+        c.set_password("same").unwrap();
+        c.update_attributes(&HashMap::from([("comment", "extra")]))
+            .unwrap();
+        assert!(credentials_equal(&b, &c).unwrap());
+        c.set_password("different").unwrap();
+        assert!(!credentials_equal(&b, &c).unwrap());
+    }
+}
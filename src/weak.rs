@@ -0,0 +1,384 @@
+/*!
+
+# Weak store
+
+This module provides a credential store wrapper that holds only a weak
+reference to the store it wraps. Entries built by the wrapper don't keep
+the wrapped store alive: each operation on them looks the store up again
+and rebuilds the entry in it. Once the wrapped store has been dropped,
+every operation fails with a [StoreGone](Error::StoreGone) error.
+
+This lets a long-running process cache entries without deciding
+how long the store lives, which stays up to whoever owns the store:
+
+```rust
+# use std::sync::Arc;
+# use keyring_core::{CredentialStore, Error, api::CredentialStoreApi, mock, weak::WeakStore};
+let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+let weak = WeakStore::new(&store).unwrap();
+let entry = weak.build("my-service", "my-user", None).unwrap();
+entry.set_password("my password").unwrap();
+assert_eq!(entry.get_password().unwrap(), "my password");
+drop(store);
+assert!(matches!(entry.get_password(), Err(Error::StoreGone)));
+```
+
+Because entries are rebuilt for each operation, modifiers given when
+an entry is built take effect only on that first build. (They are checked,
+and any credential they create is created, but later operations don't see
+them.) Wrappers returned by operations on the entries, such as
+[get_credential](crate::Entry::get_credential), come from the wrapped store
+and keep it alive as its own entries do.
+ */
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::{CredentialApi, CredentialStoreApi};
+use crate::{
+    Capabilities, Credential, CredentialPersistence, CredentialStore, Entry, Error, Result,
+    SetOutcome,
+};
+
+/// A store that holds its wrapped store only weakly.
+pub struct WeakStore {
+    pub id: String,
+    pub inner: Weak<CredentialStore>,
+}
+
+impl std::fmt::Debug for WeakStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakStore")
+            .field("vendor", &self.vendor())
+            .field("id", &self.id)
+            .field("inner", &self.inner.upgrade())
+            .finish()
+    }
+}
+
+impl WeakStore {
+    /// Wrap a weak reference to `store`.
+    pub fn new(store: &Arc<CredentialStore>) -> Result<Arc<Self>> {
+        Ok(Arc::new(WeakStore {
+            id: format!(
+                "Crate version {}, Instantiated at {}",
+                env!("CARGO_PKG_VERSION"),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_else(|_| Duration::new(0, 0))
+                    .as_secs_f64()
+            ),
+            inner: Arc::downgrade(store),
+        }))
+    }
+
+    /// Get the wrapped store, if it's still alive.
+    pub fn upgrade(&self) -> Result<Arc<CredentialStore>> {
+        self.inner.upgrade().ok_or(Error::StoreGone)
+    }
+}
+
+impl CredentialStoreApi for WeakStore {
+    fn vendor(&self) -> String {
+        String::from("Weak store, https://crates.io/crates/keyring-core")
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The members are those of the wrapped store, if it's still alive.
+    fn member_vendors(&self) -> Vec<String> {
+        match self.inner.upgrade() {
+            Some(store) => store.member_vendors(),
+            None => vec![],
+        }
+    }
+
+    /// Build an entry that holds the wrapped store weakly.
+    ///
+    /// The entry is built once in the wrapped store, with the given
+    /// modifiers, so that errors are reported now. It is rebuilt,
+    /// without modifiers, for each operation.
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        mods: Option<&HashMap<&str, &str>>,
+    ) -> Result<Entry> {
+        self.upgrade()?.build(service, user, mods)?;
+        Ok(Entry::new_with_credential(Arc::new(WeakCred {
+            store: self.inner.clone(),
+            service: service.to_string(),
+            user: user.to_string(),
+        })))
+    }
+
+    /// Search the wrapped store.
+    ///
+    /// The entries found are those of the wrapped store, so they keep it alive.
+    fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
+        self.upgrade()?.search(spec)
+    }
+
+    /// List the credentials in the wrapped store.
+    ///
+    /// The entries are those of the wrapped store, so they keep it alive.
+    fn all(&self) -> Result<Vec<Entry>> {
+        self.upgrade()?.all()
+    }
+
+    /// Count the credentials in the wrapped store.
+    fn count(&self) -> Result<usize> {
+        self.upgrade()?.count()
+    }
+
+    /// Clear the wrapped store.
+    fn clear(&self) -> Result<usize> {
+        self.upgrade()?.clear()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// The persistence of the wrapped store, or `Unspecified` if it's gone.
+    fn persistence(&self) -> CredentialPersistence {
+        match self.inner.upgrade() {
+            Some(store) => store.persistence(),
+            None => CredentialPersistence::Unspecified,
+        }
+    }
+
+    /// The capabilities of the wrapped store, or the defaults if it's gone.
+    fn capabilities(&self) -> Capabilities {
+        match self.inner.upgrade() {
+            Some(store) => store.capabilities(),
+            None => Capabilities::default(),
+        }
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A credential that's rebuilt in a weakly-held store for each operation.
+#[derive(Debug)]
+pub struct WeakCred {
+    pub store: Weak<CredentialStore>,
+    pub service: String,
+    pub user: String,
+}
+
+impl WeakCred {
+    /// Rebuild this credential in the store, if the store is still alive.
+    fn resolve(&self) -> Result<Arc<Credential>> {
+        let store = self.store.upgrade().ok_or(Error::StoreGone)?;
+        Ok(store.build(&self.service, &self.user, None)?.inner)
+    }
+}
+
+impl CredentialApi for WeakCred {
+    /// See the API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.resolve()?.set_secret(secret)
+    }
+
+    /// See the API docs.
+    fn set_secret_reporting(&self, secret: &[u8]) -> Result<SetOutcome> {
+        self.resolve()?.set_secret_reporting(secret)
+    }
+
+    /// See the API docs.
+    fn set_secret_if_changed(&self, secret: &[u8]) -> Result<bool> {
+        self.resolve()?.set_secret_if_changed(secret)
+    }
+
+    /// See the API docs.
+    fn compare_and_set(&self, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        self.resolve()?.compare_and_set(expected, new)
+    }
+
+    /// See the API docs.
+    fn increment_secret_u64(&self, delta: u64) -> Result<u64> {
+        self.resolve()?.increment_secret_u64(delta)
+    }
+
+    /// See the API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        self.resolve()?.get_secret()
+    }
+
+    /// See the API docs.
+    fn with_secret(&self, f: &mut dyn FnMut(&[u8])) -> Result<()> {
+        self.resolve()?.with_secret(f)
+    }
+
+    /// See the API docs.
+    fn has_empty_secret(&self) -> Result<bool> {
+        self.resolve()?.has_empty_secret()
+    }
+
+    /// See the API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        self.resolve()?.get_attributes()
+    }
+
+    /// See the API docs.
+    fn get_secret_and_attributes(&self) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        self.resolve()?.get_secret_and_attributes()
+    }
+
+    /// See the API docs.
+    fn attribute_keys(&self) -> Result<Vec<String>> {
+        self.resolve()?.attribute_keys()
+    }
+
+    /// See the API docs.
+    fn get_created(&self) -> Result<Option<SystemTime>> {
+        self.resolve()?.get_created()
+    }
+
+    /// See the API docs.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        self.resolve()?.get_modified()
+    }
+
+    /// See the API docs.
+    fn update_attributes(&self, attrs: &HashMap<&str, &str>) -> Result<()> {
+        self.resolve()?.update_attributes(attrs)
+    }
+
+    /// See the API docs.
+    fn delete_credential(&self) -> Result<()> {
+        self.resolve()?.delete_credential()
+    }
+
+    /// See the API docs.
+    ///
+    /// This credential still refers to the old service and user afterwards.
+    fn rename(&self, new_service: &str, new_user: &str) -> Result<()> {
+        self.resolve()?.rename(new_service, new_user)
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper comes from the wrapped store, so it keeps the store alive.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let inner = self.resolve()?;
+        match inner.get_credential()? {
+            Some(wrapper) => Ok(Some(wrapper)),
+            None => Ok(Some(inner)),
+        }
+    }
+
+    /// See the API docs.
+    ///
+    /// The wrapper comes from the wrapped store, so it keeps the store alive.
+    fn get_primary_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let inner = self.resolve()?;
+        match inner.get_primary_credential()? {
+            Some(wrapper) => Ok(Some(wrapper)),
+            None => Ok(Some(inner)),
+        }
+    }
+
+    /// See the API docs.
+    fn exists(&self) -> Result<bool> {
+        self.resolve()?.exists()
+    }
+
+    /// See the API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::WeakStore;
+    use crate::{CredentialPersistence, CredentialStore, Error, mock};
+
+    #[test]
+    fn test_operations_while_alive() {
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let weak: Arc<CredentialStore> = WeakStore::new(&store).unwrap();
+        let entry = weak.build("service", "user", None).unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        entry.set_password("password").unwrap();
+        assert_eq!(entry.get_password().unwrap(), "password");
+        let direct = store.build("service", "user", None).unwrap();
+        assert_eq!(direct.get_password().unwrap(), "password");
+        assert_eq!(weak.search(&HashMap::new()).unwrap().len(), 1);
+        assert_eq!(weak.member_vendors(), vec![store.vendor()]);
+        assert!(matches!(
+            weak.persistence(),
+            CredentialPersistence::ProcessOnly
+        ));
+        entry.delete_credential().unwrap();
+        assert!(matches!(direct.get_password(), Err(Error::NoEntry)));
+    }
+
+    #[test]
+    fn test_store_gone() {
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let weak: Arc<CredentialStore> = WeakStore::new(&store).unwrap();
+        let entry = weak.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        drop(store);
+        assert!(matches!(entry.get_password(), Err(Error::StoreGone)));
+        assert!(matches!(entry.set_password("new"), Err(Error::StoreGone)));
+        assert!(matches!(entry.delete_credential(), Err(Error::StoreGone)));
+        assert!(matches!(entry.get_credential(), Err(Error::StoreGone)));
+        assert!(matches!(
+            weak.build("service", "user", None),
+            Err(Error::StoreGone)
+        ));
+        assert!(matches!(
+            weak.search(&HashMap::new()),
+            Err(Error::StoreGone)
+        ));
+        assert!(weak.member_vendors().is_empty());
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn test_rename() {
+        let store: Arc<CredentialStore> = crate::sample::Store::new().unwrap();
+        let weak: Arc<CredentialStore> = WeakStore::new(&store).unwrap();
+        let entry = weak.build("service", "old", None).unwrap();
+        entry.set_password("password").unwrap();
+        entry.rename("service", "new").unwrap();
+        assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+        let moved = weak.build("service", "new", None).unwrap();
+        assert_eq!(moved.get_password().unwrap(), "password");
+    }
+
+    #[test]
+    fn test_store_wide_operations() {
+        let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+        let weak: Arc<CredentialStore> = WeakStore::new(&store).unwrap();
+        let entry = weak.build("service", "user", None).unwrap();
+        entry.set_password("password").unwrap();
+        assert_eq!(weak.count().unwrap(), 1);
+        assert_eq!(weak.all().unwrap().len(), 1);
+        assert_eq!(weak.clear().unwrap(), 1);
+        assert_eq!(store.count().unwrap(), 0);
+        drop(store);
+        assert!(matches!(weak.count(), Err(Error::StoreGone)));
+        assert!(matches!(weak.all(), Err(Error::StoreGone)));
+        assert!(matches!(weak.clear(), Err(Error::StoreGone)));
+    }
+}
@@ -0,0 +1,424 @@
+//! Tests of the crate-level default store functions.
+//!
+//! These set and unset the global default store, so they are kept
+//! out of the unit tests and run one at a time.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use keyring_core::modifiers::DefaultModifiersStore;
+use keyring_core::{CredentialPersistence, CredentialStore, Entry, Error, mock};
+
+static DEFAULT_STORE_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock() -> std::sync::MutexGuard<'static, ()> {
+    DEFAULT_STORE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[test]
+fn test_persistence_without_default() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    assert!(matches!(
+        keyring_core::default_store_persistence(),
+        Err(Error::NoDefaultStore)
+    ));
+}
+
+#[test]
+fn test_persistence_of_mock_default() {
+    let _guard = lock();
+    keyring_core::set_default_store(mock::Store::new().unwrap());
+    assert!(matches!(
+        keyring_core::default_store_persistence(),
+        Ok(CredentialPersistence::ProcessOnly)
+    ));
+    keyring_core::unset_default_store();
+}
+
+#[cfg(feature = "sample")]
+#[test]
+fn test_persistence_of_backed_sample_default() {
+    use keyring_core::sample;
+
+    let _guard = lock();
+    let path = std::env::temp_dir().join(format!(
+        "keyring-core-default-store-{}.ron",
+        std::process::id()
+    ));
+    let store = sample::Store::new_with_backing(path.to_str().unwrap()).unwrap();
+    keyring_core::set_default_store(store);
+    assert!(matches!(
+        keyring_core::default_store_persistence(),
+        Ok(CredentialPersistence::UntilDelete)
+    ));
+    keyring_core::unset_default_store();
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_fallback_default_store() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    // neither set
+    assert!(keyring_core::get_default_store().is_none());
+    assert!(matches!(
+        Entry::new("service", "user"),
+        Err(Error::NoDefaultStore)
+    ));
+    // fallback only
+    let fallback = mock::Store::new().unwrap();
+    keyring_core::set_fallback_default_store(fallback.clone());
+    assert_eq!(keyring_core::get_default_store().unwrap().id(), fallback.id);
+    let entry = Entry::new("service", "user").unwrap();
+    entry.set_password("fallback").unwrap();
+    assert_eq!(Entry::search(&HashMap::new()).unwrap().len(), 1);
+    // explicit default takes precedence
+    let primary = mock::Store::new().unwrap();
+    keyring_core::set_default_store(primary.clone());
+    assert_eq!(keyring_core::get_default_store().unwrap().id(), primary.id);
+    let entry = Entry::new("service", "user").unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    let found = Entry::search(&HashMap::new()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(matches!(found[0].get_password(), Err(Error::NoEntry)));
+    // unsetting the explicit default reveals the fallback again
+    keyring_core::unset_default_store();
+    let entry = Entry::new("service", "user").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "fallback");
+    assert!(keyring_core::unset_fallback_default_store().is_some());
+    assert!(keyring_core::get_default_store().is_none());
+}
+
+#[test]
+fn test_installed_stores() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    assert!(keyring_core::installed_stores().is_empty());
+    let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let vendor = store.vendor();
+    keyring_core::set_fallback_default_store(store);
+    assert_eq!(
+        keyring_core::installed_stores(),
+        vec![("fallback".to_string(), vendor.clone())]
+    );
+    let wrapper: Arc<CredentialStore> =
+        DefaultModifiersStore::new(mock::Store::new().unwrap(), &HashMap::new()).unwrap();
+    let wrapper_vendor = wrapper.vendor();
+    keyring_core::set_default_store(wrapper);
+    assert_eq!(
+        keyring_core::installed_stores(),
+        vec![
+            ("default".to_string(), wrapper_vendor),
+            ("fallback".to_string(), vendor)
+        ]
+    );
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+}
+
+#[test]
+fn test_entry_keeps_its_store_after_default_swap() {
+    let _guard = lock();
+    keyring_core::unset_fallback_default_store();
+    keyring_core::set_default_store(mock::Store::new().unwrap());
+    let original = Entry::new("service", "user").unwrap();
+    original.set_password("original").unwrap();
+    keyring_core::set_default_store(mock::Store::new().unwrap());
+    let swapped = Entry::new("service", "user").unwrap();
+    assert!(matches!(swapped.get_password(), Err(Error::NoEntry)));
+    assert_eq!(original.get_password().unwrap(), "original");
+    original.set_password("still original").unwrap();
+    assert!(matches!(swapped.get_password(), Err(Error::NoEntry)));
+    keyring_core::unset_default_store();
+    assert_eq!(original.get_password().unwrap(), "still original");
+    original.delete_credential().unwrap();
+}
+
+#[cfg(feature = "sample")]
+#[test]
+fn test_sample_entry_keeps_its_store_after_default_swap() {
+    use keyring_core::sample;
+
+    let _guard = lock();
+    keyring_core::unset_fallback_default_store();
+    let first: Arc<CredentialStore> = sample::Store::new().unwrap();
+    keyring_core::set_default_store(first.clone());
+    let entry = Entry::new("service", "user").unwrap();
+    keyring_core::set_default_store(sample::Store::new().unwrap());
+    entry.set_password("first store").unwrap();
+    let spec = HashMap::from([("service", "service")]);
+    assert_eq!(first.search(&spec).unwrap().len(), 1);
+    let found = keyring_core::get_default_store().unwrap().search(&spec);
+    assert!(found.unwrap().is_empty());
+    keyring_core::unset_default_store();
+}
+
+#[test]
+fn test_entries_in_a_store_without_default() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let entry = Entry::new_in(&store, "service", "user").unwrap();
+    entry.set_password("in store").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "in store");
+    let mods = HashMap::from([("bad", "modifier")]);
+    assert!(matches!(
+        Entry::new_in_with_modifiers(&store, "service", "user", &mods),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    assert!(matches!(
+        Entry::new("service", "user"),
+        Err(Error::NoDefaultStore)
+    ));
+    entry.delete_credential().unwrap();
+}
+
+#[cfg(feature = "sample")]
+#[test]
+fn test_sample_entries_in_a_store_without_default() {
+    use keyring_core::sample;
+
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let store: Arc<CredentialStore> = sample::Store::new().unwrap();
+    let entry = Entry::new_in(&store, "service", "user").unwrap();
+    entry.set_password("first").unwrap();
+    let mods = HashMap::from([("force-create", "second")]);
+    Entry::new_in_with_modifiers(&store, "service", "user", &mods).unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::Ambiguous(_))));
+    let spec = HashMap::from([("service", "^service$")]);
+    assert_eq!(Entry::search_in(&store, &spec).unwrap().len(), 2);
+    assert!(matches!(Entry::search(&spec), Err(Error::NoDefaultStore)));
+}
+
+#[test]
+fn test_thread_default_stores_do_not_interfere() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let handles: Vec<_> = ["first", "second"]
+        .into_iter()
+        .map(|name| {
+            std::thread::spawn(move || {
+                let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+                keyring_core::set_thread_default_store(store.clone());
+                let entry = Entry::new(name, "user").unwrap();
+                entry.set_password(name).unwrap();
+                let found = Entry::search(&HashMap::new()).unwrap();
+                assert_eq!(found.len(), 1);
+                assert_eq!(found[0].get_password().unwrap(), name);
+                assert!(Arc::ptr_eq(
+                    &keyring_core::get_default_store().unwrap(),
+                    &store
+                ));
+                let unset = keyring_core::unset_thread_default_store().unwrap();
+                assert!(Arc::ptr_eq(&unset, &store));
+                assert!(matches!(
+                    Entry::new(name, "user"),
+                    Err(Error::NoDefaultStore)
+                ));
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(keyring_core::get_thread_default_store().is_none());
+}
+
+#[test]
+fn test_thread_default_store_takes_precedence() {
+    let _guard = lock();
+    let global: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let local: Arc<CredentialStore> = mock::Store::new().unwrap();
+    keyring_core::set_default_store(global.clone());
+    keyring_core::set_thread_default_store(local.clone());
+    Entry::new("service", "user")
+        .unwrap()
+        .set_password("local")
+        .unwrap();
+    assert!(matches!(
+        global
+            .build("service", "user", None)
+            .unwrap()
+            .get_password(),
+        Err(Error::NoEntry)
+    ));
+    assert_eq!(
+        local
+            .build("service", "user", None)
+            .unwrap()
+            .get_password()
+            .unwrap(),
+        "local"
+    );
+    let other = global.clone();
+    std::thread::spawn(move || {
+        assert!(keyring_core::get_thread_default_store().is_none());
+        assert!(Arc::ptr_eq(
+            &keyring_core::get_default_store().unwrap(),
+            &other
+        ));
+    })
+    .join()
+    .unwrap();
+    keyring_core::unset_thread_default_store();
+    assert!(Arc::ptr_eq(
+        &keyring_core::get_default_store().unwrap(),
+        &global
+    ));
+    keyring_core::unset_default_store();
+}
+
+#[test]
+fn test_get_or_init_default_store_runs_init_once() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let handles: Vec<_> = (0..10)
+        .map(|_| {
+            let calls = calls.clone();
+            std::thread::spawn(move || {
+                keyring_core::get_or_init_default_store(|| {
+                    calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    let store: Arc<CredentialStore> = mock::Store::new()?;
+                    Ok(store)
+                })
+                .unwrap()
+            })
+        })
+        .collect();
+    let stores: Vec<Arc<CredentialStore>> =
+        handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    let default = keyring_core::get_default_store().unwrap();
+    assert!(stores.iter().all(|store| Arc::ptr_eq(store, &default)));
+    keyring_core::unset_default_store();
+}
+
+#[test]
+fn test_get_or_init_default_store_failure() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let result = keyring_core::get_or_init_default_store(|| {
+        Err(Error::NoStorageAccess(Box::from("locked")))
+    });
+    assert!(matches!(result, Err(Error::NoStorageAccess(_))));
+    assert!(keyring_core::get_default_store().is_none());
+    let store: Arc<CredentialStore> = mock::Store::new().unwrap();
+    keyring_core::set_default_store(store.clone());
+    let got =
+        keyring_core::get_or_init_default_store(|| panic!("init should not be called")).unwrap();
+    assert!(Arc::ptr_eq(&got, &store));
+    keyring_core::unset_default_store();
+}
+
+#[test]
+fn test_recovers_from_poisoned_default_store() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    // a panic while initializing runs under the lock, so it poisons it
+    let result = std::thread::spawn(|| {
+        keyring_core::get_or_init_default_store(|| panic!("store failed to build"))
+    })
+    .join();
+    assert!(result.is_err());
+    assert!(keyring_core::get_default_store().is_none());
+    keyring_core::set_default_store(mock::Store::new().unwrap());
+    let entry = Entry::new("service", "user").unwrap();
+    entry.set_password("password").unwrap();
+    assert_eq!(Entry::search(&HashMap::new()).unwrap().len(), 1);
+    assert!(
+        keyring_core::try_get_default_store(std::time::Duration::from_millis(20))
+            .unwrap()
+            .is_some()
+    );
+    assert!(keyring_core::unset_default_store().is_some());
+}
+
+fn is_default(store: &Arc<CredentialStore>) -> bool {
+    keyring_core::get_default_store().is_some_and(|default| Arc::ptr_eq(&default, store))
+}
+
+#[test]
+fn test_push_default_store_nested() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let base: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let first: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let second: Arc<CredentialStore> = mock::Store::new().unwrap();
+    keyring_core::set_default_store(base.clone());
+    {
+        let _outer = keyring_core::push_default_store(first.clone());
+        assert!(is_default(&first));
+        {
+            let _inner = keyring_core::push_default_store(second.clone());
+            assert!(is_default(&second));
+            Entry::new("service", "user")
+                .unwrap()
+                .set_password("second")
+                .unwrap();
+        }
+        assert!(is_default(&first));
+        assert!(matches!(
+            Entry::new("service", "user").unwrap().get_password(),
+            Err(Error::NoEntry)
+        ));
+    }
+    assert!(is_default(&base));
+    assert!(keyring_core::unset_default_store().is_some());
+    assert!(keyring_core::get_default_store().is_none());
+}
+
+#[test]
+fn test_push_default_store_out_of_order() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    keyring_core::unset_fallback_default_store();
+    let base: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let first: Arc<CredentialStore> = mock::Store::new().unwrap();
+    let second: Arc<CredentialStore> = mock::Store::new().unwrap();
+    keyring_core::set_default_store(base.clone());
+    let outer = keyring_core::push_default_store(first.clone());
+    let inner = keyring_core::push_default_store(second.clone());
+    drop(outer);
+    // dropping the outer guard releases both pushed stores
+    assert!(is_default(&base));
+    drop(inner);
+    // the inner guard restores what was the default when it was pushed
+    assert!(is_default(&first));
+    assert!(keyring_core::unset_default_store().is_some());
+    assert!(is_default(&base));
+    assert!(keyring_core::unset_default_store().is_some());
+    assert!(keyring_core::get_default_store().is_none());
+}
+
+#[test]
+fn test_try_get_default_store_times_out() {
+    let _guard = lock();
+    keyring_core::unset_default_store();
+    // the lock on the default store is held while init runs
+    keyring_core::get_or_init_default_store(|| {
+        let attempt = std::thread::spawn(|| {
+            keyring_core::try_get_default_store(std::time::Duration::from_millis(20))
+        });
+        assert!(matches!(attempt.join().unwrap(), Err(Error::Timeout(_))));
+        let store: Arc<CredentialStore> = mock::Store::new()?;
+        Ok(store)
+    })
+    .unwrap();
+    let store = keyring_core::try_get_default_store(std::time::Duration::from_millis(20));
+    assert!(store.unwrap().is_some());
+    keyring_core::unset_default_store();
+}
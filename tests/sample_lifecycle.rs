@@ -0,0 +1,85 @@
+//! Tests of the sample store's lifecycle events.
+//!
+//! These install a global logger, so they are kept out of the unit tests.
+#![cfg(feature = "sample")]
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use keyring_core::sample::{Store, store::LIFECYCLE_TARGET};
+
+static EVENTS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == LIFECYCLE_TARGET
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let event = (record.level(), record.args().to_string());
+            EVENTS.lock().unwrap().push(event);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn take_events() -> Vec<(log::Level, String)> {
+    std::mem::take(&mut *EVENTS.lock().unwrap())
+}
+
+fn event_name(message: &str) -> &str {
+    message
+        .strip_prefix("event=")
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap()
+}
+
+#[test]
+fn test_lifecycle_events() {
+    log::set_boxed_logger(Box::new(Capture)).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+    let dir = std::env::temp_dir().join(format!("keyring-lifecycle-{}", std::process::id()));
+    let path = dir.join("store.ron");
+    let path = path.to_str().unwrap();
+    let config = HashMap::from([("backing-file", path), ("create-dirs", "true")]);
+
+    let store = Store::new_with_configuration(&config).unwrap();
+    let id = store.id.clone();
+    let cred = keyring_core::api::CredentialStoreApi::build(store.as_ref(), "s", "u", None);
+    cred.unwrap().set_password("pw").unwrap();
+    store.save().unwrap();
+    drop(store);
+    let events = take_events();
+    let names: Vec<&str> = events.iter().map(|(_, m)| event_name(m)).collect();
+    assert_eq!(
+        names,
+        vec!["created", "backing-loaded", "saved", "saved", "dropped"]
+    );
+    for (level, message) in events.iter() {
+        assert_eq!(*level, log::Level::Debug);
+        assert!(message.contains(&format!("id={id:?}")), "{message}");
+        assert!(message.contains(&format!("backing={:?}", Some(path))));
+        assert!(message.contains("vendor=\"Sample store"));
+    }
+    assert!(events[0].1.ends_with("count=0"));
+    assert!(events[2].1.ends_with("count=1"));
+
+    let store = Store::new_with_backing(path).unwrap();
+    assert!(take_events()[1].1.ends_with("count=1"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    drop(store);
+    let events = take_events();
+    let names: Vec<&str> = events.iter().map(|(_, m)| event_name(m)).collect();
+    assert_eq!(names, vec!["save-failed", "dropped"]);
+    assert_eq!(events[0].0, log::Level::Error);
+    assert!(events[0].1.contains(" error="));
+
+    drop(Store::new().unwrap());
+    let events = take_events();
+    let names: Vec<&str> = events.iter().map(|(_, m)| event_name(m)).collect();
+    assert_eq!(names, vec!["created", "dropped"]);
+    assert!(events[0].1.contains("backing=None"));
+}